@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+
+use crate::{
+    budget::Spending, decimal, value::Value, Filter, JQErr, JQStream, Sanitized, SanitizedJQStream,
+    Token,
+};
+
+/// A struct for handling the `add` and `add(generator)` jq queries.
+/// The values being added are pulled one at a time, so only the running
+/// total is ever held in memory, and the active budget is spent on every
+/// token of them.
+pub struct Add<Stream, Gen>
+where
+    Stream: JQStream,
+    Gen: Filter,
+{
+    finished: bool,
+    stream: Sanitized<Spending<Stream>>,
+    generator: Option<Gen>,
+    buf: VecDeque<Token>,
+}
+
+impl<Stream, Gen> Add<Stream, Gen>
+where
+    Stream: JQStream,
+    Gen: Filter,
+{
+    pub(crate) fn new(stream: Stream, generator: Option<Gen>) -> Self {
+        Self {
+            finished: false,
+            stream: Spending::new(stream).sanitize(),
+            generator,
+            buf: VecDeque::new(),
+        }
+    }
+
+    fn sum_next_input(&mut self) -> Option<Result<Value, JQErr>> {
+        let first = match self.stream.next()? {
+            Err(err) => return Some(Err(err)),
+            Ok(token) => token,
+        };
+
+        let mut total = Value::Null;
+        match &mut self.generator {
+            None => {
+                let in_object = match first {
+                    Token::ArrayStart => false,
+                    Token::ObjectStart => true,
+                    other => {
+                        return Some(Value::parse(other, &mut self.stream).and_then(|value| {
+                            Err(JQErr::StreamOperationFailed(
+                                format!("Cannot iterate over {}", value.describe()).into(),
+                            ))
+                        }))
+                    }
+                };
+
+                while let Some(member) = Value::next_member(&mut self.stream, in_object) {
+                    total = match member.and_then(|member| add(total, member)) {
+                        Err(err) => return Some(Err(err)),
+                        Ok(total) => total,
+                    };
+                }
+            }
+            Some(generator) => {
+                let input = match Value::parse(first, &mut self.stream) {
+                    Err(err) => return Some(Err(err)),
+                    Ok(input) => input,
+                };
+
+                let mut values = Spending::new(generator.apply(input.into_stream())).sanitize();
+                while let Some(value) = Value::next_from(&mut values) {
+                    total = match value.and_then(|value| add(total, value)) {
+                        Err(err) => return Some(Err(err)),
+                        Ok(total) => total,
+                    };
+                }
+            }
+        }
+
+        Some(Ok(total))
+    }
+}
+
+impl<Stream, Gen> Iterator for Add<Stream, Gen>
+where
+    Stream: JQStream,
+    Gen: Filter,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.buf.pop_front() {
+            return Some(Ok(token));
+        }
+
+        if self.finished {
+            return None;
+        }
+
+        match self.sum_next_input()? {
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+            Ok(total) => {
+                total.push_tokens(&mut self.buf);
+                self.buf.pop_front().map(Ok)
+            }
+        }
+    }
+}
+
+impl<Stream, Gen> SanitizedJQStream for Add<Stream, Gen>
+where
+    Stream: JQStream,
+    Gen: Filter,
+{
+}
+
+/// jq's `+` operator.
+pub(crate) fn add(left: Value, right: Value) -> Result<Value, JQErr> {
+    match (left, right) {
+        (Value::Null, other) | (other, Value::Null) => Ok(other),
+        (left @ (Value::Number(_) | Value::ParsedNumber(_)), right) if right.as_f64().is_some() => {
+            if let (Value::Number(left), Value::Number(right)) = (&left, &right) {
+                if let Some(sum) = decimal::add(left, right) {
+                    return Ok(Value::Number(sum));
+                }
+            }
+            Ok(Value::ParsedNumber(
+                left.as_f64().unwrap_or(f64::NAN) + right.as_f64().unwrap_or(f64::NAN),
+            ))
+        }
+        (Value::String(left), Value::String(right)) => {
+            Ok(Value::String(format!("{left}{right}").into()))
+        }
+        (Value::Array(mut left), Value::Array(right)) => {
+            left.extend(right);
+            Ok(Value::Array(left))
+        }
+        (Value::Object(mut left), Value::Object(right)) => {
+            for (key, value) in right {
+                match left.iter_mut().find(|(existing, _)| *existing == key) {
+                    Some((_, existing)) => *existing = value,
+                    None => left.push((key, value)),
+                }
+            }
+            Ok(Value::Object(left))
+        }
+        (left, right) => Err(JQErr::StreamOperationFailed(
+            format!(
+                "{} and {} cannot be added",
+                left.describe(),
+                right.describe()
+            )
+            .into(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_support::values, SanitizedJQStream, ValueTokens};
+
+    #[test]
+    fn adds_the_items_of_each_input() {
+        assert_eq!(
+            values(r#"[1, 2, 3] ["a", "b"] [[1], [2]] [] [null, 1]"#)
+                .add()
+                .to_string()
+                .unwrap(),
+            "6\n\"ab\"\n[1,2]\nnull\n1\n"
+        );
+        assert_eq!(
+            values(r#"[{"a": 1, "b": 1}, {"a": 2}]"#)
+                .add()
+                .to_string()
+                .unwrap(),
+            "{\"a\":2,\"b\":1}\n"
+        );
+    }
+
+    #[test]
+    fn adds_the_outputs_of_a_generator() {
+        assert_eq!(
+            values("null")
+                .add_of(|value: ValueTokens| value.range(4.0))
+                .to_string()
+                .unwrap(),
+            "6\n"
+        );
+    }
+
+    #[test]
+    fn rejects_values_which_cannot_be_added() {
+        assert!(values(r#"[1, "a"]"#).add().to_string().is_err());
+    }
+}
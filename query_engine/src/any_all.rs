@@ -0,0 +1,197 @@
+use crate::{
+    budget::Spending, value::Value, Filter, JQErr, JQStream, Sanitized, SanitizedJQStream, Token,
+};
+
+/// A struct for handling the `any`, `any(condition)`,
+/// `any(generator; condition)` jq queries and their `all` counterparts.
+///
+/// Evaluation stops as soon as the answer is known: once `any` finds a
+/// true value (or `all` finds a false one), the rest of the input is
+/// skipped over without being materialized and the generator is dropped.
+/// The active budget is spent on every token of the values checked and
+/// skipped.
+pub struct AnyAll<const ALL: bool, Stream, Gen, Cond>
+where
+    Stream: JQStream,
+    Gen: Filter,
+    Cond: Filter,
+{
+    finished: bool,
+    stream: Sanitized<Spending<Stream>>,
+    generator: Option<Gen>,
+    condition: Cond,
+}
+
+impl<const ALL: bool, Stream, Gen, Cond> AnyAll<ALL, Stream, Gen, Cond>
+where
+    Stream: JQStream,
+    Gen: Filter,
+    Cond: Filter,
+{
+    pub(crate) fn new(stream: Stream, generator: Option<Gen>, condition: Cond) -> Self {
+        Self {
+            finished: false,
+            stream: Spending::new(stream).sanitize(),
+            generator,
+            condition,
+        }
+    }
+
+    /// Checks whether `value` decides the result, i.e. whether the
+    /// condition produces a true value for `any` or a false value for
+    /// `all`.
+    fn is_decisive(condition: &mut Cond, value: Value) -> Result<bool, JQErr> {
+        let mut outputs = Spending::new(condition.apply(value.into_stream())).sanitize();
+        while let Some(output) = Value::next_from(&mut outputs) {
+            if output?.is_truthy() != ALL {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn check_next_input(&mut self) -> Option<Result<bool, JQErr>> {
+        let first = match self.stream.next()? {
+            Err(err) => return Some(Err(err)),
+            Ok(token) => token,
+        };
+
+        match &mut self.generator {
+            None => {
+                let in_object = match first {
+                    Token::ArrayStart => false,
+                    Token::ObjectStart => true,
+                    other => {
+                        return Some(Value::parse(other, &mut self.stream).and_then(|value| {
+                            Err(JQErr::StreamOperationFailed(
+                                format!("Cannot iterate over {}", value.describe()).into(),
+                            ))
+                        }))
+                    }
+                };
+
+                while let Some(member) = Value::next_member(&mut self.stream, in_object) {
+                    match member.and_then(|member| Self::is_decisive(&mut self.condition, member)) {
+                        Err(err) => return Some(Err(err)),
+                        Ok(false) => {}
+                        Ok(true) => return Some(self.stream.skip_value().map(|_| !ALL)),
+                    }
+                }
+            }
+            Some(generator) => {
+                let input = match Value::parse(first, &mut self.stream) {
+                    Err(err) => return Some(Err(err)),
+                    Ok(input) => input,
+                };
+
+                let mut values = Spending::new(generator.apply(input.into_stream())).sanitize();
+                while let Some(value) = Value::next_from(&mut values) {
+                    match value.and_then(|value| Self::is_decisive(&mut self.condition, value)) {
+                        Err(err) => return Some(Err(err)),
+                        Ok(false) => {}
+                        Ok(true) => return Some(Ok(!ALL)),
+                    }
+                }
+            }
+        }
+
+        Some(Ok(ALL))
+    }
+}
+
+impl<const ALL: bool, Stream, Gen, Cond> Iterator for AnyAll<ALL, Stream, Gen, Cond>
+where
+    Stream: JQStream,
+    Gen: Filter,
+    Cond: Filter,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        match self.check_next_input()? {
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+            Ok(true) => Some(Ok(Token::True)),
+            Ok(false) => Some(Ok(Token::False)),
+        }
+    }
+}
+
+impl<const ALL: bool, Stream, Gen, Cond> SanitizedJQStream for AnyAll<ALL, Stream, Gen, Cond>
+where
+    Stream: JQStream,
+    Gen: Filter,
+    Cond: Filter,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_support::values, SanitizedJQStream, ValueTokens};
+
+    #[test]
+    fn checks_the_truthiness_of_each_item() {
+        assert_eq!(
+            values("[true, false] [null, 1] []")
+                .json_any()
+                .to_string()
+                .unwrap(),
+            "true\ntrue\nfalse\n"
+        );
+        assert_eq!(
+            values("[true, false] [0, \"\"] []")
+                .json_all()
+                .to_string()
+                .unwrap(),
+            "false\ntrue\ntrue\n"
+        );
+    }
+
+    #[test]
+    fn checks_a_condition_against_each_item() {
+        assert_eq!(
+            values("[0, 1]")
+                .json_any_by(|value: ValueTokens| value.isnormal())
+                .to_string()
+                .unwrap(),
+            "true\n"
+        );
+        assert_eq!(
+            values("[0, 1]")
+                .json_all_by(|value: ValueTokens| value.isnormal())
+                .to_string()
+                .unwrap(),
+            "false\n"
+        );
+    }
+
+    #[test]
+    fn checks_a_condition_against_each_output_of_a_generator() {
+        assert_eq!(
+            values("null")
+                .json_any_of(
+                    |value: ValueTokens| value.range_from(1.0, 3.0),
+                    |value: ValueTokens| value.isnormal()
+                )
+                .to_string()
+                .unwrap(),
+            "true\n"
+        );
+        assert_eq!(
+            values("null")
+                .json_all_of(
+                    |value: ValueTokens| value.range(3.0),
+                    |value: ValueTokens| value.isnormal()
+                )
+                .to_string()
+                .unwrap(),
+            "false\n"
+        );
+    }
+}
@@ -37,7 +37,7 @@ where
 
     fn drop_queue(&mut self) {
         self.queue.clear();
-        self.queue.shrink_to(self.index.abs() as usize);
+        self.queue.shrink_to(self.index.unsigned_abs());
     }
 }
 
@@ -112,9 +112,7 @@ where
                                     if self.index == 0 {
                                         return Some(Ok(token_kind));
                                     } else if self.index < 0 {
-                                        let mut value_pieces = Vec::new();
-                                        value_pieces.push(token_kind);
-                                        self.queue.push_back(value_pieces);
+                                        self.queue.push_back(vec![token_kind]);
                                     }
 
                                     continue;
@@ -158,7 +156,7 @@ where
                             if EMIT_ERRS {
                                 self.finish();
                                 return Some(Err(JQErr::StreamOperationFailed(
-                                    format!("Cannot index string with number").into(),
+                                    "Cannot index string with number".to_string().into(),
                                 )));
                             } else {
                                 self.matching = false;
@@ -169,7 +167,7 @@ where
                             if EMIT_ERRS {
                                 self.finish();
                                 return Some(Err(JQErr::StreamOperationFailed(
-                                    format!("Cannot index number with number").into(),
+                                    "Cannot index number with number".to_string().into(),
                                 )));
                             } else {
                                 self.matching = false;
@@ -194,7 +192,7 @@ where
                     Some(Ok(mut token_kind)) => {
                         match token_kind {
                             Token::Comma => {
-                                let index_abs = self.index.abs() as usize;
+                                let index_abs = self.index.unsigned_abs();
                                 if self.index < 0 {
                                     self.matching = true;
 
@@ -203,16 +201,14 @@ where
                                     }
 
                                     self.queue.push_back(Vec::new());
-                                } else if index_abs == (index + 1) {
-                                    self.matching = true;
                                 } else {
-                                    self.matching = false;
+                                    self.matching = index_abs == (index + 1);
                                 }
                             }
                             Token::ArrayEnd => {
                                 self.matching = false;
 
-                                let target_index_abs = self.index.abs() as usize;
+                                let target_index_abs = self.index.unsigned_abs();
                                 if self.index < 0 {
                                     let queue_len = self.queue.len();
                                     if queue_len < target_index_abs {
@@ -220,7 +216,7 @@ where
                                         return Some(Ok(Token::Null));
                                     }
 
-                                    match self.queue.get_mut(queue_len - target_index_abs).take() {
+                                    match self.queue.get_mut(queue_len - target_index_abs) {
                                         None => {
                                             self.drop_queue();
                                             return Some(Ok(Token::Null));
@@ -0,0 +1,228 @@
+use std::{iter::Peekable, rc::Rc};
+
+use crate::{raw::LimitCheck, value::Value, JQErr, Location, Token};
+
+/// How binary decoders represent byte strings, which JSON has no type for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryBytes {
+    /// A string holding the standard, padded base64 encoding of the bytes.
+    Base64,
+    /// An array holding each byte as a number.
+    Array,
+}
+
+/// How binary decoders represent CBOR tags and MessagePack extension
+/// types, which JSON has no way to attach to a value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryTags {
+    /// Wraps the value in an object, as `{"tag": 1, "value": ...}`.
+    Surface,
+    /// Produces the value on its own.
+    Drop,
+}
+
+/// How deeply the binary decoders nest arrays, maps and tags unless their
+/// [`ParseLimits`] say otherwise. Items are decoded recursively, so unlike
+/// [`RawTokenStream`](crate::RawTokenStream), nesting is never unlimited.
+const MAX_DEPTH: usize = 256;
+
+/// Reads bytes for the binary decoders, keeping track of the offset of the
+/// next byte for error messages.
+pub(crate) struct ByteReader<Bytes>
+where
+    Bytes: Iterator<Item = u8>,
+{
+    bytes: Peekable<Bytes>,
+    offset: usize,
+    format: &'static str,
+    pub(crate) limits: LimitCheck,
+    /// How many arrays, maps and tags the next item is inside of.
+    depth: usize,
+    /// How many items of the top-level value being decoded have been read,
+    /// each of which becomes at least one token.
+    items: usize,
+}
+
+impl<Bytes> ByteReader<Bytes>
+where
+    Bytes: Iterator<Item = u8>,
+{
+    pub(crate) fn new(bytes: Bytes, format: &'static str) -> Self {
+        Self {
+            bytes: bytes.peekable(),
+            offset: 0,
+            format,
+            limits: LimitCheck::default(),
+            depth: 0,
+            items: 0,
+        }
+    }
+
+    /// Where the byte which was just read is, for the limit errors. Binary
+    /// input has no lines, so the column is the offset of the byte.
+    fn location(&self) -> Location {
+        Location::new(0, self.offset.saturating_sub(1))
+    }
+
+    /// Fails if a token of the item which was just read goes past the
+    /// limits, with the error at the end of that item.
+    pub(crate) fn check(&mut self, token: &Token) -> Result<(), JQErr> {
+        let location = self.location();
+        self.limits.check(token, || location)
+    }
+
+    /// Starts decoding a top-level value.
+    pub(crate) fn begin(&mut self) {
+        self.items = 0;
+    }
+
+    /// Counts an item whose initial byte was just read, failing if the
+    /// value it's part of already has more tokens than the limits allow.
+    pub(crate) fn count_item(&mut self) -> Result<(), JQErr> {
+        self.items += 1;
+        let location = self.location();
+        self.limits.reserve(self.items, || location)
+    }
+
+    /// Fails if a text string of `len` bytes is longer than the limits
+    /// allow, before any of it is read.
+    pub(crate) fn check_text_len(&self, len: u64) -> Result<(), JQErr> {
+        match self.limits.limits.max_string_len {
+            Some(max) if len > max as u64 => Err(JQErr::StringLimitExceeded(self.location())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails if a byte string of `len` bytes, represented as chosen by
+    /// `mode`, goes past the limits, before any of it is read.
+    pub(crate) fn check_bytes_len(&self, len: u64, mode: BinaryBytes) -> Result<(), JQErr> {
+        match mode {
+            BinaryBytes::Base64 => self.check_text_len(len.div_ceil(3).saturating_mul(4)),
+            BinaryBytes::Array => {
+                let len = usize::try_from(len).unwrap_or(usize::MAX);
+                let location = self.location();
+                self.limits
+                    .reserve(self.items.saturating_add(len), || location)
+            }
+        }
+    }
+
+    /// Enters an array, map or tag whose initial byte was just read,
+    /// failing if it's nested deeper than the limits allow.
+    pub(crate) fn enter(&mut self) -> Result<(), JQErr> {
+        let max = self.limits.limits.max_depth.unwrap_or(MAX_DEPTH);
+        if self.depth >= max {
+            return Err(JQErr::DepthLimitExceeded(self.location()));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    pub(crate) fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// An error about the byte which was just read.
+    pub(crate) fn invalid(&self, msg: impl Into<Rc<str>>) -> JQErr {
+        JQErr::InvalidBinary {
+            format: self.format,
+            offset: self.offset.saturating_sub(1),
+            msg: msg.into(),
+        }
+    }
+
+    pub(crate) fn is_empty(&mut self) -> bool {
+        self.bytes.peek().is_none()
+    }
+
+    pub(crate) fn byte(&mut self) -> Result<u8, JQErr> {
+        let byte = self.bytes.next().ok_or(JQErr::UnexpectedEOF)?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    /// Reads `len` bytes. The length comes from the input, so nothing is
+    /// allocated up front in case it is bogus.
+    pub(crate) fn take(&mut self, len: u64) -> Result<Vec<u8>, JQErr> {
+        let mut bytes = Vec::new();
+        for _ in 0..len {
+            bytes.push(self.byte()?);
+        }
+        Ok(bytes)
+    }
+
+    /// Reads a big-endian unsigned integer of `len` bytes.
+    pub(crate) fn uint(&mut self, len: usize) -> Result<u64, JQErr> {
+        let mut value = 0;
+        for _ in 0..len {
+            value = (value << 8) | u64::from(self.byte()?);
+        }
+        Ok(value)
+    }
+
+    pub(crate) fn text(&mut self, len: u64) -> Result<String, JQErr> {
+        self.check_text_len(len)?;
+        String::from_utf8(self.take(len)?).map_err(|_| self.invalid("string is not valid UTF-8"))
+    }
+
+    /// Reads a byte string of `len` bytes, as chosen by `mode`.
+    pub(crate) fn bytes(&mut self, len: u64, mode: BinaryBytes) -> Result<Value, JQErr> {
+        self.check_bytes_len(len, mode)?;
+        Ok(bytes_value(&self.take(len)?, mode))
+    }
+}
+
+/// Converts a byte string into a value, as chosen by `mode`.
+pub(crate) fn bytes_value(bytes: &[u8], mode: BinaryBytes) -> Value {
+    match mode {
+        BinaryBytes::Base64 => Value::String(base64(bytes).into()),
+        BinaryBytes::Array => Value::Array(
+            bytes
+                .iter()
+                .map(|byte| Value::Number(byte.to_string().into()))
+                .collect(),
+        ),
+    }
+}
+
+/// Attaches a tag to a value, as chosen by `mode`.
+pub(crate) fn tagged_value(tag: Value, value: Value, mode: BinaryTags) -> Value {
+    match mode {
+        BinaryTags::Surface => Value::Object(vec![("tag".into(), tag), ("value".into(), value)]),
+        BinaryTags::Drop => value,
+    }
+}
+
+/// The standard, padded base64 encoding of `bytes`.
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | u32::from(*byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// The integer a number holds, if it holds one which the binary formats
+/// can encode as an integer rather than a float.
+pub(crate) fn integer(value: &Value) -> Option<i128> {
+    let int = match value {
+        Value::Number(num) => num.parse::<i128>().ok()?,
+        Value::ParsedNumber(num) if num.fract() == 0.0 && num.abs() <= u64::MAX as f64 => {
+            *num as i128
+        }
+        _ => return None,
+    };
+
+    (-(1 << 64)..1 << 64).contains(&int).then_some(int)
+}
@@ -0,0 +1,547 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use crate::{value::Value, JQErr, JQStream, Sanitized, SanitizedJQStream, Token};
+
+/// Which part of an [`ExecutionBudget`] ran out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecutionLimit {
+    /// More tokens were pulled than [`ExecutionBudget::with_max_steps`]
+    /// allows.
+    Steps,
+    /// More values were produced than
+    /// [`ExecutionBudget::with_max_outputs`] allows.
+    Outputs,
+    /// A value was larger than [`ExecutionBudget::with_max_value_size`]
+    /// allows.
+    ValueSize,
+    /// The clock passed [`ExecutionBudget::with_deadline`].
+    Deadline,
+}
+
+/// A source of the current time for [`ExecutionBudget::with_deadline`],
+/// which can be replaced to control time in tests or to read a cheaper
+/// clock.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// The [`Clock`] used by default, which reads [`Instant::now`].
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Stops a filter from another thread, or from anywhere else which holds
+/// a clone of it. Streams with an [`ExecutionBudget`] using this token
+/// fail with [`JQErr::Cancelled`] at the next token they pull.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Limits how much work a filter may do, for filters which can't be
+/// trusted to finish, such as `repeat(.)`. Nothing is limited by default.
+///
+/// A budget is attached to a stream with
+/// [`SanitizedJQStream::with_budget`], and checked every time a token is
+/// pulled through it. While it's pulled, the budget is also spent by every
+/// builtin underneath it, however deeply it's wrapped: the builtins which
+/// loop, `while`, `until`, `repeat`, `range`, the sorts and `slurp`, spend
+/// it on every turn of their loop, so `until(cond; update)` is stopped
+/// even while it produces nothing, and builtins such as `add` spend it on
+/// the values they take apart. Clones share what has been spent, so the same budget can be attached to the outermost stream and
+/// to the streams that [`Filter`](crate::Filter)s return. Once any limit
+/// is hit, every stream using the budget fails.
+#[derive(Clone)]
+pub struct ExecutionBudget {
+    max_steps: Option<u64>,
+    max_outputs: Option<u64>,
+    max_value_size: Option<usize>,
+    deadline: Option<Instant>,
+    clock: Rc<dyn Clock>,
+    cancellation: Option<CancellationToken>,
+    steps: Rc<Cell<u64>>,
+    outputs: Rc<Cell<u64>>,
+}
+
+impl Default for ExecutionBudget {
+    fn default() -> Self {
+        Self {
+            max_steps: None,
+            max_outputs: None,
+            max_value_size: None,
+            deadline: None,
+            clock: Rc::new(SystemClock),
+            cancellation: None,
+            steps: Rc::new(Cell::new(0)),
+            outputs: Rc::new(Cell::new(0)),
+        }
+    }
+}
+
+thread_local! {
+    /// The budget of the innermost [`Budgeted`] stream being pulled from.
+    static ACTIVE: RefCell<ExecutionBudget> = RefCell::new(ExecutionBudget::default());
+}
+
+impl ExecutionBudget {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The budget of the innermost [`Budgeted`] stream being pulled from,
+    /// or an unlimited budget outside of one, which builtins spend on the
+    /// work they do between the tokens they produce.
+    pub(crate) fn active() -> Self {
+        ACTIVE.with(|active| active.borrow().clone())
+    }
+
+    /// Spends a step of the [`active`](Self::active) budget.
+    pub(crate) fn step_active() -> Result<(), JQErr> {
+        ACTIVE.with(|active| active.borrow().step())
+    }
+
+    /// Makes this the [`active`](Self::active) budget until the returned
+    /// guard is dropped.
+    fn activate(&self) -> Activation {
+        Activation {
+            outer: Some(ACTIVE.with(|active| active.replace(self.clone()))),
+        }
+    }
+
+    /// Sets how many tokens may be pulled, in total, through every stream
+    /// the budget is attached to.
+    pub fn with_max_steps(mut self, steps: u64) -> Self {
+        self.max_steps = Some(steps);
+        self
+    }
+
+    /// Sets how many values may be produced, in total, by every stream the
+    /// budget is attached to. Attach it only to the outermost stream to
+    /// limit the values a program outputs.
+    pub fn with_max_outputs(mut self, outputs: u64) -> Self {
+        self.max_outputs = Some(outputs);
+        self
+    }
+
+    /// Sets roughly how many bytes a single value may take up once it's
+    /// materialized, as builtins such as `sort` and `until` do, counting
+    /// the text of its strings and numbers and the size of each token.
+    /// Each value a stream produces is measured on its own, while the
+    /// looping builtins also measure what they hold in memory.
+    pub fn with_max_value_size(mut self, bytes: usize) -> Self {
+        self.max_value_size = Some(bytes);
+        self
+    }
+
+    /// Sets when, according to the budget's [`Clock`], streams stop.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Replaces the [`SystemClock`] which deadlines are checked against.
+    pub fn with_clock<C>(mut self, clock: C) -> Self
+    where
+        C: Clock + 'static,
+    {
+        self.clock = Rc::new(clock);
+        self
+    }
+
+    /// Stops every stream using the budget once `cancellation` is
+    /// cancelled.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Spends a step, failing if the budget has run out.
+    pub(crate) fn step(&self) -> Result<(), JQErr> {
+        if self
+            .cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+        {
+            return Err(JQErr::Cancelled);
+        }
+
+        let steps = self.steps.get() + 1;
+        self.steps.set(steps);
+        if self.max_steps.is_some_and(|max| steps > max) {
+            return Err(JQErr::ExecutionLimitExceeded(ExecutionLimit::Steps));
+        }
+
+        if self
+            .deadline
+            .is_some_and(|deadline| self.clock.now() >= deadline)
+        {
+            return Err(JQErr::ExecutionLimitExceeded(ExecutionLimit::Deadline));
+        }
+        Ok(())
+    }
+
+    /// Adds the size of `token` to `size`, the size of a value being built
+    /// up, failing if it's grown too large.
+    pub(crate) fn charge(&self, size: &mut usize, token: &Token) -> Result<(), JQErr> {
+        *size += token_size(token);
+        if self.max_value_size.is_some_and(|max| *size > max) {
+            return Err(JQErr::ExecutionLimitExceeded(ExecutionLimit::ValueSize));
+        }
+        Ok(())
+    }
+
+    /// Materializes the next value of `stream`, spending a step on each of
+    /// its tokens and charging them to `size`, for builtins which hold
+    /// values in memory rather than passing their tokens along.
+    pub(crate) fn next_value<Stream>(
+        &self,
+        stream: &mut Stream,
+        size: &mut usize,
+    ) -> Option<Result<Value, JQErr>>
+    where
+        Stream: Iterator<Item = crate::Item>,
+    {
+        let mut tokens = stream.by_ref().map(|item| {
+            let token = item?;
+            self.step()?;
+            self.charge(size, &token)?;
+            Ok(token)
+        });
+        Value::next_from(&mut tokens)
+    }
+
+    /// Counts a value which has been produced, failing if there are too
+    /// many.
+    fn output(&self) -> Result<(), JQErr> {
+        let outputs = self.outputs.get() + 1;
+        self.outputs.set(outputs);
+        if self.max_outputs.is_some_and(|max| outputs > max) {
+            return Err(JQErr::ExecutionLimitExceeded(ExecutionLimit::Outputs));
+        }
+        Ok(())
+    }
+}
+
+/// Roughly how many bytes a token takes up in a materialized value.
+fn token_size(token: &Token) -> usize {
+    let text = match token {
+        Token::String(text) | Token::Number(text) => text.len(),
+        _ => 0,
+    };
+    std::mem::size_of::<Token>() + text
+}
+
+/// Restores the budget which was active before
+/// [`ExecutionBudget::activate`] once dropped.
+struct Activation {
+    outer: Option<ExecutionBudget>,
+}
+
+impl Drop for Activation {
+    fn drop(&mut self) {
+        if let Some(outer) = self.outer.take() {
+            ACTIVE.with(|active| active.replace(outer));
+        }
+    }
+}
+
+/// A stream which spends the [`active`](ExecutionBudget::active) budget on
+/// every token pulled from it, for builtins which take values apart or
+/// run filters before producing anything.
+pub(crate) struct Spending<Stream>
+where
+    Stream: JQStream,
+{
+    stream: Stream,
+}
+
+impl<Stream> Spending<Stream>
+where
+    Stream: JQStream,
+{
+    pub(crate) fn new(stream: Stream) -> Self {
+        Self { stream }
+    }
+}
+
+impl<Stream> Iterator for Spending<Stream>
+where
+    Stream: JQStream,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(err) = ExecutionBudget::step_active() {
+            return Some(Err(err));
+        }
+        self.stream.next()
+    }
+}
+
+/// A stream which spends an [`ExecutionBudget`] on every token pulled
+/// through it, and makes it the budget of every builtin underneath.
+pub struct Budgeted<Stream>
+where
+    Stream: JQStream,
+{
+    finished: bool,
+    stream: Sanitized<Stream>,
+    budget: ExecutionBudget,
+    /// The size of the value currently being produced.
+    value_size: usize,
+}
+
+impl<Stream> Budgeted<Stream>
+where
+    Stream: JQStream,
+{
+    pub(crate) fn new(stream: Stream, budget: ExecutionBudget) -> Self {
+        Self {
+            finished: false,
+            stream: stream.sanitize(),
+            budget,
+            value_size: 0,
+        }
+    }
+
+    fn check(&mut self, token: &Token) -> Result<(), JQErr> {
+        self.budget.charge(&mut self.value_size, token)?;
+
+        if self.stream.get_path().is_empty() {
+            self.value_size = 0;
+            self.budget.output()?;
+        }
+        Ok(())
+    }
+}
+
+impl<Stream> Iterator for Budgeted<Stream>
+where
+    Stream: JQStream,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        // The budget is checked before pulling, so that a stream which
+        // loops forever before producing its next token is never entered
+        // once the budget has run out.
+        let checked = self.budget.step().and_then(|()| {
+            let _activation = self.budget.activate();
+            match self.stream.next() {
+                None => Ok(None),
+                Some(Err(err)) => Err(err),
+                Some(Ok(token)) => self.check(&token).map(|()| Some(token)),
+            }
+        });
+
+        match checked {
+            Ok(Some(token)) => Some(Ok(token)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl<Stream> SanitizedJQStream for Budgeted<Stream> where Stream: JQStream {}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        CharStream, Clock, ExecutionBudget, ExecutionLimit, Identity, JQErr, SanitizedJQStream,
+        Token, ValueTokens,
+    };
+
+    /// A clock which moves forward a millisecond every time it's read.
+    struct Ticking {
+        start: Instant,
+        ticks: Cell<u32>,
+    }
+
+    impl Clock for Ticking {
+        fn now(&self) -> Instant {
+            self.ticks.set(self.ticks.get() + 1);
+            self.start + Duration::from_millis(u64::from(self.ticks.get()))
+        }
+    }
+
+    fn never(_: ValueTokens) -> impl Iterator<Item = crate::Item> {
+        "false".chars().into_json_tokens()
+    }
+
+    fn limit(result: Result<String, JQErr>) -> Option<ExecutionLimit> {
+        match result {
+            Err(JQErr::ExecutionLimitExceeded(limit)) => Some(limit),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn stops_loops_which_produce_nothing_after_max_steps() {
+        let budget = ExecutionBudget::new().with_max_steps(1000);
+        let result = "1"
+            .chars()
+            .into_json_tokens()
+            .until(never, Identity)
+            .with_budget(&budget)
+            .to_string();
+        assert_eq!(limit(result), Some(ExecutionLimit::Steps));
+    }
+
+    #[test]
+    fn stops_wrapped_loops_which_produce_nothing() {
+        fn run<Stream>(stream: Stream, budget: &ExecutionBudget) -> Result<String, JQErr>
+        where
+            Stream: SanitizedJQStream,
+        {
+            stream.with_budget(budget).to_string()
+        }
+
+        let budget = ExecutionBudget::new().with_max_steps(1000);
+        let looping = "1"
+            .chars()
+            .into_json_tokens()
+            .until(never, Identity)
+            .limit(1);
+        assert_eq!(limit(run(looping, &budget)), Some(ExecutionLimit::Steps));
+    }
+
+    #[test]
+    fn spends_steps_on_values_taken_apart() {
+        let input = format!("[{}]", vec!["1"; 100].join(","));
+        let run = |budget: &ExecutionBudget| {
+            input
+                .chars()
+                .into_json_tokens()
+                .add()
+                .with_budget(budget)
+                .to_string()
+        };
+        assert_eq!(run(&ExecutionBudget::new()).unwrap(), "100\n");
+        assert_eq!(
+            limit(run(&ExecutionBudget::new().with_max_steps(50))),
+            Some(ExecutionLimit::Steps)
+        );
+    }
+
+    #[test]
+    fn stops_loops_which_produce_nothing_at_the_deadline() {
+        let start = Instant::now();
+        let budget = ExecutionBudget::new()
+            .with_clock(Ticking {
+                start,
+                ticks: Cell::new(0),
+            })
+            .with_deadline(start + Duration::from_millis(100));
+        let result = "1"
+            .chars()
+            .into_json_tokens()
+            .until(never, Identity)
+            .with_budget(&budget)
+            .to_string();
+        assert_eq!(limit(result), Some(ExecutionLimit::Deadline));
+    }
+
+    #[test]
+    fn spends_steps_on_ranges_which_produce_nothing() {
+        let inputs = "null ".repeat(100);
+        let run = |budget: &ExecutionBudget| {
+            inputs
+                .chars()
+                .into_json_tokens()
+                .range_step(0.0, 1.0, 0.0)
+                .with_budget(budget)
+                .to_string()
+        };
+        assert_eq!(run(&ExecutionBudget::new()).unwrap(), "");
+        assert_eq!(
+            limit(run(&ExecutionBudget::new().with_max_steps(50))),
+            Some(ExecutionLimit::Steps)
+        );
+    }
+
+    #[test]
+    fn charges_what_sorts_hold_in_memory() {
+        // The sorted array alone fits, but not along with its keys.
+        let size = 7 * std::mem::size_of::<Token>() + 3;
+        let run = |budget: &ExecutionBudget| {
+            "[3,1,2]"
+                .chars()
+                .into_json_tokens()
+                .sort()
+                .with_budget(budget)
+                .to_string()
+        };
+        assert_eq!(run(&ExecutionBudget::new()).unwrap(), "[1,2,3]\n");
+        assert_eq!(
+            limit(run(&ExecutionBudget::new().with_max_value_size(size))),
+            Some(ExecutionLimit::ValueSize)
+        );
+        assert_eq!(
+            "[1,2,3]"
+                .chars()
+                .into_json_tokens()
+                .with_budget(&ExecutionBudget::new().with_max_value_size(size))
+                .to_string()
+                .unwrap(),
+            "[1,2,3]\n"
+        );
+    }
+
+    #[test]
+    fn spends_steps_on_everything_slurped() {
+        let run = |budget: &ExecutionBudget| {
+            "1 2 3 4 5"
+                .chars()
+                .into_json_tokens()
+                .slurp()
+                .with_budget(budget)
+                .to_string()
+        };
+        assert_eq!(run(&ExecutionBudget::new()).unwrap(), "[1,2,3,4,5]\n");
+        assert_eq!(
+            limit(run(&ExecutionBudget::new().with_max_steps(12))),
+            Some(ExecutionLimit::Steps)
+        );
+    }
+}
@@ -0,0 +1,213 @@
+/// Every jq builtin this crate implements, as `name/arity`. This is what
+/// the `builtins` query produces, so a builtin is listed here once its
+/// `SanitizedJQStream` method is added.
+pub(crate) const BUILTINS: &[&str] = &[
+    "GROUP_BY/1",
+    "IN/1",
+    "IN/2",
+    "INDEX/1",
+    "INDEX/2",
+    "UNIQUE_BY/1",
+    "abs/0",
+    "acos/0",
+    "acosh/0",
+    "add/0",
+    "add/1",
+    "all/0",
+    "all/1",
+    "all/2",
+    "any/0",
+    "any/1",
+    "any/2",
+    "ascii_downcase/0",
+    "ascii_upcase/0",
+    "asin/0",
+    "asinh/0",
+    "atan/0",
+    "atan2/2",
+    "atanh/0",
+    "builtins/0",
+    "cbrt/0",
+    "ceil/0",
+    "contains/1",
+    "copysign/2",
+    "cos/0",
+    "cosh/0",
+    "debug/0",
+    "debug/1",
+    "drem/2",
+    "empty/0",
+    "endswith/1",
+    "exp/0",
+    "exp10/0",
+    "exp2/0",
+    "explode/0",
+    "expm1/0",
+    "fabs/0",
+    "fdim/2",
+    "first/1",
+    "flatten/0",
+    "flatten/1",
+    "floor/0",
+    "fma/3",
+    "fmax/2",
+    "fmin/2",
+    "fmod/2",
+    "frexp/0",
+    "fromjson/0",
+    "fromstream/1",
+    "gamma/0",
+    "getpath/1",
+    "group_by/1",
+    "have_decnum/0",
+    "have_literal_numbers/0",
+    "hypot/2",
+    "implode/0",
+    "indices/1",
+    "infinite/0",
+    "input/0",
+    "inputs/0",
+    "inside/1",
+    "isempty/1",
+    "isinfinite/0",
+    "isnan/0",
+    "isnormal/0",
+    "j0/0",
+    "j1/0",
+    "join/1",
+    "last/1",
+    "ldexp/2",
+    "lgamma/0",
+    "lgamma_r/0",
+    "limit/2",
+    "log/0",
+    "log10/0",
+    "log1p/0",
+    "log2/0",
+    "logb/0",
+    "ltrim/0",
+    "ltrimstr/1",
+    "max/0",
+    "max_by/1",
+    "min/0",
+    "min_by/1",
+    "modf/0",
+    "nan/0",
+    "nearbyint/0",
+    "nextafter/2",
+    "nexttoward/2",
+    "nth/2",
+    "pick/1",
+    "pow/2",
+    "pow10/0",
+    "range/1",
+    "range/2",
+    "range/3",
+    "repeat/1",
+    "reverse/0",
+    "rint/0",
+    "round/0",
+    "rtrim/0",
+    "rtrimstr/1",
+    "scalb/2",
+    "scalbln/2",
+    "scan/1",
+    "scan/2",
+    "significand/0",
+    "sin/0",
+    "sinh/0",
+    "sort/0",
+    "sort_by/1",
+    "split/1",
+    "splits/1",
+    "splits/2",
+    "sqrt/0",
+    "startswith/1",
+    "tan/0",
+    "tanh/0",
+    "tgamma/0",
+    "toarray/0",
+    "tojson/0",
+    "tonumber/0",
+    "tostream/0",
+    "tostring/0",
+    "trim/0",
+    "trunc/0",
+    "truncate_stream/1",
+    "unique/0",
+    "unique_by/1",
+    "until/2",
+    "utf8bytelength/0",
+    "while/2",
+    "y0/0",
+    "y1/0",
+];
+
+#[cfg(test)]
+mod tests {
+    use crate::{CharStream, SanitizedJQStream};
+
+    use super::BUILTINS;
+
+    fn builtins() -> String {
+        "null"
+            .chars()
+            .into_json_tokens()
+            .builtins()
+            .to_string()
+            .unwrap()
+    }
+
+    #[test]
+    fn builtins_lists_every_builtin() {
+        let listed = BUILTINS
+            .iter()
+            .map(|builtin| format!("\"{builtin}\""))
+            .collect::<Vec<_>>();
+        assert_eq!(builtins(), format!("[{}]\n", listed.join(",")));
+
+        for builtin in [
+            "atan2/2",
+            "fma/3",
+            "sort_by/1",
+            "flatten/1",
+            "range/3",
+            "ltrimstr/1",
+            "join/1",
+            "limit/2",
+            "isempty/1",
+            "input/0",
+            "INDEX/2",
+            "IN/1",
+            "getpath/1",
+            "scan/2",
+            "debug/1",
+            "tostream/0",
+            "fromstream/1",
+            "truncate_stream/1",
+            "have_decnum/0",
+        ] {
+            assert!(BUILTINS.contains(&builtin), "{builtin}");
+        }
+    }
+
+    #[test]
+    fn builtins_are_sorted_and_unique() {
+        assert!(BUILTINS.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn builtins_are_named_with_their_arity() {
+        for builtin in BUILTINS {
+            let (name, arity) = builtin.split_once('/').expect("a name and arity");
+            assert!(
+                name.starts_with(|ch: char| ch.is_ascii_alphabetic())
+                    && name
+                        .chars()
+                        .all(|ch| ch.is_ascii_alphanumeric() || ch == '_'),
+                "{builtin}"
+            );
+            assert!(arity.parse::<usize>().is_ok(), "{builtin}");
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::{cmp::Ordering, collections::VecDeque};
+
+use crate::{
+    escape::write_string, number_format::format_number_ecmascript, value::Value, JQErr, JQStream,
+    Sanitized, StringEscapes, Token,
+};
+
+/// Writes values in the RFC 8785 JSON Canonicalization Scheme, so equal
+/// values always produce the same text for hashing or signing: no
+/// whitespace, object keys sorted by their UTF-16 code units, numbers
+/// formatted like ECMAScript and only the escapes which strings require.
+///
+/// Only objects need to be buffered to sort their keys, so everything
+/// outside of them, such as the items of a top-level array, is written as
+/// it streams through. Separate top-level values are separated by a
+/// newline, without one after the last value.
+pub struct CanonicalChars<Stream>
+where
+    Stream: JQStream,
+{
+    finished: bool,
+    stream: Sanitized<Stream>,
+    started: bool,
+    buf: VecDeque<char>,
+}
+
+impl<Stream> CanonicalChars<Stream>
+where
+    Stream: JQStream,
+{
+    pub(crate) fn new(stream: Stream) -> Self {
+        Self {
+            finished: false,
+            stream: stream.sanitize(),
+            started: false,
+            buf: VecDeque::new(),
+        }
+    }
+
+    fn write_token(&mut self, token: Token) -> Result<(), JQErr> {
+        match token {
+            Token::ObjectStart => {
+                let object = Value::parse(Token::ObjectStart, &mut self.stream)?;
+                write_value(&object, &mut self.buf)
+            }
+            token => write_scalar(token, &mut self.buf),
+        }
+    }
+}
+
+impl<Stream> Iterator for CanonicalChars<Stream>
+where
+    Stream: JQStream,
+{
+    type Item = Result<char, JQErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(ch) = self.buf.pop_front() {
+            return Some(Ok(ch));
+        }
+
+        if self.finished {
+            return None;
+        }
+
+        let top_level = self.stream.get_path().is_empty();
+        let token = match self.stream.next()? {
+            Ok(token) => token,
+            Err(err) => {
+                self.finished = true;
+                return Some(Err(err));
+            }
+        };
+
+        // Every top-level value after the first goes on a new line.
+        if top_level && std::mem::replace(&mut self.started, true) {
+            self.buf.push_back('\n');
+        }
+
+        if let Err(err) = self.write_token(token) {
+            self.finished = true;
+            return Some(Err(err));
+        }
+        self.buf.pop_front().map(Ok)
+    }
+}
+
+/// Writes a token which isn't the start of an object.
+fn write_scalar(token: Token, out: &mut VecDeque<char>) -> Result<(), JQErr> {
+    match token {
+        Token::Null => out.extend("null".chars()),
+        Token::True => out.extend("true".chars()),
+        Token::False => out.extend("false".chars()),
+        Token::Number(num) => write_number(num.parse().unwrap_or(f64::NAN), out)?,
+        Token::ParsedNumber(num) => write_number(num, out)?,
+        Token::String(str) => write_string(&str, StringEscapes::minimal(), out),
+        Token::ArrayStart => out.push_back('['),
+        Token::ArrayEnd => out.push_back(']'),
+        Token::ObjectStart => out.push_back('{'),
+        Token::ObjectEnd => out.push_back('}'),
+        Token::Colon => out.push_back(':'),
+        Token::Comma => out.push_back(','),
+    }
+    Ok(())
+}
+
+fn write_value(value: &Value, out: &mut VecDeque<char>) -> Result<(), JQErr> {
+    match value {
+        Value::Array(items) => {
+            out.push_back('[');
+            for (i, item) in items.iter().enumerate() {
+                if i != 0 {
+                    out.push_back(',');
+                }
+                write_value(item, out)?;
+            }
+            out.push_back(']');
+        }
+        Value::Object(entries) => {
+            let mut entries = entries.iter().collect::<Vec<_>>();
+            entries.sort_by(|(left, _), (right, _)| utf16_cmp(left, right));
+
+            out.push_back('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i != 0 {
+                    out.push_back(',');
+                }
+                write_string(key, StringEscapes::minimal(), out);
+                out.push_back(':');
+                write_value(value, out)?;
+            }
+            out.push_back('}');
+        }
+        Value::Null => write_scalar(Token::Null, out)?,
+        Value::True => write_scalar(Token::True, out)?,
+        Value::False => write_scalar(Token::False, out)?,
+        Value::Number(num) => write_number(num.parse().unwrap_or(f64::NAN), out)?,
+        Value::ParsedNumber(num) => write_number(*num, out)?,
+        Value::String(str) => write_string(str, StringEscapes::minimal(), out),
+    }
+    Ok(())
+}
+
+/// Compares strings by their UTF-16 code units, which orders characters
+/// outside of the basic multilingual plane differently to UTF-8.
+fn utf16_cmp(left: &str, right: &str) -> Ordering {
+    left.encode_utf16().cmp(right.encode_utf16())
+}
+
+fn write_number(num: f64, out: &mut VecDeque<char>) -> Result<(), JQErr> {
+    match format_number_ecmascript(num) {
+        Some(num) => {
+            out.extend(num.chars());
+            Ok(())
+        }
+        None => Err(JQErr::StreamOperationFailed(
+            "NaN and infinite numbers can't be canonicalized".into(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CharStream, JQErr, SanitizedJQStream};
+
+    fn canonical(json: &str) -> Result<String, JQErr> {
+        json.chars()
+            .into_json_tokens()
+            .to_chars_canonical()
+            .collect()
+    }
+
+    #[test]
+    fn sorts_keys_by_utf16_code_units() {
+        assert_eq!(
+            canonical(r#"{"b": [{"z": 1, "a": 2}], "a": {}, "ﬁ": 1, "😀": 2, "é": 3}"#).unwrap(),
+            r#"{"a":{},"b":[{"a":2,"z":1}],"é":3,"😀":2,"ﬁ":1}"#
+        );
+    }
+
+    #[test]
+    fn formats_numbers_and_strings_minimally() {
+        assert_eq!(
+            canonical(r#"[1.000, -0, 1e21, 1E-7, 0.000001, 123.4500]"#).unwrap(),
+            "[1,0,1e+21,1e-7,0.000001,123.45]"
+        );
+        assert_eq!(
+            canonical(r#""\u0001\b\t\n\f\r\"\\\/\u007fé""#).unwrap(),
+            "\"\\u0001\\b\\t\\n\\f\\r\\\"\\\\/\u{7f}é\""
+        );
+    }
+
+    #[test]
+    fn separates_values_with_newlines() {
+        assert_eq!(
+            canonical("1 [true, null] {\"b\": 1, \"a\": 2}").unwrap(),
+            "1\n[true,null]\n{\"a\":2,\"b\":1}"
+        );
+    }
+
+    #[test]
+    fn rejects_numbers_outside_of_a_double() {
+        assert!(matches!(
+            canonical("[1e1000]"),
+            Err(JQErr::StreamOperationFailed(msg))
+                if &*msg == "NaN and infinite numbers can't be canonicalized"
+        ));
+    }
+}
@@ -0,0 +1,562 @@
+use std::collections::VecDeque;
+
+use crate::{
+    binary::{bytes_value, integer, tagged_value, BinaryBytes, BinaryTags, ByteReader},
+    raw::LimitCheck,
+    value::{ObjectEntries, Value},
+    JQErr, JQStream, ParseLimits, Sanitized, SanitizedJQStream, Token,
+};
+
+/// The additional information which marks an indefinite length item.
+const INDEFINITE: u8 = 31;
+const BREAK: u8 = 0xff;
+
+/// Reads CBOR (RFC 8949) into a stream of JSON tokens, producing every
+/// top-level item in the input as a separate value, so CBOR sequences
+/// (RFC 8742) work too. Map keys which aren't text strings become their
+/// compact JSON, since JSON only allows string keys.
+pub struct CborTokens<Bytes>
+where
+    Bytes: Iterator<Item = u8>,
+{
+    finished: bool,
+    reader: ByteReader<Bytes>,
+    bytes: BinaryBytes,
+    tags: BinaryTags,
+    buf: VecDeque<Token>,
+}
+
+impl<Bytes> CborTokens<Bytes>
+where
+    Bytes: Iterator<Item = u8>,
+{
+    pub(crate) fn new(bytes: Bytes, bytes_mode: BinaryBytes, tags: BinaryTags) -> Self {
+        Self {
+            finished: false,
+            reader: ByteReader::new(bytes, "CBOR"),
+            bytes: bytes_mode,
+            tags,
+            buf: VecDeque::new(),
+        }
+    }
+
+    /// Sets limits on the input, such as how deeply it may be nested.
+    /// Nesting is always limited, as items are decoded recursively, to a
+    /// depth of 256 unless `limits` sets another.
+    ///
+    /// Strings are measured before they're read and items are counted as
+    /// they're decoded, so neither an oversized string nor too many items
+    /// are ever buffered. The tokens of each top-level value are checked
+    /// exactly once it's decoded, with errors at the offset of its last
+    /// byte.
+    pub fn with_limits(mut self, limits: ParseLimits) -> Self {
+        self.reader.limits = LimitCheck::new(limits);
+        self
+    }
+
+    /// Reads the argument of an item whose initial byte has already been
+    /// read, or [`None`] for indefinite lengths.
+    fn argument(&mut self, initial: u8) -> Result<Option<u64>, JQErr> {
+        match initial & 0x1f {
+            info @ 0..=23 => Ok(Some(u64::from(info))),
+            info @ 24..=27 => Ok(Some(self.reader.uint(1 << (info - 24))?)),
+            INDEFINITE if matches!(initial >> 5, 2..=5) => Ok(None),
+            _ => Err(self.reader.invalid("invalid additional information")),
+        }
+    }
+
+    /// Reads the chunks of an indefinite length byte or text string.
+    fn chunks(&mut self, major: u8) -> Result<Vec<u8>, JQErr> {
+        let mut bytes = Vec::new();
+        loop {
+            let initial = self.reader.byte()?;
+            if initial == BREAK {
+                return Ok(bytes);
+            }
+
+            match (initial >> 5 == major, self.argument(initial)?) {
+                (true, Some(len)) => {
+                    let total = (bytes.len() as u64).saturating_add(len);
+                    match major {
+                        2 => self.reader.check_bytes_len(total, self.bytes)?,
+                        _ => self.reader.check_text_len(total)?,
+                    }
+                    bytes.extend(self.reader.take(len)?)
+                }
+                _ => {
+                    return Err(self
+                        .reader
+                        .invalid("invalid chunk in indefinite length string"))
+                }
+            }
+        }
+    }
+
+    /// Reads the next item, or [`None`] for a break.
+    fn item(&mut self) -> Result<Option<Value>, JQErr> {
+        let initial = self.reader.byte()?;
+        if initial == BREAK {
+            return Ok(None);
+        }
+        self.reader.count_item()?;
+
+        let major = initial >> 5;
+        if major == 7 {
+            return self.simple(initial).map(Some);
+        }
+
+        let argument = self.argument(initial)?;
+        let nested = matches!(major, 4..=6);
+        if nested {
+            self.reader.enter()?;
+        }
+
+        let value = match (major, argument) {
+            (0, Some(num)) => Value::Number(num.to_string().into()),
+            (1, Some(num)) => Value::Number((-1 - i128::from(num)).to_string().into()),
+            (2, Some(len)) => self.reader.bytes(len, self.bytes)?,
+            (2, None) => bytes_value(&self.chunks(2)?, self.bytes),
+            (3, Some(len)) => Value::String(self.reader.text(len)?.into()),
+            (3, None) => match String::from_utf8(self.chunks(3)?) {
+                Ok(str) => Value::String(str.into()),
+                Err(_) => return Err(self.reader.invalid("string is not valid UTF-8")),
+            },
+            (4, len) => {
+                let mut items = Vec::new();
+                while len.is_none_or(|len| (items.len() as u64) < len) {
+                    match self.item()? {
+                        Some(item) => items.push(item),
+                        None if len.is_none() => break,
+                        None => return Err(self.reader.invalid("unexpected break")),
+                    }
+                }
+                Value::Array(items)
+            }
+            (5, len) => {
+                let mut entries = ObjectEntries::default();
+                let mut read = 0;
+                while len.is_none_or(|len| read < len) {
+                    read += 1;
+                    let key = match self.item()? {
+                        Some(Value::String(key)) => key,
+                        Some(key) => key.to_json()?.into(),
+                        None if len.is_none() => break,
+                        None => return Err(self.reader.invalid("unexpected break")),
+                    };
+                    match self.item()? {
+                        Some(value) => entries.insert(key, value),
+                        None => return Err(self.reader.invalid("unexpected break")),
+                    }
+                }
+                entries.into_value()
+            }
+            (6, Some(tag)) => match self.item()? {
+                Some(value) => {
+                    tagged_value(Value::Number(tag.to_string().into()), value, self.tags)
+                }
+                None => return Err(self.reader.invalid("unexpected break")),
+            },
+            _ => unreachable!("only strings, arrays and maps can be indefinite"),
+        };
+
+        if nested {
+            self.reader.leave();
+        }
+        Ok(Some(value))
+    }
+
+    /// Reads a major type 7 item: a float or a simple value.
+    fn simple(&mut self, initial: u8) -> Result<Value, JQErr> {
+        Ok(match initial & 0x1f {
+            20 => Value::False,
+            21 => Value::True,
+            // Undefined has no JSON equivalent, so it becomes null.
+            22 | 23 => Value::Null,
+            25 => Value::ParsedNumber(f16_to_f64(self.reader.uint(2)? as u16)),
+            26 => Value::ParsedNumber(f64::from(f32::from_bits(self.reader.uint(4)? as u32))),
+            27 => Value::ParsedNumber(f64::from_bits(self.reader.uint(8)?)),
+            info => {
+                let simple = if info == 24 {
+                    self.reader.byte()?
+                } else {
+                    info
+                };
+                return Err(self
+                    .reader
+                    .invalid(format!("unsupported simple value {simple}")));
+            }
+        })
+    }
+}
+
+impl<Bytes> Iterator for CborTokens<Bytes>
+where
+    Bytes: Iterator<Item = u8>,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.buf.pop_front() {
+            if let Err(err) = self.reader.check(&token) {
+                self.finished = true;
+                self.buf.clear();
+                return Some(Err(err));
+            }
+            return Some(Ok(token));
+        }
+
+        if self.finished || self.reader.is_empty() {
+            return None;
+        }
+
+        self.reader.begin();
+        match self.item() {
+            Ok(Some(value)) => {
+                value.push_tokens(&mut self.buf);
+                self.next()
+            }
+            Ok(None) => {
+                self.finished = true;
+                Some(Err(self.reader.invalid("unexpected break")))
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl<Bytes> SanitizedJQStream for CborTokens<Bytes>
+where
+    Bytes: Iterator<Item = u8>,
+{
+    // Maps are decoded into objects which keep the last of each key.
+    fn has_unique_keys(&self) -> bool {
+        true
+    }
+}
+
+/// Converts the bits of an IEEE 754 half-precision float.
+fn f16_to_f64(bits: u16) -> f64 {
+    let sign = if bits >> 15 == 1 { -1.0 } else { 1.0 };
+    let exponent = i32::from(bits >> 10 & 0x1f);
+    let fraction = f64::from(bits & 0x3ff);
+
+    sign * match exponent {
+        0 => fraction * 2f64.powi(-24),
+        0x1f if fraction == 0.0 => f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => (1.0 + fraction / 1024.0) * 2f64.powi(exponent - 15),
+    }
+}
+
+/// Writes the initial bytes of an item, using the shortest encoding of its
+/// argument.
+fn head(major: u8, argument: u64, out: &mut VecDeque<u8>) {
+    let major = major << 5;
+    match argument {
+        0..=23 => out.push_back(major | argument as u8),
+        24..=0xff => out.extend([major | 24, argument as u8]),
+        0x100..=0xffff => {
+            out.push_back(major | 25);
+            out.extend((argument as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push_back(major | 26);
+            out.extend((argument as u32).to_be_bytes());
+        }
+        _ => {
+            out.push_back(major | 27);
+            out.extend(argument.to_be_bytes());
+        }
+    }
+}
+
+fn encode(value: &Value, out: &mut VecDeque<u8>) {
+    match value {
+        Value::False => out.push_back(0xf4),
+        Value::True => out.push_back(0xf5),
+        Value::Null => out.push_back(0xf6),
+        Value::Number(_) | Value::ParsedNumber(_) => match integer(value) {
+            Some(int) if int >= 0 => head(0, int as u64, out),
+            Some(int) => head(1, (-1 - int) as u64, out),
+            None => {
+                out.push_back(0xfb);
+                out.extend(value.as_f64().unwrap_or(f64::NAN).to_be_bytes());
+            }
+        },
+        Value::String(str) => {
+            head(3, str.len() as u64, out);
+            out.extend(str.as_bytes());
+        }
+        Value::Array(items) => {
+            head(4, items.len() as u64, out);
+            for item in items {
+                encode(item, out);
+            }
+        }
+        Value::Object(entries) => {
+            head(5, entries.len() as u64, out);
+            for (key, value) in entries {
+                head(3, key.len() as u64, out);
+                out.extend(key.as_bytes());
+                encode(value, out);
+            }
+        }
+    }
+}
+
+/// Writes values as CBOR, one top-level item per value, using the
+/// shortest encoding of every integer and length. Numbers which aren't
+/// integers are written as double-precision floats.
+pub struct CborBytes<Stream>
+where
+    Stream: JQStream,
+{
+    finished: bool,
+    stream: Sanitized<Stream>,
+    buf: VecDeque<u8>,
+}
+
+impl<Stream> CborBytes<Stream>
+where
+    Stream: JQStream,
+{
+    pub(crate) fn new(stream: Stream) -> Self {
+        Self {
+            finished: false,
+            stream: stream.sanitize(),
+            buf: VecDeque::new(),
+        }
+    }
+}
+
+impl<Stream> Iterator for CborBytes<Stream>
+where
+    Stream: JQStream,
+{
+    type Item = Result<u8, JQErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(byte) = self.buf.pop_front() {
+            return Some(Ok(byte));
+        }
+
+        if self.finished {
+            return None;
+        }
+
+        match Value::next_from(&mut self.stream)? {
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+            Ok(value) => {
+                encode(&value, &mut self.buf);
+                self.buf.pop_front().map(Ok)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        BinaryBytes, BinaryTags, ByteStream, CharStream, JQErr, ParseLimits, SanitizedJQStream,
+    };
+
+    fn decode(bytes: &[u8], tags: BinaryTags) -> Result<String, JQErr> {
+        bytes
+            .iter()
+            .copied()
+            .into_cbor_tokens(BinaryBytes::Base64, tags)
+            .to_string()
+    }
+
+    #[test]
+    fn decodes_every_major_type() {
+        let bytes = [
+            0xa6, // a map of 6 entries
+            0x61, b'a', 0x17, // "a": 23
+            0x61, b'b', 0x38, 0x63, // "b": -100
+            0x61, b'c', 0x82, 0xf5, 0xf6, // "c": [true, null]
+            0x61, b'd', 0x43, 1, 2, 3, // "d": h'010203'
+            0x61, b'e', 0xf9, 0x3e, 0x00, // "e": 1.5 as a half float
+            0x01, 0xf4, // 1: false
+        ];
+
+        assert_eq!(
+            decode(&bytes, BinaryTags::Surface).unwrap(),
+            "{\"a\":23,\"b\":-100,\"c\":[true,null],\"d\":\"AQID\",\"e\":1.5,\"1\":false}\n"
+        );
+    }
+
+    #[test]
+    fn decodes_indefinite_lengths() {
+        let bytes = [
+            0x9f, // an indefinite array
+            0x7f, 0x62, b'a', b'b', 0x61, b'c', 0xff, // "ab" "c" as one string
+            0xbf, 0x61, b'k', 0x01, 0xff, // {"k": 1}
+            0xff,
+        ];
+
+        assert_eq!(
+            decode(&bytes, BinaryTags::Surface).unwrap(),
+            "[\"abc\",{\"k\":1}]\n"
+        );
+    }
+
+    #[test]
+    fn reads_sequences_and_tags() {
+        let bytes = [0xc1, 0x1a, 0x5f, 0x5e, 0x10, 0x00, 0x02];
+
+        assert_eq!(
+            decode(&bytes, BinaryTags::Surface).unwrap(),
+            "{\"tag\":1,\"value\":1600000000}\n2\n"
+        );
+        assert_eq!(decode(&bytes, BinaryTags::Drop).unwrap(), "1600000000\n2\n");
+    }
+
+    #[test]
+    fn keeps_the_last_of_repeated_keys() {
+        let bytes = [0xa2, 0x61, b'a', 0x01, 0x61, b'a', 0x02];
+
+        assert_eq!(decode(&bytes, BinaryTags::Surface).unwrap(), "{\"a\":2}\n");
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(matches!(
+            decode(&[0x82, 0x01], BinaryTags::Surface),
+            Err(JQErr::UnexpectedEOF)
+        ));
+        assert!(matches!(
+            decode(&[0xff], BinaryTags::Surface),
+            Err(JQErr::InvalidBinary { offset: 0, .. })
+        ));
+        assert!(matches!(
+            decode(&[0x62, 0xff, 0xfe], BinaryTags::Surface),
+            Err(JQErr::InvalidBinary { .. })
+        ));
+    }
+
+    #[test]
+    fn round_trips_values() {
+        let json = "{\"a\":[1,-2,3.5,\"x\"],\"b\":{\"c\":null,\"d\":true}} 18446744073709551615";
+        let bytes = json
+            .chars()
+            .into_json_tokens()
+            .to_bytes_cbor()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(
+            decode(&bytes, BinaryTags::Surface).unwrap(),
+            "{\"a\":[1,-2,3.5,\"x\"],\"b\":{\"c\":null,\"d\":true}}\n18446744073709551615\n"
+        );
+    }
+
+    #[test]
+    fn limits_nesting_instead_of_overflowing_the_stack() {
+        let mut bytes = vec![0x81; 2_000_000];
+        bytes.push(0x00);
+
+        assert!(matches!(
+            decode(&bytes, BinaryTags::Surface),
+            Err(JQErr::DepthLimitExceeded(_))
+        ));
+
+        let mut tags = vec![0xc1; 2_000_000];
+        tags.push(0x00);
+        assert!(matches!(
+            decode(&tags, BinaryTags::Drop),
+            Err(JQErr::DepthLimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn limits_nesting_to_the_given_depth() {
+        let decode = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .copied()
+                .into_cbor_tokens(BinaryBytes::Base64, BinaryTags::Surface)
+                .with_limits(ParseLimits::default().with_max_depth(2))
+                .to_string()
+        };
+
+        assert_eq!(decode(&[0x81, 0x81, 0x00]).unwrap(), "[[0]]\n");
+        assert!(matches!(
+            decode(&[0x81, 0x81, 0x81, 0x00]),
+            Err(JQErr::DepthLimitExceeded(location)) if location.col() == 2
+        ));
+    }
+
+    #[test]
+    fn limits_strings_and_tokens() {
+        let decode = |bytes: &[u8], limits: ParseLimits| {
+            bytes
+                .iter()
+                .copied()
+                .into_cbor_tokens(BinaryBytes::Base64, BinaryTags::Surface)
+                .with_limits(limits)
+                .to_string()
+        };
+
+        let text = [0x63, b'a', b'b', b'c'];
+        assert_eq!(
+            decode(&text, ParseLimits::default().with_max_string_len(3)).unwrap(),
+            "\"abc\"\n"
+        );
+        assert!(matches!(
+            decode(&text, ParseLimits::default().with_max_string_len(2)),
+            Err(JQErr::StringLimitExceeded(location)) if location.col() == 0
+        ));
+
+        let array = [0x83, 0x01, 0x02, 0x03];
+        assert_eq!(
+            decode(&array, ParseLimits::default().with_max_tokens(7)).unwrap(),
+            "[1,2,3]\n"
+        );
+        assert!(matches!(
+            decode(&array, ParseLimits::default().with_max_tokens(4)),
+            Err(JQErr::TokenLimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn limits_strings_and_items_before_reading_them() {
+        let decode = |bytes: &[u8], limits: ParseLimits| {
+            bytes
+                .iter()
+                .copied()
+                .into_cbor_tokens(BinaryBytes::Base64, BinaryTags::Surface)
+                .with_limits(limits)
+                .to_string()
+        };
+        let huge = [0xff; 8];
+
+        for major in [0x7b, 0x5b] {
+            let mut string = vec![major];
+            string.extend(huge);
+            assert!(matches!(
+                decode(&string, ParseLimits::default().with_max_string_len(10)),
+                Err(JQErr::StringLimitExceeded(location)) if location.col() == 8
+            ));
+        }
+
+        let chunks = [0x7f, 0x62, b'a', b'b', 0x62, b'c', b'd', 0xff];
+        assert!(matches!(
+            decode(&chunks, ParseLimits::default().with_max_string_len(3)),
+            Err(JQErr::StringLimitExceeded(location)) if location.col() == 4
+        ));
+
+        let mut array = vec![0x9a, 0x00, 0xff, 0xff, 0xff];
+        array.extend([0x00; 50]);
+        assert!(matches!(
+            decode(&array, ParseLimits::default().with_max_tokens(10)),
+            Err(JQErr::TokenLimitExceeded(location)) if location.col() == 14
+        ));
+    }
+}
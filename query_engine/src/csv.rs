@@ -0,0 +1,351 @@
+use std::{collections::VecDeque, rc::Rc};
+
+use crate::{
+    char_locations::CharLocations,
+    raw::decimal_token,
+    value::{ObjectEntries, Value},
+    JQErr, Location, SanitizedJQStream, Token,
+};
+
+/// The text of each field in a row, along with whether it was quoted.
+type Row = Vec<(String, bool)>;
+
+/// Reads CSV (RFC 4180) or TSV, producing every row as an array of
+/// strings. With `HEADER`, the first row is read as a header instead and
+/// every other row becomes an object keyed by it, with null for any
+/// missing fields. Blank lines are skipped.
+///
+/// CSV fields may be quoted, in which case they can hold the delimiter,
+/// newlines and `""` for a quote. TSV fields can't be quoted, but the
+/// `\t`, `\n`, `\r` and `\\` escapes which jq's `@tsv` writes are undone.
+///
+/// With `infer_numbers`, unquoted fields which are valid JSON numbers
+/// become numbers. Anything else, such as `007` or `+1`, stays a string
+/// so that identifiers aren't mangled.
+pub struct CsvRows<const HEADER: bool, Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    finished: bool,
+    chars: CharLocations<Chars>,
+    peeked: Option<(Location, char)>,
+    tsv: bool,
+    infer_numbers: bool,
+    header: Option<Vec<Rc<str>>>,
+    buf: VecDeque<Token>,
+}
+
+impl<const HEADER: bool, Chars> CsvRows<HEADER, Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    pub(crate) fn new(chars: Chars, tsv: bool, infer_numbers: bool) -> Self {
+        Self {
+            finished: false,
+            chars: CharLocations::new(chars),
+            peeked: None,
+            tsv,
+            infer_numbers,
+            header: None,
+            buf: VecDeque::new(),
+        }
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        match self.peeked.take() {
+            Some((_, ch)) => Some(ch),
+            None => self.chars.next(),
+        }
+    }
+
+    fn peek_char(&mut self) -> Option<char> {
+        if self.peeked.is_none() {
+            let location = self.chars.peek_location();
+            self.peeked = self.chars.next().map(|ch| (location, ch));
+        }
+        self.peeked.as_ref().map(|(_, ch)| *ch)
+    }
+
+    /// The location of the next character.
+    fn location(&self) -> Location {
+        match &self.peeked {
+            Some((location, _)) => location.clone(),
+            None => self.chars.peek_location(),
+        }
+    }
+
+    fn delimiter(&self) -> char {
+        if self.tsv {
+            '\t'
+        } else {
+            ','
+        }
+    }
+
+    /// Reads the rest of the current line's `\r\n` or `\n`, returning
+    /// whether there was one. A `\r` at the very end of the input ends
+    /// the line too.
+    fn end_of_line(&mut self, ch: char) -> bool {
+        match ch {
+            '\n' => true,
+            '\r' => match self.peek_char() {
+                Some('\n') => {
+                    self.peeked = None;
+                    true
+                }
+                next => next.is_none(),
+            },
+            _ => false,
+        }
+    }
+
+    /// Reads a quoted CSV field, after its opening quote.
+    fn quoted_field(&mut self) -> Result<String, JQErr> {
+        let mut field = String::new();
+        loop {
+            match self.next_char() {
+                None => return Err(JQErr::UnexpectedEOF),
+                Some('"') if self.peek_char() == Some('"') => {
+                    self.peeked = None;
+                    field.push('"');
+                }
+                Some('"') => return Ok(field),
+                Some(ch) => field.push(ch),
+            }
+        }
+    }
+
+    /// Reads the next row, or [`None`] at the end of the input. The row's
+    /// location is where it starts, after any blank lines before it.
+    fn row(&mut self) -> Result<Option<(Location, Row)>, JQErr> {
+        let mut start = self.location();
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        loop {
+            let location = self.location();
+            let ch = self.next_char();
+
+            let end_of_field = match ch {
+                None => true,
+                Some(ch) if ch == self.delimiter() => true,
+                Some(ch) => self.end_of_line(ch),
+            };
+            if end_of_field {
+                fields.push((std::mem::take(&mut field), quoted));
+                quoted = false;
+                if ch == Some(self.delimiter()) {
+                    continue;
+                }
+
+                // Blank lines don't hold any rows.
+                if matches!(&fields[..], [(field, false)] if field.is_empty()) {
+                    if ch.is_none() {
+                        return Ok(None);
+                    }
+                    fields.clear();
+                    start = self.location();
+                    continue;
+                }
+                return Ok(Some((start, fields)));
+            }
+
+            match ch {
+                Some('"') if !self.tsv && field.is_empty() && !quoted => {
+                    field = self.quoted_field()?;
+                    quoted = true;
+                }
+                Some('\\') if self.tsv => match self.next_char() {
+                    Some('t') => field.push('\t'),
+                    Some('n') => field.push('\n'),
+                    Some('r') => field.push('\r'),
+                    Some('\\') => field.push('\\'),
+                    Some(other) => field.extend(['\\', other]),
+                    None => field.push('\\'),
+                },
+                // Nothing but a delimiter or newline can follow a quoted
+                // field.
+                Some(_) if quoted => return Err(JQErr::UnexpectedCharacter(location)),
+                Some(ch) => field.push(ch),
+                None => unreachable!("the end of the input ends the field"),
+            }
+        }
+    }
+
+    fn field(&self, (field, quoted): (String, bool)) -> Value {
+        if self.infer_numbers && !quoted && !field.starts_with('+') {
+            if let Some(Token::Number(num)) = decimal_token(&field) {
+                return Value::Number(num);
+            }
+        }
+        Value::String(field.into())
+    }
+
+    /// Converts a row into an array, or an object keyed by the header if
+    /// there is one.
+    fn value(&self, row: Row, location: Location) -> Result<Value, JQErr> {
+        let header = match &self.header {
+            None => {
+                return Ok(Value::Array(
+                    row.into_iter().map(|field| self.field(field)).collect(),
+                ))
+            }
+            Some(header) => header,
+        };
+
+        if row.len() > header.len() {
+            return Err(JQErr::TooManyFields {
+                location,
+                fields: row.len(),
+                header: header.len(),
+            });
+        }
+
+        let mut row = row.into_iter();
+        Ok(header
+            .iter()
+            .map(|key| {
+                let value = row.next().map(|field| self.field(field));
+                (key.clone(), value.unwrap_or(Value::Null))
+            })
+            .collect::<ObjectEntries>()
+            .into_value())
+    }
+}
+
+impl<const HEADER: bool, Chars> Iterator for CsvRows<HEADER, Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(token) = self.buf.pop_front() {
+                return Some(Ok(token));
+            }
+
+            if self.finished {
+                return None;
+            }
+
+            let result = self.row().and_then(|row| match row {
+                None => Ok(None),
+                Some((_, row)) if HEADER && self.header.is_none() => {
+                    self.header = Some(row.into_iter().map(|(key, _)| key.into()).collect());
+                    Ok(Some(None))
+                }
+                Some((location, row)) => self.value(row, location).map(|value| Some(Some(value))),
+            });
+
+            match result {
+                Ok(Some(Some(value))) => value.push_tokens(&mut self.buf),
+                // The header row doesn't produce a value.
+                Ok(Some(None)) => {}
+                Ok(None) => self.finished = true,
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+impl<const HEADER: bool, Chars> SanitizedJQStream for CsvRows<HEADER, Chars> where
+    Chars: Iterator<Item = char>
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CharStream, JQErr, Location, SanitizedJQStream};
+
+    #[test]
+    fn reads_quoted_fields() {
+        let csv = "a,\"b,c\",\"say \"\"hi\"\"\"\r\n\"multi\nline\",,\"\"\n";
+
+        assert_eq!(
+            csv.chars().into_csv_rows(false).to_string().unwrap(),
+            "[\"a\",\"b,c\",\"say \\\"hi\\\"\"]\n[\"multi\\nline\",\"\",\"\"]\n"
+        );
+    }
+
+    #[test]
+    fn rejects_text_after_a_quoted_field() {
+        let mut rows = "\"a\"b,c\n".chars().into_csv_rows(false);
+
+        assert!(matches!(
+            rows.next(),
+            Some(Err(JQErr::UnexpectedCharacter(location))) if location == Location::new(0, 3)
+        ));
+    }
+
+    #[test]
+    fn fails_on_an_unterminated_quote() {
+        let mut rows = "a,\"b\n".chars().into_csv_rows(false);
+
+        assert!(matches!(rows.next(), Some(Err(JQErr::UnexpectedEOF))));
+    }
+
+    #[test]
+    fn undoes_tsv_escapes() {
+        let tsv = "a\\tb\tc\\\\d\te\\nf\n";
+
+        assert_eq!(
+            tsv.chars().into_tsv_rows(false).to_string().unwrap(),
+            "[\"a\\tb\",\"c\\\\d\",\"e\\nf\"]\n"
+        );
+    }
+
+    #[test]
+    fn keys_rows_by_the_header() {
+        let csv = "id,name\n\n1,x\n2\n";
+
+        assert_eq!(
+            csv.chars().into_csv_objects(true).to_string().unwrap(),
+            "{\"id\":1,\"name\":\"x\"}\n{\"id\":2,\"name\":null}\n"
+        );
+    }
+
+    #[test]
+    fn only_infers_valid_numbers() {
+        let csv = "1,-2.5e3,007,+1,\"3\",1.\n";
+
+        assert_eq!(
+            csv.chars().into_csv_rows(true).to_string().unwrap(),
+            "[1,-2.5e3,\"007\",\"+1\",\"3\",\"1.\"]\n"
+        );
+    }
+
+    #[test]
+    fn reports_where_a_row_with_too_many_fields_starts() {
+        let mut rows = "a,b\n1,2\n\n\n\n3,4,5\n".chars().into_csv_objects(false);
+
+        assert!(rows.by_ref().take(9).all(|token| token.is_ok()));
+        assert!(matches!(
+            rows.next(),
+            Some(Err(JQErr::TooManyFields {
+                location,
+                fields: 3,
+                header: 2,
+            })) if location == Location::new(5, 0)
+        ));
+    }
+
+    #[test]
+    fn strips_a_carriage_return_at_the_end_of_the_input() {
+        assert_eq!(
+            "a,b\r".chars().into_csv_rows(false).to_string().unwrap(),
+            "[\"a\",\"b\"]\n"
+        );
+        assert_eq!(
+            "a,\"b\"\r"
+                .chars()
+                .into_csv_rows(false)
+                .to_string()
+                .unwrap(),
+            "[\"a\",\"b\"]\n"
+        );
+    }
+}
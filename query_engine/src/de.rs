@@ -0,0 +1,488 @@
+use serde::de::{
+    self, value::StringDeserializer, DeserializeOwned, DeserializeSeed, EnumAccess,
+    IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+
+use crate::{JQErr, JQStream, Sanitized, SanitizedJQStream, Token};
+
+/// Deserializes exactly one value out of a stream, such as the output of a
+/// jq filter, into any type implementing [`serde::Deserialize`].
+pub fn from_stream<T, Stream>(stream: Stream) -> Result<T, JQErr>
+where
+    T: DeserializeOwned,
+    Stream: SanitizedJQStream,
+{
+    let mut deserializer = Deserializer::new(stream);
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// A [`serde::Deserializer`] which reads values straight out of a token
+/// stream, without materializing them first. Every top-level value in the
+/// stream can be deserialized in turn.
+pub struct Deserializer<Stream>
+where
+    Stream: JQStream,
+{
+    stream: Sanitized<Stream>,
+    peeked: Option<Token>,
+}
+
+impl<Stream> Deserializer<Stream>
+where
+    Stream: JQStream,
+{
+    pub fn new(stream: Stream) -> Self {
+        Self {
+            stream: stream.sanitize(),
+            peeked: None,
+        }
+    }
+
+    /// Checks that every value in the stream has been deserialized.
+    pub fn end(&mut self) -> Result<(), JQErr> {
+        match self.peek()? {
+            None => Ok(()),
+            Some(_) => Err(JQErr::StreamOperationFailed(
+                "Expected a single value, but the stream has more".into(),
+            )),
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<&Token>, JQErr> {
+        if self.peeked.is_none() {
+            self.peeked = self.stream.next().transpose()?;
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    fn next(&mut self) -> Result<Token, JQErr> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.stream.next().ok_or(JQErr::UnexpectedEOF)?,
+        }
+    }
+
+    /// Skips the comma between members of a container, returning whether
+    /// the container ended instead.
+    fn next_member(&mut self, end: Token) -> Result<bool, JQErr> {
+        if matches!(self.peek()?, Some(Token::Comma)) {
+            self.next()?;
+        }
+
+        let end = std::mem::discriminant(&end);
+        if self.peek()?.map(std::mem::discriminant) == Some(end) {
+            self.next()?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Checks that a container which a visitor has stopped reading has no
+    /// members left.
+    fn end_container(&mut self, end: Token) -> Result<(), JQErr> {
+        if self.next_member(end)? {
+            return Err(de::Error::custom("trailing members"));
+        }
+        Ok(())
+    }
+}
+
+impl<'de, Stream> de::Deserializer<'de> for &mut Deserializer<Stream>
+where
+    Stream: JQStream,
+{
+    type Error = JQErr;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.next()? {
+            Token::Null => visitor.visit_unit(),
+            Token::True => visitor.visit_bool(true),
+            Token::False => visitor.visit_bool(false),
+            Token::Number(num) => {
+                if let Ok(num) = num.parse::<u64>() {
+                    visitor.visit_u64(num)
+                } else if let Ok(num) = num.parse::<i64>() {
+                    visitor.visit_i64(num)
+                } else {
+                    match num.parse::<f64>() {
+                        Ok(num) => visitor.visit_f64(num),
+                        Err(_) => Err(JQErr::InvalidStream),
+                    }
+                }
+            }
+            // Arithmetic always produces floats, so whole numbers are
+            // visited as integers to let them land in integer fields.
+            Token::ParsedNumber(num)
+                if num.fract() == 0.0 && num >= 0.0 && num < u64::MAX as f64 =>
+            {
+                visitor.visit_u64(num as u64)
+            }
+            Token::ParsedNumber(num)
+                if num.fract() == 0.0 && num >= i64::MIN as f64 && num < 0.0 =>
+            {
+                visitor.visit_i64(num as i64)
+            }
+            Token::ParsedNumber(num) => visitor.visit_f64(num),
+            Token::String(str) => visitor.visit_str(&str),
+            Token::ArrayStart => {
+                let mut seq = Members {
+                    de: self,
+                    ended: false,
+                };
+                let value = visitor.visit_seq(&mut seq)?;
+                if !seq.ended {
+                    self.end_container(Token::ArrayEnd)?;
+                }
+                Ok(value)
+            }
+            Token::ObjectStart => {
+                let mut map = Members {
+                    de: self,
+                    ended: false,
+                };
+                let value = visitor.visit_map(&mut map)?;
+                if !map.ended {
+                    self.end_container(Token::ObjectEnd)?;
+                }
+                Ok(value)
+            }
+            Token::ArrayEnd | Token::ObjectEnd | Token::Colon | Token::Comma => {
+                Err(JQErr::InvalidStream)
+            }
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if matches!(self.peek()?, Some(Token::Null)) {
+            self.next()?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.next()? {
+            // Unit variants are written as just their name.
+            Token::String(variant) => visitor.visit_enum(variant.to_string().into_deserializer()),
+            // Other variants are written as `{"Variant": ...}`.
+            Token::ObjectStart => {
+                let value = visitor.visit_enum(&mut *self)?;
+                self.end_container(Token::ObjectEnd)?;
+                Ok(value)
+            }
+            _ => Err(de::Error::custom(
+                "expected a string or an object for an enum",
+            )),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+/// Reads the members of an array or object for a visitor.
+struct Members<'a, Stream>
+where
+    Stream: JQStream,
+{
+    de: &'a mut Deserializer<Stream>,
+    ended: bool,
+}
+
+impl<'de, Stream> SeqAccess<'de> for &mut Members<'_, Stream>
+where
+    Stream: JQStream,
+{
+    type Error = JQErr;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.ended || !self.de.next_member(Token::ArrayEnd)? {
+            self.ended = true;
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+impl<'de, Stream> MapAccess<'de> for &mut Members<'_, Stream>
+where
+    Stream: JQStream,
+{
+    type Error = JQErr;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.ended || !self.de.next_member(Token::ObjectEnd)? {
+            self.ended = true;
+            return Ok(None);
+        }
+
+        match self.de.next()? {
+            Token::String(key) => seed
+                .deserialize(MapKeyDeserializer {
+                    key: key.to_string(),
+                })
+                .map(Some),
+            _ => Err(JQErr::InvalidStream),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.de.next()? {
+            Token::Colon => seed.deserialize(&mut *self.de),
+            _ => Err(JQErr::InvalidStream),
+        }
+    }
+}
+
+impl<'de, Stream> EnumAccess<'de> for &mut Deserializer<Stream>
+where
+    Stream: JQStream,
+{
+    type Error = JQErr;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = match self.next()? {
+            Token::String(variant) => variant,
+            Token::ObjectEnd => return Err(de::Error::custom("expected an enum variant")),
+            _ => return Err(JQErr::InvalidStream),
+        };
+
+        match self.next()? {
+            Token::Colon => {}
+            _ => return Err(JQErr::InvalidStream),
+        }
+
+        let variant = seed.deserialize(StringDeserializer::new(variant.to_string()))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, Stream> VariantAccess<'de> for &mut Deserializer<Stream>
+where
+    Stream: JQStream,
+{
+    type Error = JQErr;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+/// Reads object keys. Since JSON keys are always strings, keys read as
+/// integers or booleans are parsed out of the string, matching what
+/// [`to_stream`](crate::to_stream) writes for them.
+struct MapKeyDeserializer {
+    key: String,
+}
+
+macro_rules! deserialize_parsed_key {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                match self.key.parse() {
+                    Ok(key) => visitor.$visit(key),
+                    Err(_) => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for MapKeyDeserializer {
+    type Error = JQErr;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_string(self.key)
+    }
+
+    deserialize_parsed_key! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_enum(self.key.into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        f32 f64 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use crate::{from_stream, CharStream, Deserializer, JQErr, SanitizedJQStream};
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Item {
+        id: u8,
+        #[serde(default)]
+        label: Option<String>,
+    }
+
+    #[test]
+    fn reads_values_straight_from_parsed_json() {
+        let items: Vec<Item> = from_stream(
+            r#"{"items": [{"id": 1, "label": "a"}, {"id": 2, "label": null}, {"id": 3}]}"#
+                .chars()
+                .into_json_tokens()
+                .at_key("items"),
+        )
+        .unwrap();
+        assert_eq!(
+            items,
+            vec![
+                Item {
+                    id: 1,
+                    label: Some("a".to_string())
+                },
+                Item { id: 2, label: None },
+                Item { id: 3, label: None },
+            ]
+        );
+
+        let keys: HashMap<u16, i64> =
+            from_stream(r#"{"1": -1, "65535": 9007199254740993}"#.chars().into_json_tokens())
+                .unwrap();
+        assert_eq!(keys, HashMap::from([(1, -1), (65535, 9007199254740993)]));
+    }
+
+    #[test]
+    fn reads_each_value_of_a_stream_in_turn() {
+        let mut deserializer = Deserializer::new("1 [true] \"a\"".chars().into_json_tokens());
+        assert_eq!(u8::deserialize(&mut deserializer).unwrap(), 1);
+        assert_eq!(Vec::<bool>::deserialize(&mut deserializer).unwrap(), [true]);
+        assert_eq!(String::deserialize(&mut deserializer).unwrap(), "a");
+        assert!(deserializer.end().is_ok());
+    }
+
+    #[test]
+    fn rejects_mismatched_and_extra_values() {
+        assert!(from_stream::<u8, _>("256".chars().into_json_tokens()).is_err());
+        assert!(from_stream::<Item, _>(r#"{"label": "a"}"#.chars().into_json_tokens()).is_err());
+        assert!(matches!(
+            from_stream::<u8, _>("1 2".chars().into_json_tokens()),
+            Err(JQErr::StreamOperationFailed(msg))
+                if &*msg == "Expected a single value, but the stream has more"
+        ));
+        assert!(matches!(
+            from_stream::<Vec<u8>, _>("[1,".chars().into_json_tokens()),
+            Err(JQErr::UnexpectedEOF)
+        ));
+    }
+}
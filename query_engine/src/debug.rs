@@ -0,0 +1,112 @@
+use std::collections::VecDeque;
+
+use crate::{value::Value, Filter, JQErr, JQStream, Sanitized, SanitizedJQStream, Token};
+
+/// A struct for handling the `debug` and `debug(msg)` jq queries. Every
+/// input is passed through unchanged after writing `["DEBUG:",<value>]` to
+/// stderr, where the value is the input itself or each value of `msg`.
+pub struct Debug<Stream, Msg>
+where
+    Stream: JQStream,
+    Msg: Filter,
+{
+    finished: bool,
+    stream: Sanitized<Stream>,
+    msg: Option<Msg>,
+    buf: VecDeque<Token>,
+}
+
+impl<Stream, Msg> Debug<Stream, Msg>
+where
+    Stream: JQStream,
+    Msg: Filter,
+{
+    pub(crate) fn new(stream: Stream, msg: Option<Msg>) -> Self {
+        Self {
+            finished: false,
+            stream: stream.sanitize(),
+            msg,
+            buf: VecDeque::new(),
+        }
+    }
+
+    fn log(value: Value) -> Result<(), JQErr> {
+        let message = Value::Array(vec![Value::String("DEBUG:".into()), value]);
+        eprintln!("{}", message.to_json()?);
+        Ok(())
+    }
+
+    fn debug(&mut self, input: &Value) -> Result<(), JQErr> {
+        match &mut self.msg {
+            None => Self::log(input.clone()),
+            Some(msg) => {
+                let mut messages = msg.apply(input.clone().into_stream()).sanitize();
+                while let Some(message) = Value::next_from(&mut messages) {
+                    Self::log(message?)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<Stream, Msg> Iterator for Debug<Stream, Msg>
+where
+    Stream: JQStream,
+    Msg: Filter,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.buf.pop_front() {
+            return Some(Ok(token));
+        }
+
+        if self.finished {
+            return None;
+        }
+
+        let result =
+            Value::next_from(&mut self.stream)?.and_then(|input| self.debug(&input).map(|_| input));
+        match result {
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+            Ok(value) => {
+                value.push_tokens(&mut self.buf);
+                self.buf.pop_front().map(Ok)
+            }
+        }
+    }
+}
+
+impl<Stream, Msg> SanitizedJQStream for Debug<Stream, Msg>
+where
+    Stream: JQStream,
+    Msg: Filter,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CharStream, SanitizedJQStream, ValueTokens};
+
+    #[test]
+    fn passes_every_input_through() {
+        let json = r#"{"a": [1, 2]} "b" null"#;
+        let expected = "{\"a\":[1,2]}\n\"b\"\nnull\n";
+        assert_eq!(
+            json.chars().into_json_tokens().debug().to_string().unwrap(),
+            expected
+        );
+        assert_eq!(
+            json.chars()
+                .into_json_tokens()
+                .debug_msg(|value: ValueTokens| value.toarray())
+                .to_string()
+                .unwrap(),
+            expected
+        );
+    }
+}
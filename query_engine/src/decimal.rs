@@ -0,0 +1,149 @@
+use std::{cmp::Ordering, rc::Rc};
+
+#[cfg(feature = "decimal")]
+use bigdecimal::BigDecimal;
+
+use crate::value::Value;
+
+/// Whether adding and comparing number literals is exact, as reported by
+/// `have_decnum`. Without the `decimal` feature, both go through f64s like
+/// jq built without decNumber, though unmodified literals are still
+/// printed exactly as written either way.
+pub(crate) const HAVE_DECNUM: bool = cfg!(feature = "decimal");
+
+/// Exponents further out than this fall back to f64 arithmetic, since
+/// exact results for something like `1e1000000000 + 1` would be enormous.
+#[cfg(feature = "decimal")]
+const MAX_SCALE: i64 = 1000;
+
+#[cfg(feature = "decimal")]
+fn parse(literal: &str) -> Option<BigDecimal> {
+    let num = literal.parse::<BigDecimal>().ok()?;
+    (num.fractional_digit_count().abs() <= MAX_SCALE).then_some(num)
+}
+
+/// Adds two number literals exactly, producing the literal of their sum,
+/// or [`None`] if the f64 result should be used instead.
+#[cfg(feature = "decimal")]
+pub(crate) fn add(left: &str, right: &str) -> Option<Rc<str>> {
+    let sum = parse(left)? + parse(right)?;
+    // Like jq's `%.17g`, sums with 17 or more trailing zeros, such as
+    // `1e300 + 0`, are written with an exponent rather than every zero.
+    let normalized = sum.normalized();
+    if normalized.fractional_digit_count() <= -17 {
+        Some(normalized.to_scientific_notation().into())
+    } else {
+        Some(sum.to_plain_string().into())
+    }
+}
+
+#[cfg(not(feature = "decimal"))]
+pub(crate) fn add(_left: &str, _right: &str) -> Option<Rc<str>> {
+    None
+}
+
+/// Where a number falls when compared exactly. Every number maps to exactly
+/// one of these, so mixing literals with parsed f64s still sorts
+/// consistently: `nan` below everything, like jq, then the infinities around
+/// every finite value.
+#[cfg(feature = "decimal")]
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Exact {
+    Nan,
+    NegativeInfinity,
+    Finite(BigDecimal),
+    Infinity,
+}
+
+#[cfg(feature = "decimal")]
+impl Exact {
+    fn of(value: &Value) -> Self {
+        if let Value::Number(literal) = value {
+            if let Ok(num) = literal.parse::<BigDecimal>() {
+                return Exact::Finite(num);
+            }
+        }
+
+        match value.as_f64() {
+            Some(f64::INFINITY) => Exact::Infinity,
+            Some(f64::NEG_INFINITY) => Exact::NegativeInfinity,
+            // Finite f64s are exactly representable as decimals.
+            Some(num) => BigDecimal::try_from(num).map_or(Exact::Nan, Exact::Finite),
+            None => Exact::Nan,
+        }
+    }
+}
+
+/// Compares two numbers exactly, so that 64-bit IDs which round to the
+/// same f64 still sort apart, or returns [`None`] if their f64s should be
+/// compared instead.
+#[cfg(feature = "decimal")]
+pub(crate) fn cmp(left: &Value, right: &Value) -> Option<Ordering> {
+    Some(Exact::of(left).cmp(&Exact::of(right)))
+}
+
+#[cfg(not(feature = "decimal"))]
+pub(crate) fn cmp(_left: &Value, _right: &Value) -> Option<Ordering> {
+    None
+}
+
+#[cfg(all(test, feature = "decimal"))]
+mod tests {
+    use std::cmp::Ordering;
+
+    use crate::{value::Value, CharStream, SanitizedJQStream};
+
+    fn sum(json: &str) -> String {
+        json.chars().into_json_tokens().add().to_string().unwrap()
+    }
+
+    fn sort(json: &str) -> String {
+        json.chars().into_json_tokens().sort().to_string().unwrap()
+    }
+
+    #[test]
+    fn adds_literals_exactly() {
+        assert_eq!(sum("[0.1, 0.2]"), "0.3\n");
+        assert_eq!(sum("[9007199254740993, 1]"), "9007199254740994\n");
+        assert_eq!(sum("[1e300, 0]"), "1e300\n");
+    }
+
+    #[test]
+    fn compares_literals_and_parsed_numbers_in_one_domain() {
+        let above = Value::Number("9007199254740993".into());
+        let parsed = Value::ParsedNumber(9007199254740992.0);
+        let literal = Value::Number("9007199254740992".into());
+        assert_eq!(above.cmp(&parsed), Ordering::Greater);
+        assert_eq!(parsed.cmp(&literal), Ordering::Equal);
+        assert_eq!(above.cmp(&literal), Ordering::Greater);
+        assert_eq!(
+            Value::Number("0.1".into()).cmp(&Value::ParsedNumber(0.1)),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn sorts_nan_and_infinities_around_exact_numbers() {
+        let nan = Value::ParsedNumber(f64::NAN);
+        let infinity = Value::ParsedNumber(f64::INFINITY);
+        let huge = Value::Number("1e1000000000".into());
+        let tiny = Value::Number("1e-1000000000".into());
+        let zero = Value::ParsedNumber(-0.0);
+        assert_eq!(
+            nan.cmp(&Value::ParsedNumber(f64::NEG_INFINITY)),
+            Ordering::Less
+        );
+        assert_eq!(huge.cmp(&infinity), Ordering::Less);
+        assert_eq!(huge.cmp(&Value::ParsedNumber(f64::MAX)), Ordering::Greater);
+        assert_eq!(tiny.cmp(&zero), Ordering::Greater);
+        assert_eq!(zero.cmp(&Value::Number("0".into())), Ordering::Equal);
+    }
+
+    #[test]
+    fn sorts_large_ids_apart() {
+        assert_eq!(
+            sort("[9007199254740993, 9007199254740992, 1]"),
+            "[1,9007199254740992,9007199254740993]\n"
+        );
+    }
+}
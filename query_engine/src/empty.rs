@@ -0,0 +1,63 @@
+use crate::{JQStream, Sanitized, SanitizedJQStream};
+
+/// A struct for handling the `empty` jq query. The input is still read
+/// to the end so that any errors in it are reported, but no values are
+/// produced.
+pub struct Empty<Stream>
+where
+    Stream: JQStream,
+{
+    stream: Sanitized<Stream>,
+}
+
+impl<Stream> Empty<Stream>
+where
+    Stream: JQStream,
+{
+    pub(crate) fn new(stream: Stream) -> Self {
+        Self {
+            stream: stream.sanitize(),
+        }
+    }
+}
+
+impl<Stream> Iterator for Empty<Stream>
+where
+    Stream: JQStream,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Err(err) = self.stream.next()? {
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+impl<Stream> SanitizedJQStream for Empty<Stream> where Stream: JQStream {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CharStream, SanitizedJQStream};
+
+    #[test]
+    fn produces_nothing_but_still_reports_errors() {
+        assert_eq!(
+            "1 [2]"
+                .chars()
+                .into_json_tokens()
+                .empty()
+                .to_string()
+                .unwrap(),
+            ""
+        );
+        assert!("1 }"
+            .chars()
+            .into_json_tokens()
+            .empty()
+            .to_string()
+            .is_err());
+    }
+}
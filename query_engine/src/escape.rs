@@ -0,0 +1,159 @@
+use std::collections::VecDeque;
+
+/// Which characters are escaped when strings are written as JSON, on top
+/// of the quotes, backslashes and control characters which always are.
+///
+/// The default matches jq: `DEL` is written as `\u007f`, while `/` and
+/// anything outside of ASCII are written as they are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StringEscapes {
+    ascii: bool,
+    slash: bool,
+    del: bool,
+}
+
+impl StringEscapes {
+    /// Escapes only what JSON requires, leaving `DEL` as it is, like
+    /// RFC 8785 canonical JSON.
+    pub const fn minimal() -> Self {
+        Self {
+            ascii: false,
+            slash: false,
+            del: false,
+        }
+    }
+
+    /// Sets whether characters outside of ASCII are escaped, like jq's
+    /// `--ascii-output` flag. Characters outside of the basic multilingual
+    /// plane are written as UTF-16 surrogate pairs (`\ud83d\ude00`).
+    pub const fn with_ascii_output(mut self, ascii: bool) -> Self {
+        self.ascii = ascii;
+        self
+    }
+
+    /// Sets whether `/` is written as `\/`, which keeps `</script>` out
+    /// of JSON embedded in HTML.
+    pub const fn with_escaped_slash(mut self, slash: bool) -> Self {
+        self.slash = slash;
+        self
+    }
+
+    /// Sets whether the `DEL` control character is written as `\u007f`.
+    pub const fn with_escaped_del(mut self, del: bool) -> Self {
+        self.del = del;
+        self
+    }
+}
+
+impl Default for StringEscapes {
+    fn default() -> Self {
+        Self::minimal().with_escaped_del(true)
+    }
+}
+
+/// Writes a string as a quoted JSON string. Escapes use the short forms
+/// where they exist, and lowercase hex otherwise (`\u001f`), like jq.
+pub(crate) fn write_string(str: &str, escapes: StringEscapes, out: &mut VecDeque<char>) {
+    out.push_back('"');
+    for ch in str.chars() {
+        match ch {
+            '"' => out.extend(['\\', '"']),
+            '\\' => out.extend(['\\', '\\']),
+            '\u{8}' => out.extend(['\\', 'b']),
+            '\t' => out.extend(['\\', 't']),
+            '\n' => out.extend(['\\', 'n']),
+            '\u{c}' => out.extend(['\\', 'f']),
+            '\r' => out.extend(['\\', 'r']),
+            '/' if escapes.slash => out.extend(['\\', '/']),
+            '\0'..='\u{1f}' => write_unicode_escape(ch, out),
+            '\u{7f}' if escapes.del => write_unicode_escape(ch, out),
+            '\u{80}'.. if escapes.ascii => write_unicode_escape(ch, out),
+            ch => out.push_back(ch),
+        }
+    }
+    out.push_back('"');
+}
+
+/// Writes `\uXXXX` escapes for each of the UTF-16 code units of a
+/// character.
+fn write_unicode_escape(ch: char, out: &mut VecDeque<char>) {
+    for unit in ch.encode_utf16(&mut [0; 2]) {
+        out.extend(format!("\\u{:04x}", unit).chars());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::write_string;
+    use crate::{CharStream, JQErr, SanitizedJQStream, StringEscapes};
+
+    const STRING: &str = "\"\\\u{8}\t\n\u{c}\r\0\u{1f}/\u{7f}é😀";
+
+    fn escape(escapes: StringEscapes) -> String {
+        let mut out = VecDeque::new();
+        write_string(STRING, escapes, &mut out);
+        out.into_iter().collect()
+    }
+
+    fn collect(chars: impl Iterator<Item = Result<char, JQErr>>) -> String {
+        chars.collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn escapes_like_jq_by_default() {
+        assert_eq!(
+            escape(StringEscapes::default()),
+            r#""\"\\\b\t\n\f\r\u0000\u001f/\u007fé😀""#
+        );
+        assert_eq!(
+            escape(StringEscapes::minimal()),
+            "\"\\\"\\\\\\b\\t\\n\\f\\r\\u0000\\u001f/\u{7f}é😀\""
+        );
+    }
+
+    #[test]
+    fn escapes_slashes_and_non_ascii_when_asked() {
+        assert_eq!(
+            escape(
+                StringEscapes::default()
+                    .with_escaped_slash(true)
+                    .with_ascii_output(true)
+            ),
+            r#""\"\\\b\t\n\f\r\u0000\u001f\/\u007f\u00e9\ud83d\ude00""#
+        );
+    }
+
+    #[test]
+    fn every_writer_shares_the_escapes() {
+        let json = r#"{"k/é": ["a\u007f/é", "\u0001"]}"#;
+        let escapes = StringEscapes::default().with_ascii_output(true);
+        assert_eq!(
+            collect(json.chars().into_json_tokens().to_chars_compact()),
+            "{\"k/é\":[\"a\\u007f/é\",\"\\u0001\"]}\n"
+        );
+        assert_eq!(
+            collect(
+                json.chars()
+                    .into_json_tokens()
+                    .to_chars_compact()
+                    .with_escapes(escapes)
+            ),
+            "{\"k/\\u00e9\":[\"a\\u007f/\\u00e9\",\"\\u0001\"]}\n"
+        );
+        assert_eq!(
+            collect(
+                json.chars()
+                    .into_json_tokens()
+                    .to_chars_pretty()
+                    .with_escapes(escapes)
+            ),
+            "{\n  \"k/\\u00e9\": [\n    \"a\\u007f/\\u00e9\",\n    \"\\u0001\"\n  ]\n}"
+        );
+        assert_eq!(
+            json.chars().into_json_tokens().to_string_pretty().unwrap(),
+            "{\n  \"k/é\": [\n    \"a\\u007f/é\",\n    \"\\u0001\"\n  ]\n}"
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use crate::{budget::Spending, value::Value, JQErr, JQStream, ValueTokens};
+
+/// A jq filter which can be passed as an argument to builtins such as
+/// `sort_by(f)` or `any(generator; condition)`. Any closure which takes
+/// the tokens of a single value and returns a [`JQStream`] is a filter,
+/// so the usual way to create one is `|value: ValueTokens| value.at_key("a")`.
+pub trait Filter {
+    type Output: JQStream;
+
+    /// Runs this filter against a single input value.
+    fn apply(&mut self, input: ValueTokens) -> Self::Output;
+}
+
+impl<F, Out> Filter for F
+where
+    F: FnMut(ValueTokens) -> Out,
+    Out: JQStream,
+{
+    type Output = Out;
+
+    fn apply(&mut self, input: ValueTokens) -> Self::Output {
+        self(input)
+    }
+}
+
+/// The `.` filter, which produces its input unchanged.
+pub struct Identity;
+
+impl Filter for Identity {
+    type Output = ValueTokens;
+
+    fn apply(&mut self, input: ValueTokens) -> Self::Output {
+        input
+    }
+}
+
+/// Runs `filter` against `input` and collects every value it produces,
+/// spending the active budget on every token of them.
+pub(crate) fn collect_outputs<F>(filter: &mut F, input: &Value) -> Result<Vec<Value>, JQErr>
+where
+    F: Filter,
+{
+    Value::all_from(Spending::new(filter.apply(input.clone().into_stream())))
+}
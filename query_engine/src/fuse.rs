@@ -36,13 +36,13 @@ where
         match self.stream.next() {
             None => {
                 self.done = true;
-                return None;
+                None
             }
             Some(Err(err)) => {
                 self.done = true;
-                return Some(Err(err));
+                Some(Err(err))
             }
-            Some(Ok(token_kind)) => return Some(Ok(token_kind)),
+            Some(Ok(token_kind)) => Some(Ok(token_kind)),
         }
     }
 }
@@ -0,0 +1,106 @@
+use crate::{JQErr, JQStream, Sanitized, SanitizedJQStream};
+
+/// A struct for handling the `input` and `inputs` jq queries. Like jq,
+/// the values are read from the same stream as the inputs themselves:
+/// `input` replaces every input with the one after it, which is then
+/// consumed, and `inputs` replaces the first input with all of the rest.
+pub struct Input<const ALL: bool, Stream>
+where
+    Stream: JQStream,
+{
+    finished: bool,
+    stream: Sanitized<Stream>,
+    in_value: bool,
+}
+
+impl<const ALL: bool, Stream> Input<ALL, Stream>
+where
+    Stream: JQStream,
+{
+    pub(crate) fn new(stream: Stream) -> Self {
+        Self {
+            finished: false,
+            stream: stream.sanitize(),
+            in_value: false,
+        }
+    }
+
+    /// Skips over the current input, reporting whether there was one.
+    fn skip_input(&mut self) -> Result<bool, JQErr> {
+        match self.stream.next() {
+            None => Ok(false),
+            Some(Err(err)) => Err(err),
+            Some(Ok(_)) => self.stream.skip_value().map(|_| true),
+        }
+    }
+}
+
+impl<const ALL: bool, Stream> Iterator for Input<ALL, Stream>
+where
+    Stream: JQStream,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let starting = !self.in_value;
+        if starting {
+            match self.skip_input() {
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+                Ok(false) => {
+                    self.finished = true;
+                    return None;
+                }
+                Ok(true) => {}
+            }
+        }
+
+        match self.stream.next() {
+            None if starting && !ALL => {
+                self.finished = true;
+                Some(Err(JQErr::StreamOperationFailed("No more inputs".into())))
+            }
+            None => {
+                self.finished = true;
+                None
+            }
+            Some(Err(err)) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+            Some(Ok(token)) => {
+                // `inputs` never goes back to skipping, as every value after
+                // the first one is passed through.
+                self.in_value = ALL || !self.stream.get_path().is_empty();
+                Some(Ok(token))
+            }
+        }
+    }
+}
+
+impl<const ALL: bool, Stream> SanitizedJQStream for Input<ALL, Stream> where Stream: JQStream {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_support::values, SanitizedJQStream};
+
+    #[test]
+    fn replaces_each_input_with_the_next() {
+        assert_eq!(
+            values("1 [2] 3 {\"a\": 4}").input().to_string().unwrap(),
+            "[2]\n{\"a\":4}\n"
+        );
+    }
+
+    #[test]
+    fn replaces_the_first_input_with_the_rest() {
+        assert_eq!(values("1 [2] 3").inputs().to_string().unwrap(), "[2]\n3\n");
+        assert_eq!(values("1").inputs().to_string().unwrap(), "");
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::VecDeque;
+
+use crate::{
+    filter::collect_outputs, value::Value, ExecutionBudget, Filter, JQErr, JQStream, Sanitized,
+    SanitizedJQStream, Token,
+};
+
+/// The builtins which repeatedly feed a value back through an update.
+pub(crate) enum IterateMode {
+    /// `while(cond; update)`.
+    While,
+    /// `until(cond; update)`.
+    Until,
+    /// `repeat(update)`.
+    Repeat,
+}
+
+/// A pending piece of work while iterating.
+enum Frame<Outputs> {
+    /// A value which still has to be run through the loop body.
+    Value(Value),
+    /// The remaining values produced by a call to `update`.
+    Outputs(Outputs),
+    /// A value which is ready to be output.
+    Emit(Value),
+}
+
+/// A struct for handling the `while(cond; update)`, `until(cond; update)`
+/// and `repeat(update)` jq queries.
+///
+/// The recursion is evaluated depth first and lazily, just like jq does,
+/// so `repeat(update)` can produce an unbounded number of values as long
+/// as its consumer stops pulling at some point.
+pub struct Iterate<Stream, Cond, Update>
+where
+    Stream: JQStream,
+    Cond: Filter,
+    Update: Filter,
+{
+    finished: bool,
+    stream: Sanitized<Stream>,
+    cond: Cond,
+    update: Update,
+    mode: IterateMode,
+    stack: Vec<Frame<Sanitized<Update::Output>>>,
+    buf: VecDeque<Token>,
+}
+
+impl<Stream, Cond, Update> Iterate<Stream, Cond, Update>
+where
+    Stream: JQStream,
+    Cond: Filter,
+    Update: Filter,
+{
+    pub(crate) fn new(stream: Stream, cond: Cond, update: Update, mode: IterateMode) -> Self {
+        Self {
+            finished: false,
+            stream: stream.sanitize(),
+            cond,
+            update,
+            mode,
+            stack: Vec::new(),
+            buf: VecDeque::new(),
+        }
+    }
+
+    fn updated(&mut self, value: Value) -> Frame<Sanitized<Update::Output>> {
+        Frame::Outputs(self.update.apply(value.into_stream()).sanitize())
+    }
+
+    /// Runs `value` through the loop body, pushing the resulting work onto
+    /// the stack in reverse so that it is popped off in order.
+    fn expand(&mut self, value: Value) -> Result<(), JQErr> {
+        let mut frames = Vec::new();
+        match self.mode {
+            IterateMode::Repeat => {
+                frames.push(Frame::Emit(value.clone()));
+                frames.push(self.updated(value));
+            }
+            IterateMode::While => {
+                for cond in collect_outputs(&mut self.cond, &value)? {
+                    if cond.is_truthy() {
+                        frames.push(Frame::Emit(value.clone()));
+                        frames.push(self.updated(value.clone()));
+                    }
+                }
+            }
+            IterateMode::Until => {
+                for cond in collect_outputs(&mut self.cond, &value)? {
+                    if cond.is_truthy() {
+                        frames.push(Frame::Emit(value.clone()));
+                    } else {
+                        frames.push(self.updated(value.clone()));
+                    }
+                }
+            }
+        }
+
+        self.stack.extend(frames.into_iter().rev());
+        Ok(())
+    }
+
+    /// Runs the loop until it produces its next value, spending the
+    /// active budget on every turn, so that `until(cond; update)` is
+    /// stopped even while it produces nothing. Each value `update`
+    /// produces is charged to the budget's maximum value size as it's
+    /// materialized.
+    fn next_value(&mut self) -> Option<Result<Value, JQErr>> {
+        let budget = ExecutionBudget::active();
+        loop {
+            if let Err(err) = budget.step() {
+                return Some(Err(err));
+            }
+
+            match self.stack.pop() {
+                None => match Value::next_from(&mut self.stream)? {
+                    Err(err) => return Some(Err(err)),
+                    Ok(input) => self.stack.push(Frame::Value(input)),
+                },
+                Some(Frame::Emit(value)) => return Some(Ok(value)),
+                Some(Frame::Value(value)) => {
+                    if let Err(err) = self.expand(value) {
+                        return Some(Err(err));
+                    }
+                }
+                Some(Frame::Outputs(mut outputs)) => {
+                    match budget.next_value(&mut outputs, &mut 0) {
+                        None => {}
+                        Some(Err(err)) => return Some(Err(err)),
+                        Some(Ok(value)) => {
+                            self.stack.push(Frame::Outputs(outputs));
+                            self.stack.push(Frame::Value(value));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<Stream, Cond, Update> Iterator for Iterate<Stream, Cond, Update>
+where
+    Stream: JQStream,
+    Cond: Filter,
+    Update: Filter,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.buf.pop_front() {
+            return Some(Ok(token));
+        }
+
+        if self.finished {
+            return None;
+        }
+
+        match self.next_value()? {
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+            Ok(value) => {
+                value.push_tokens(&mut self.buf);
+                self.buf.pop_front().map(Ok)
+            }
+        }
+    }
+}
+
+impl<Stream, Cond, Update> SanitizedJQStream for Iterate<Stream, Cond, Update>
+where
+    Stream: JQStream,
+    Cond: Filter,
+    Update: Filter,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_support::values, SanitizedJQStream, ValueTokens};
+
+    fn starts_with_a(value: ValueTokens) -> impl Iterator<Item = crate::Item> {
+        value.startswith("a")
+    }
+
+    fn drop_an_a(value: ValueTokens) -> impl Iterator<Item = crate::Item> {
+        value.ltrimstr("a")
+    }
+
+    #[test]
+    fn loops_while_the_condition_holds() {
+        assert_eq!(
+            values(r#""aab" "b""#)
+                .json_while(starts_with_a, drop_an_a)
+                .to_string()
+                .unwrap(),
+            "\"aab\"\n\"ab\"\n"
+        );
+    }
+
+    #[test]
+    fn loops_until_the_condition_holds() {
+        assert_eq!(
+            values(r#""aab" "b""#)
+                .until(|value: ValueTokens| value.startswith("b"), drop_an_a)
+                .to_string()
+                .unwrap(),
+            "\"b\"\n\"b\"\n"
+        );
+    }
+
+    #[test]
+    fn repeats_lazily() {
+        assert_eq!(
+            values(r#""aab""#)
+                .repeat(drop_an_a)
+                .limit(4)
+                .to_string()
+                .unwrap(),
+            "\"aab\"\n\"ab\"\n\"b\"\n\"b\"\n"
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use std::{error::Error, fmt::Display, rc::Rc};
 
-use crate::Location;
+use crate::{ExecutionLimit, Location};
 
 #[derive(Debug, Clone)]
 pub enum JQErr {
@@ -20,10 +20,94 @@ pub enum JQErr {
     UnescapedEscapeCharacter(Location),
     /// Yielded if an illegal backslash escape sequence is encountered.
     InvalidEscapeSequence(Location),
+    /// Yielded when an object repeats a key and duplicate keys are
+    /// rejected. `first` and `second` are the locations of both keys.
+    DuplicateKey {
+        key: Rc<str>,
+        first: Location,
+        second: Location,
+    },
+    /// Yielded when arrays and objects are nested deeper than the
+    /// [`ParseLimits`](crate::ParseLimits) allow, at the bracket which
+    /// went too deep.
+    DepthLimitExceeded(Location),
+    /// Yielded when a string is longer than the
+    /// [`ParseLimits`](crate::ParseLimits) allow, at the start of it.
+    StringLimitExceeded(Location),
+    /// Yielded when a number is longer than the
+    /// [`ParseLimits`](crate::ParseLimits) allow, at the start of it.
+    NumberLimitExceeded(Location),
+    /// Yielded when the input has more tokens than the
+    /// [`ParseLimits`](crate::ParseLimits) allow, at the first token past
+    /// the limit.
+    TokenLimitExceeded(Location),
+    /// Yielded when an [`ExecutionBudget`](crate::ExecutionBudget) runs
+    /// out.
+    ExecutionLimitExceeded(ExecutionLimit),
+    /// Yielded when the [`CancellationToken`](crate::CancellationToken)
+    /// of an [`ExecutionBudget`](crate::ExecutionBudget) is cancelled.
+    Cancelled,
     StreamOperationFailed(Rc<str>),
+    /// Yielded by line-based readers such as NDJSON when one line fails to
+    /// parse. `line` is zero-based like [`Location`] and any location in
+    /// `err` is relative to the start of that line.
+    InvalidLine {
+        line: usize,
+        err: Box<JQErr>,
+    },
+    /// Yielded by the RFC 7464 JSON text sequence readers when one record
+    /// is malformed or truncated. `record` is the zero-based index of the
+    /// record. Any location in `err` is relative to the start of the record
+    /// for [`JsonSeqDocuments`](crate::JsonSeqDocuments), and to the start
+    /// of the input for
+    /// [`into_json_seq_tokens`](crate::CharStream::into_json_seq_tokens).
+    InvalidRecord {
+        record: usize,
+        err: Box<JQErr>,
+    },
+    /// Yielded by the YAML reader when its input is malformed or can't be
+    /// represented as JSON.
+    InvalidYaml {
+        location: Location,
+        msg: Rc<str>,
+    },
+    /// Yielded by the TOML reader when its input is malformed.
+    InvalidToml {
+        location: Location,
+        msg: Rc<str>,
+    },
+    /// Yielded by the CSV and TSV readers when a row has more fields than
+    /// the header, at the start of the row.
+    TooManyFields {
+        location: Location,
+        fields: usize,
+        header: usize,
+    },
+    /// Yielded by the binary decoders, such as CBOR, when their input is
+    /// malformed. `offset` is the index of the offending byte.
+    InvalidBinary {
+        format: &'static str,
+        offset: usize,
+        msg: Rc<str>,
+    },
 }
 
 impl Error for JQErr {}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for JQErr {
+    fn custom<T: Display>(msg: T) -> Self {
+        JQErr::StreamOperationFailed(msg.to_string().into())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::ser::Error for JQErr {
+    fn custom<T: Display>(msg: T) -> Self {
+        JQErr::StreamOperationFailed(msg.to_string().into())
+    }
+}
+
 impl Display for JQErr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -42,6 +126,12 @@ impl Display for JQErr {
             JQErr::InvalidEscapeSequence(loc) => {
                 write!(f, "Found invalid escape sequence at {}.", loc)
             }
+            JQErr::DuplicateKey { key, first, second } => {
+                write!(
+                    f,
+                    "Found duplicate key \"{key}\" at {second}, first seen at {first}."
+                )
+            }
             JQErr::UnescapedEscapeCharacter(loc) => {
                 write!(
                     f,
@@ -49,9 +139,62 @@ impl Display for JQErr {
                     loc
                 )
             }
+            JQErr::DepthLimitExceeded(loc) => {
+                write!(f, "Exceeded the maximum nesting depth at {}.", loc)
+            }
+            JQErr::StringLimitExceeded(loc) => {
+                write!(f, "Exceeded the maximum string length at {}.", loc)
+            }
+            JQErr::NumberLimitExceeded(loc) => {
+                write!(f, "Exceeded the maximum number length at {}.", loc)
+            }
+            JQErr::TokenLimitExceeded(loc) => {
+                write!(f, "Exceeded the maximum number of tokens at {}.", loc)
+            }
+            JQErr::ExecutionLimitExceeded(limit) => {
+                let limit = match limit {
+                    ExecutionLimit::Steps => "steps",
+                    ExecutionLimit::Outputs => "outputs",
+                    ExecutionLimit::ValueSize => "value size",
+                    ExecutionLimit::Deadline => "time",
+                };
+                write!(f, "Exceeded the execution limit on {limit}.")
+            }
+            JQErr::Cancelled => {
+                write!(f, "Execution was cancelled.")
+            }
             JQErr::StreamOperationFailed(msg) => {
                 write!(f, "error: {msg}")
             }
+            JQErr::InvalidLine { line, err } => {
+                write!(f, "Invalid JSON on line {line}: {err}")
+            }
+            JQErr::InvalidRecord { record, err } => {
+                write!(f, "Invalid JSON text sequence record {record}: {err}")
+            }
+            JQErr::InvalidYaml { location, msg } => {
+                write!(f, "Invalid YAML at {location}: {msg}")
+            }
+            JQErr::InvalidToml { location, msg } => {
+                write!(f, "Invalid TOML at {location}: {msg}")
+            }
+            JQErr::TooManyFields {
+                location,
+                fields,
+                header,
+            } => {
+                write!(
+                    f,
+                    "Row at {location} has {fields} fields, but the header only has {header}"
+                )
+            }
+            JQErr::InvalidBinary {
+                format,
+                offset,
+                msg,
+            } => {
+                write!(f, "Invalid {format} at byte {offset}: {msg}")
+            }
         }
     }
 }
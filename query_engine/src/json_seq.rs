@@ -0,0 +1,142 @@
+use crate::{value::Value, CompactChars, JQErr, JQStream, RawTokenStream, ValueTokens};
+
+/// The ASCII record separator which starts every RFC 7464 record.
+const RS: char = '\u{1e}';
+
+/// Reads an RFC 7464 JSON text sequence (`application/json-seq`, as
+/// produced by jq's `--seq`), producing each record as its own document.
+///
+/// Records are read by [`CharStream::into_json_seq_tokens`](crate::CharStream::into_json_seq_tokens),
+/// so a record which fails to parse doesn't affect the ones around it: it
+/// produces a [`JQErr::InvalidRecord`] and reading resumes at the next RS.
+pub struct JsonSeqDocuments<Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    tokens: RawTokenStream<Chars>,
+}
+
+impl<Chars> JsonSeqDocuments<Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    pub(crate) fn new(chars: Chars) -> Self {
+        Self {
+            tokens: RawTokenStream::new_json_seq(chars),
+        }
+    }
+}
+
+impl<Chars> Iterator for JsonSeqDocuments<Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    type Item = Result<ValueTokens, JQErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Value::next_from(&mut self.tokens).map(|value| value.map(Value::into_stream))
+    }
+}
+
+/// Writes an RFC 7464 JSON text sequence, matching jq's `--seq` flag:
+/// every top-level value is compact, starts with RS and ends with a
+/// newline.
+pub struct JsonSeqChars<Stream>
+where
+    Stream: JQStream,
+{
+    chars: CompactChars<Stream>,
+    at_value_start: bool,
+    on_deck: Option<char>,
+}
+
+impl<Stream> JsonSeqChars<Stream>
+where
+    Stream: JQStream,
+{
+    pub fn new(stream: Stream) -> Self {
+        Self {
+            chars: CompactChars::new(stream),
+            at_value_start: true,
+            on_deck: None,
+        }
+    }
+}
+
+impl<Stream> Iterator for JsonSeqChars<Stream>
+where
+    Stream: JQStream,
+{
+    type Item = Result<char, JQErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(ch) = self.on_deck.take() {
+            return Some(Ok(ch));
+        }
+
+        let ch = match self.chars.next()? {
+            Err(err) => return Some(Err(err)),
+            Ok(ch) => ch,
+        };
+
+        // Compact output only ever contains a newline at the end of a
+        // top-level value, as newlines in strings are escaped.
+        if std::mem::replace(&mut self.at_value_start, ch == '\n') {
+            self.on_deck = Some(ch);
+            return Some(Ok(RS));
+        }
+
+        Some(Ok(ch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CharStream, JQErr, SanitizedJQStream};
+
+    #[test]
+    fn reads_each_record_as_a_document() {
+        let documents = "\u{1e}{\"a\":1}\n\u{1e}[1,\n\u{1e}3\n"
+            .chars()
+            .into_json_seq_documents()
+            .map(|document| document.and_then(|tokens| tokens.to_string()))
+            .collect::<Vec<_>>();
+
+        assert!(matches!(
+            &documents[..],
+            [
+                Ok(object),
+                Err(JQErr::InvalidRecord { record: 1, .. }),
+                Ok(number),
+            ] if object == "{\"a\":1}\n" && number == "3\n"
+        ));
+    }
+
+    #[test]
+    fn writes_each_value_as_a_record() {
+        let written = "{\"a\":\"x\\ny\"} [1] 2"
+            .chars()
+            .into_json_tokens()
+            .to_chars_json_seq()
+            .collect::<Result<String, _>>()
+            .unwrap();
+
+        assert_eq!(written, "\u{1e}{\"a\":\"x\\ny\"}\n\u{1e}[1]\n\u{1e}2\n");
+    }
+
+    #[test]
+    fn escapes_control_characters_inside_strings() {
+        let written = "\"a\\u001eb\\nc\" {\"\\u001e\": \"\\t\"}"
+            .chars()
+            .into_json_tokens()
+            .to_chars_json_seq()
+            .collect::<Result<String, _>>()
+            .unwrap();
+
+        assert_eq!(
+            written,
+            "\u{1e}\"a\\u001eb\\nc\"\n\u{1e}{\"\\u001e\":\"\\t\"}\n"
+        );
+        assert_eq!(written.matches('\u{1e}').count(), 2);
+    }
+}
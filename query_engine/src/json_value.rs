@@ -0,0 +1,147 @@
+use serde_json::{value::RawValue, Map, Number};
+
+use crate::{
+    value::Value, CharStream, JQErr, RawTokenStream, SanitizedJQStream, Token, ValueTokens,
+};
+
+/// Walks a [`serde_json::Value`], producing its tokens.
+pub fn from_json_value(value: &serde_json::Value) -> ValueTokens {
+    let mut tokens = Vec::new();
+    push_tokens(value, &mut tokens);
+    ValueTokens::new(tokens)
+}
+
+fn push_tokens(value: &serde_json::Value, out: &mut Vec<Token>) {
+    match value {
+        serde_json::Value::Null => out.push(Token::Null),
+        serde_json::Value::Bool(true) => out.push(Token::True),
+        serde_json::Value::Bool(false) => out.push(Token::False),
+        serde_json::Value::Number(num) => out.push(Token::Number(num.to_string().into())),
+        serde_json::Value::String(str) => out.push(Token::String(str.as_str().into())),
+        serde_json::Value::Array(items) => {
+            out.push(Token::ArrayStart);
+            for (i, item) in items.iter().enumerate() {
+                if i != 0 {
+                    out.push(Token::Comma);
+                }
+                push_tokens(item, out);
+            }
+            out.push(Token::ArrayEnd);
+        }
+        serde_json::Value::Object(entries) => {
+            out.push(Token::ObjectStart);
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i != 0 {
+                    out.push(Token::Comma);
+                }
+                out.extend([Token::String(key.as_str().into()), Token::Colon]);
+                push_tokens(value, out);
+            }
+            out.push(Token::ObjectEnd);
+        }
+    }
+}
+
+/// Tokenizes the text of a [`RawValue`] lazily, so only the parts a filter
+/// looks at are ever parsed.
+pub fn from_raw_value(value: &RawValue) -> RawTokenStream<std::str::Chars<'_>> {
+    value.get().chars().into_json_tokens()
+}
+
+/// Builds a [`serde_json::Value`] out of a stream holding exactly one
+/// value, such as the output of a jq filter. Like jq, NaN becomes null and
+/// infinities become the largest finite numbers.
+pub fn to_json_value<Stream>(stream: Stream) -> Result<serde_json::Value, JQErr>
+where
+    Stream: SanitizedJQStream,
+{
+    let mut values = Value::all_from(stream)?.into_iter();
+    match (values.next(), values.next()) {
+        (Some(value), None) => Ok(json_value(value)),
+        (None, _) => Err(JQErr::UnexpectedEOF),
+        (Some(_), Some(_)) => Err(JQErr::StreamOperationFailed(
+            "Expected a single value, but the stream has more".into(),
+        )),
+    }
+}
+
+fn json_value(value: Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::True => serde_json::Value::Bool(true),
+        Value::False => serde_json::Value::Bool(false),
+        // Numbers too large for an f64 fail to parse, so they're clamped
+        // along with computed infinities.
+        Value::Number(ref num) => match num.parse::<Number>() {
+            Ok(num) => serde_json::Value::Number(num),
+            Err(_) => json_number(value.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::ParsedNumber(num) => json_number(num),
+        Value::String(str) => serde_json::Value::String(str.to_string()),
+        Value::Array(items) => {
+            serde_json::Value::Array(items.into_iter().map(json_value).collect())
+        }
+        Value::Object(entries) => serde_json::Value::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), json_value(value)))
+                .collect::<Map<_, _>>(),
+        ),
+    }
+}
+
+fn json_number(num: f64) -> serde_json::Value {
+    Number::from_f64(num.clamp(f64::MIN, f64::MAX))
+        .map(serde_json::Value::Number)
+        .unwrap_or(serde_json::Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, value::RawValue};
+
+    use crate::{
+        from_json_value, from_raw_value, to_json_value, CharStream, JQErr, SanitizedJQStream,
+    };
+
+    #[test]
+    fn walks_serde_json_values() {
+        let value = json!({"a": [1, -2.5, null, true], "b": {"c": "d e"}});
+        assert_eq!(
+            from_json_value(&value).to_string().unwrap(),
+            "{\"a\":[1,-2.5,null,true],\"b\":{\"c\":\"d e\"}}\n"
+        );
+        assert_eq!(
+            from_json_value(&value).at_key("b").to_string().unwrap(),
+            "{\"c\":\"d e\"}\n"
+        );
+    }
+
+    #[test]
+    fn tokenizes_raw_values_lazily() {
+        let raw = RawValue::from_string(r#"{"a": 1, "b": [2, 3]}"#.to_string()).unwrap();
+        assert_eq!(
+            from_raw_value(&raw).at_key("b").to_string().unwrap(),
+            "[2,3]\n"
+        );
+    }
+
+    #[test]
+    fn builds_serde_json_values() {
+        let value = json!({"a": [1, 2.5, "x", {"b": null}], "c": false});
+        assert_eq!(to_json_value(from_json_value(&value)).unwrap(), value);
+        assert_eq!(
+            to_json_value("[1e400, 18446744073709551615]".chars().into_json_tokens()).unwrap(),
+            json!([f64::MAX, u64::MAX])
+        );
+        assert!(matches!(
+            to_json_value("1 2".chars().into_json_tokens()),
+            Err(JQErr::StreamOperationFailed(msg))
+                if &*msg == "Expected a single value, but the stream has more"
+        ));
+        assert!(matches!(
+            to_json_value("".chars().into_json_tokens()),
+            Err(JQErr::UnexpectedEOF)
+        ));
+    }
+}
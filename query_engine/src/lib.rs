@@ -1,38 +1,125 @@
 use std::rc::Rc;
 
 use char_locations::CharLocations;
+use iterate::IterateMode;
+use limit::LimitMode;
 pub use location::Location;
+use math::MathOp;
+use re::Pattern;
+use sort::SortMode;
 use span::Span;
+use sql::IndexMode;
+use transform::TransformOp;
 
+pub use add::Add;
+pub use any_all::AnyAll;
 pub use array_index::ArrayIndex;
+pub use binary::{BinaryBytes, BinaryTags};
+pub use budget::{
+    Budgeted, CancellationToken, Clock, ExecutionBudget, ExecutionLimit, SystemClock,
+};
+pub use canonical::CanonicalChars;
+pub use cbor::{CborBytes, CborTokens};
+pub use csv::CsvRows;
+#[cfg(feature = "serde")]
+pub use de::{from_stream, Deserializer};
+pub use debug::Debug;
+pub use empty::Empty;
+pub use escape::StringEscapes;
+pub use filter::{Filter, Identity};
+pub use input::Input;
+pub use iterate::Iterate;
 pub use json_err::JQErr;
+pub use json_seq::{JsonSeqChars, JsonSeqDocuments};
+#[cfg(feature = "serde_json")]
+pub use json_value::{from_json_value, from_raw_value, to_json_value};
+pub use limit::Limit;
+pub use math::Math;
+pub use msgpack::{MsgpackBytes, MsgpackTokens};
+pub use ndjson::NdjsonDocuments;
 pub use object_index::ObjectKeyIndex;
-pub use raw::RawTokenStream;
+pub use push::PushParser;
+pub use range::Range;
+pub use raw::{DuplicateKeys, LoneSurrogates, ParseLimits, RawTokenStream};
+pub use raw_text::{RawChars, RawInput};
 pub use sanitized::Sanitized;
 pub use scope::Scope;
+#[cfg(feature = "serde")]
+pub use ser::{to_stream, Serializer};
+#[cfg(feature = "simd-json")]
+pub use simd::from_simd_json;
 pub use slurp::Slurp;
+pub use sort::SortBy;
+pub use sql::{In, Index};
+pub use stream_events::{FromStream, ToStream};
 pub use to_string_compact::CompactChars;
 pub use to_string_pretty::PrettyChars;
 pub use token::Token;
+pub use toml::{TomlChars, TomlTokens};
+pub use transform::Transform;
+pub use value::ValueTokens;
 pub use values::Values;
+pub use yaml::{YamlChars, YamlKeys, YamlTokens};
 
 mod char_locations;
-mod span;
 mod location;
+mod span;
 
+mod add;
+mod any_all;
 mod array_index;
+mod binary;
+mod budget;
+mod builtins;
+mod canonical;
+mod cbor;
+mod csv;
+#[cfg(feature = "serde")]
+mod de;
+mod debug;
+mod decimal;
+mod empty;
+mod escape;
+mod filter;
 mod fuse;
+mod input;
+mod iterate;
 mod json_err;
+mod json_seq;
+#[cfg(feature = "serde_json")]
+mod json_value;
+mod limit;
+mod math;
+mod msgpack;
+mod ndjson;
+mod number_format;
 mod object_index;
+mod paths;
+mod push;
+mod range;
 mod raw;
+mod raw_text;
+mod re;
 mod sanitized;
 mod scope;
+#[cfg(feature = "serde")]
+mod ser;
+#[cfg(feature = "simd-json")]
+mod simd;
 mod slurp;
+mod sort;
+mod sql;
 mod stream_context;
+mod stream_events;
+mod strings;
 mod to_string_compact;
 mod to_string_pretty;
 mod token;
+mod toml;
+mod transform;
+mod value;
 mod values;
+mod yaml;
 
 pub(crate) type Item = Result<Token, JQErr>;
 
@@ -65,10 +152,146 @@ pub trait CharStream: Iterator<Item = char> {
     {
         RawTokenStream::new(self)
     }
+
+    /// Converts these characters into a stream of JSON tokens, also
+    /// accepting JSON5 and JSONC: comments, trailing commas, single-quoted
+    /// strings, unquoted keys, hexadecimal numbers, `Infinity` and `NaN`.
+    fn into_lenient_json_tokens(self) -> RawTokenStream<Self>
+    where
+        Self: Sized,
+    {
+        RawTokenStream::new_lenient(self)
+    }
+
+    /// Reads these characters as newline-delimited JSON, producing each
+    /// line as a separate document.
+    fn into_ndjson_documents(self) -> NdjsonDocuments<Self>
+    where
+        Self: Sized,
+    {
+        NdjsonDocuments::new(self)
+    }
+
+    /// Reads these characters as an RFC 7464 JSON text sequence, producing
+    /// each record as a separate document.
+    fn into_json_seq_documents(self) -> JsonSeqDocuments<Self>
+    where
+        Self: Sized,
+    {
+        JsonSeqDocuments::new(self)
+    }
+
+    /// Reads these characters as an RFC 7464 JSON text sequence, producing
+    /// the tokens of every record in one stream. Malformed records produce
+    /// an error and are skipped.
+    fn into_json_seq_tokens(self) -> RawTokenStream<Self>
+    where
+        Self: Sized,
+    {
+        RawTokenStream::new_json_seq(self)
+    }
+
+    /// Reads these characters as raw text like jq's `-R` flag, producing
+    /// every line as a string.
+    fn into_raw_input(self) -> RawInput<false, Self>
+    where
+        Self: Sized,
+    {
+        RawInput::new(self)
+    }
+
+    /// Reads these characters as raw text like jq's `-Rs` flags, producing
+    /// the whole input as a single string.
+    fn into_raw_input_slurped(self) -> RawInput<true, Self>
+    where
+        Self: Sized,
+    {
+        RawInput::new(self)
+    }
+
+    /// Reads these characters as CSV, producing every row as an array of
+    /// strings. With `infer_numbers`, unquoted fields which are valid JSON
+    /// numbers become numbers.
+    fn into_csv_rows(self, infer_numbers: bool) -> CsvRows<false, Self>
+    where
+        Self: Sized,
+    {
+        CsvRows::new(self, false, infer_numbers)
+    }
+
+    /// Reads these characters as CSV with a header line, producing every
+    /// other row as an object keyed by the header.
+    fn into_csv_objects(self, infer_numbers: bool) -> CsvRows<true, Self>
+    where
+        Self: Sized,
+    {
+        CsvRows::new(self, false, infer_numbers)
+    }
+
+    /// Reads these characters as tab-separated values, producing every row
+    /// as an array of strings.
+    fn into_tsv_rows(self, infer_numbers: bool) -> CsvRows<false, Self>
+    where
+        Self: Sized,
+    {
+        CsvRows::new(self, true, infer_numbers)
+    }
+
+    /// Reads these characters as tab-separated values with a header line,
+    /// producing every other row as an object keyed by the header.
+    fn into_tsv_objects(self, infer_numbers: bool) -> CsvRows<true, Self>
+    where
+        Self: Sized,
+    {
+        CsvRows::new(self, true, infer_numbers)
+    }
+
+    /// Reads these characters as a TOML document, producing it as a single
+    /// object.
+    fn into_toml_tokens(self) -> TomlTokens<Self>
+    where
+        Self: Sized,
+    {
+        TomlTokens::new(self)
+    }
+
+    /// Reads these characters as YAML, producing each document as a
+    /// separate value. `keys` decides what happens to mapping keys which
+    /// aren't strings.
+    fn into_yaml_tokens(self, keys: YamlKeys) -> YamlTokens<Self>
+    where
+        Self: Sized,
+    {
+        YamlTokens::new(self, keys)
+    }
 }
 
 impl<T> CharStream for T where T: Iterator<Item = char> {}
 
+pub trait ByteStream: Iterator<Item = u8> {
+    /// Reads these bytes as CBOR, producing each top-level item as a
+    /// separate value. `bytes` and `tags` decide how byte strings and tags
+    /// are represented.
+    fn into_cbor_tokens(self, bytes: BinaryBytes, tags: BinaryTags) -> CborTokens<Self>
+    where
+        Self: Sized,
+    {
+        CborTokens::new(self, bytes, tags)
+    }
+
+    /// Reads these bytes as MessagePack, producing each top-level object
+    /// as a separate value. `bytes` and `tags` decide how binary data and
+    /// extension types are represented.
+    fn into_msgpack_tokens(self, bytes: BinaryBytes, tags: BinaryTags) -> MsgpackTokens<Self>
+    where
+        Self: Sized,
+    {
+        MsgpackTokens::new(self, bytes, tags)
+    }
+}
+
+impl<T> ByteStream for T where T: Iterator<Item = u8> {}
+
 /// A named trait for a JQ iterator. All JQ iterators have
 /// an Item type of [`Result`] where the [`Ok`] variant is
 /// of type [`Token`] and the [`Err`] variant is of type
@@ -78,8 +301,8 @@ pub trait JQStream: Iterator<Item = crate::Item> {
     /// that:
     /// 1. The JSON token stream conforms to the JSON grammar
     /// 2. The JSON token stream will fuse after returning the
-    /// first error (this helps prevent getting into weird states
-    /// and spining in infinite loops).
+    ///    first error (this helps prevent getting into weird states
+    ///    and spining in infinite loops).
     fn sanitize(self) -> Sanitized<Self>
     where
         Self: Sized,
@@ -92,9 +315,9 @@ pub trait JQStream: Iterator<Item = crate::Item> {
 /// must guarantee that:
 /// 1. The JSON token stream conforms to the JSON grammar
 /// 2. The JSON token stream will fuse after returning the
-/// first error (if this guarantee is violated, other
-/// derivative JQStreams may enter weird states and spin
-/// in infinite loops).
+///    first error (if this guarantee is violated, other
+///    derivative JQStreams may enter weird states and spin
+///    in infinite loops).
 ///
 /// Any JQStream can be transformed into a SanitizedJQStream
 /// by calling .sanitize().
@@ -159,13 +382,21 @@ pub trait SanitizedJQStream: JQStream {
         ArrayIndex::new(self, index)
     }
 
+    /// Whether no object in this stream repeats a key, so that lookups
+    /// such as [`SanitizedJQStream::at_key`] can stream the value they find
+    /// rather than holding it until the object closes.
+    fn has_unique_keys(&self) -> bool {
+        false
+    }
+
     /// Runs a `.["{key}"]` operation
     fn at_key<Key>(self, key: Key) -> ObjectKeyIndex<true, Self>
     where
         Self: Sized,
         Key: Into<Rc<str>>,
     {
-        ObjectKeyIndex::new(self, key.into())
+        let unique = self.has_unique_keys();
+        ObjectKeyIndex::new(self, key.into(), unique)
     }
 
     /// Runs a `.["{key}"]?` operation
@@ -174,7 +405,8 @@ pub trait SanitizedJQStream: JQStream {
         Self: Sized,
         Key: Into<Rc<str>>,
     {
-        ObjectKeyIndex::new(self, key.into())
+        let unique = self.has_unique_keys();
+        ObjectKeyIndex::new(self, key.into(), unique)
     }
 
     /// Runs a `slurp` operation
@@ -203,6 +435,99 @@ pub trait SanitizedJQStream: JQStream {
         PrettyChars::new(self)
     }
 
+    /// Converts the JSON token stream into RFC 8785 canonical JSON, for
+    /// hashing or signing. Each value after the first starts on a new
+    /// line.
+    fn to_chars_canonical(self) -> CanonicalChars<Self>
+    where
+        Self: Sized,
+    {
+        CanonicalChars::new(self)
+    }
+
+    /// Converts the JSON token stream into newline-delimited JSON
+    /// (JSON Lines), with exactly one compact value per line.
+    fn to_chars_ndjson(self) -> CompactChars<Self>
+    where
+        Self: Sized,
+    {
+        CompactChars::new(self)
+    }
+
+    /// Converts the JSON token stream into an RFC 7464 JSON text
+    /// sequence, like jq's `--seq` flag.
+    fn to_chars_json_seq(self) -> JsonSeqChars<Self>
+    where
+        Self: Sized,
+    {
+        JsonSeqChars::new(self)
+    }
+
+    /// Converts the JSON token stream into characters like jq's `-r` flag,
+    /// writing top-level strings without quotes. Every value ends with a
+    /// newline.
+    fn to_chars_raw(self) -> RawChars<Self>
+    where
+        Self: Sized,
+    {
+        RawChars::new(self, Some('\n'))
+    }
+
+    /// Converts the JSON token stream into characters like jq's `-j` flag,
+    /// which is the same as `-r` without any newlines.
+    fn to_chars_raw_joined(self) -> RawChars<Self>
+    where
+        Self: Sized,
+    {
+        RawChars::new(self, None)
+    }
+
+    /// Converts the JSON token stream into characters like jq's
+    /// `--raw-output0` flag, which is the same as `-r` except that every
+    /// value ends with a NUL character instead of a newline.
+    fn to_chars_raw0(self) -> RawChars<Self>
+    where
+        Self: Sized,
+    {
+        RawChars::new(self, Some('\0'))
+    }
+
+    /// Converts the JSON token stream into CBOR bytes, with each value
+    /// written as a separate top-level item.
+    fn to_bytes_cbor(self) -> CborBytes<Self>
+    where
+        Self: Sized,
+    {
+        CborBytes::new(self)
+    }
+
+    /// Converts the JSON token stream into MessagePack bytes, with each
+    /// value written as a separate top-level object.
+    fn to_bytes_msgpack(self) -> MsgpackBytes<Self>
+    where
+        Self: Sized,
+    {
+        MsgpackBytes::new(self)
+    }
+
+    /// Converts the JSON token stream into the characters of a TOML
+    /// document. The stream must hold a single object without any nulls.
+    fn to_chars_toml(self) -> TomlChars<Self>
+    where
+        Self: Sized,
+    {
+        TomlChars::new(self)
+    }
+
+    /// Converts the JSON token stream into YAML characters, with each
+    /// value written as a separate document.
+    fn to_chars_yaml(self) -> YamlChars<Self>
+    where
+        Self: Sized,
+    {
+        YamlChars::new(self)
+    }
+
     /// Converts the JSON token stream into a string of
     /// compactly formatted characters to form the JSON.
     fn to_string(self) -> Result<String, JQErr>
@@ -236,6 +561,1236 @@ pub trait SanitizedJQStream: JQStream {
     {
         Values::new(self)
     }
-}
 
-impl<T> JQStream for T where T: Iterator<Item = crate::Item> {}
+    /// Runs a `sort` operation
+    fn sort(self) -> SortBy<Self, Identity>
+    where
+        Self: Sized,
+    {
+        SortBy::new(self, Identity, SortMode::Sort)
+    }
+
+    /// Runs a `sort_by(f)` operation
+    fn sort_by<F>(self, f: F) -> SortBy<Self, F>
+    where
+        Self: Sized,
+        F: Filter,
+    {
+        SortBy::new(self, f, SortMode::Sort)
+    }
+
+    /// Runs a `group_by(f)` operation
+    fn group_by<F>(self, f: F) -> SortBy<Self, F>
+    where
+        Self: Sized,
+        F: Filter,
+    {
+        SortBy::new(self, f, SortMode::Group)
+    }
+
+    /// Runs a `unique` operation
+    fn unique(self) -> SortBy<Self, Identity>
+    where
+        Self: Sized,
+    {
+        SortBy::new(self, Identity, SortMode::Unique)
+    }
+
+    /// Runs a `unique_by(f)` operation
+    fn unique_by<F>(self, f: F) -> SortBy<Self, F>
+    where
+        Self: Sized,
+        F: Filter,
+    {
+        SortBy::new(self, f, SortMode::Unique)
+    }
+
+    /// Runs a `min` operation. This isn't named `min` to avoid
+    /// clashing with [`Iterator::min`].
+    fn json_min(self) -> SortBy<Self, Identity>
+    where
+        Self: Sized,
+    {
+        SortBy::new(self, Identity, SortMode::Min)
+    }
+
+    /// Runs a `min_by(f)` operation. This isn't named `min_by` to avoid
+    /// clashing with [`Iterator::min_by`].
+    fn json_min_by<F>(self, f: F) -> SortBy<Self, F>
+    where
+        Self: Sized,
+        F: Filter,
+    {
+        SortBy::new(self, f, SortMode::Min)
+    }
+
+    /// Runs a `max` operation. This isn't named `max` to avoid
+    /// clashing with [`Iterator::max`].
+    fn json_max(self) -> SortBy<Self, Identity>
+    where
+        Self: Sized,
+    {
+        SortBy::new(self, Identity, SortMode::Max)
+    }
+
+    /// Runs a `max_by(f)` operation. This isn't named `max_by` to avoid
+    /// clashing with [`Iterator::max_by`].
+    fn json_max_by<F>(self, f: F) -> SortBy<Self, F>
+    where
+        Self: Sized,
+        F: Filter,
+    {
+        SortBy::new(self, f, SortMode::Max)
+    }
+
+    /// Runs an `add` operation
+    fn add(self) -> Add<Self, Identity>
+    where
+        Self: Sized,
+    {
+        Add::new(self, None)
+    }
+
+    /// Runs an `add(generator)` operation
+    fn add_of<Gen>(self, generator: Gen) -> Add<Self, Gen>
+    where
+        Self: Sized,
+        Gen: Filter,
+    {
+        Add::new(self, Some(generator))
+    }
+
+    /// Runs an `any` operation. This isn't named `any` to avoid
+    /// clashing with [`Iterator::any`].
+    fn json_any(self) -> AnyAll<false, Self, Identity, Identity>
+    where
+        Self: Sized,
+    {
+        AnyAll::new(self, None, Identity)
+    }
+
+    /// Runs an `any(condition)` operation
+    fn json_any_by<Cond>(self, condition: Cond) -> AnyAll<false, Self, Identity, Cond>
+    where
+        Self: Sized,
+        Cond: Filter,
+    {
+        AnyAll::new(self, None, condition)
+    }
+
+    /// Runs an `any(generator; condition)` operation
+    fn json_any_of<Gen, Cond>(
+        self,
+        generator: Gen,
+        condition: Cond,
+    ) -> AnyAll<false, Self, Gen, Cond>
+    where
+        Self: Sized,
+        Gen: Filter,
+        Cond: Filter,
+    {
+        AnyAll::new(self, Some(generator), condition)
+    }
+
+    /// Runs an `all` operation. This isn't named `all` to avoid
+    /// clashing with [`Iterator::all`].
+    fn json_all(self) -> AnyAll<true, Self, Identity, Identity>
+    where
+        Self: Sized,
+    {
+        AnyAll::new(self, None, Identity)
+    }
+
+    /// Runs an `all(condition)` operation
+    fn json_all_by<Cond>(self, condition: Cond) -> AnyAll<true, Self, Identity, Cond>
+    where
+        Self: Sized,
+        Cond: Filter,
+    {
+        AnyAll::new(self, None, condition)
+    }
+
+    /// Runs an `all(generator; condition)` operation
+    fn json_all_of<Gen, Cond>(
+        self,
+        generator: Gen,
+        condition: Cond,
+    ) -> AnyAll<true, Self, Gen, Cond>
+    where
+        Self: Sized,
+        Gen: Filter,
+        Cond: Filter,
+    {
+        AnyAll::new(self, Some(generator), condition)
+    }
+
+    /// Runs a `flatten` operation. This isn't named `flatten` to avoid
+    /// clashing with [`Iterator::flatten`].
+    fn json_flatten(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::Flatten(None))
+    }
+
+    /// Runs a `flatten({depth})` operation
+    fn json_flatten_depth(self, depth: usize) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::Flatten(Some(depth)))
+    }
+
+    /// Runs a `range({upto})` operation
+    fn range(self, upto: f64) -> Range<Self>
+    where
+        Self: Sized,
+    {
+        Range::new(self, 0.0, upto, 1.0)
+    }
+
+    /// Runs a `range({from}; {upto})` operation
+    fn range_from(self, from: f64, upto: f64) -> Range<Self>
+    where
+        Self: Sized,
+    {
+        Range::new(self, from, upto, 1.0)
+    }
+
+    /// Runs a `range({from}; {upto}; {by})` operation
+    fn range_step(self, from: f64, upto: f64, by: f64) -> Range<Self>
+    where
+        Self: Sized,
+    {
+        Range::new(self, from, upto, by)
+    }
+
+    /// Runs a `reverse` operation
+    fn reverse(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::Reverse)
+    }
+
+    /// Runs an `indices(i)` operation once for every value in `i`
+    fn indices<I>(self, i: I) -> Transform<Self>
+    where
+        Self: Sized,
+        I: JQStream,
+    {
+        Transform::with_args(self, TransformOp::Indices, i)
+    }
+
+    /// Runs an `inside(b)` operation once for every value in `b`
+    fn inside<B>(self, b: B) -> Transform<Self>
+    where
+        Self: Sized,
+        B: JQStream,
+    {
+        Transform::with_args(self, TransformOp::Inside, b)
+    }
+
+    /// Runs a `contains(b)` operation once for every value in `b`
+    fn contains<B>(self, b: B) -> Transform<Self>
+    where
+        Self: Sized,
+        B: JQStream,
+    {
+        Transform::with_args(self, TransformOp::Contains, b)
+    }
+
+    /// Runs an `ascii_downcase` operation
+    fn ascii_downcase(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::AsciiDowncase)
+    }
+
+    /// Runs an `ascii_upcase` operation
+    fn ascii_upcase(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::AsciiUpcase)
+    }
+
+    /// Runs a `trim` operation
+    fn trim(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::Trim(true, true))
+    }
+
+    /// Runs an `ltrim` operation
+    fn ltrim(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::Trim(true, false))
+    }
+
+    /// Runs an `rtrim` operation
+    fn rtrim(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::Trim(false, true))
+    }
+
+    /// Runs an `explode` operation
+    fn explode(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::Explode)
+    }
+
+    /// Runs an `implode` operation
+    fn implode(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::Implode)
+    }
+
+    /// Runs a `tostring` operation
+    fn tostring(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::ToString)
+    }
+
+    /// Runs a `tonumber` operation
+    fn tonumber(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::ToNumber)
+    }
+
+    /// Runs a `tojson` operation
+    fn tojson(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::ToJson)
+    }
+
+    /// Runs a `fromjson` operation
+    fn fromjson(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::FromJson)
+    }
+
+    /// Runs a `utf8bytelength` operation
+    fn utf8bytelength(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::Utf8ByteLength)
+    }
+
+    /// Runs an `ltrimstr("{prefix}")` operation
+    fn ltrimstr<Str>(self, prefix: Str) -> Transform<Self>
+    where
+        Self: Sized,
+        Str: Into<Rc<str>>,
+    {
+        Transform::new(self, TransformOp::LtrimStr(prefix.into()))
+    }
+
+    /// Runs an `rtrimstr("{suffix}")` operation
+    fn rtrimstr<Str>(self, suffix: Str) -> Transform<Self>
+    where
+        Self: Sized,
+        Str: Into<Rc<str>>,
+    {
+        Transform::new(self, TransformOp::RtrimStr(suffix.into()))
+    }
+
+    /// Runs a `startswith("{prefix}")` operation
+    fn startswith<Str>(self, prefix: Str) -> Transform<Self>
+    where
+        Self: Sized,
+        Str: Into<Rc<str>>,
+    {
+        Transform::new(self, TransformOp::StartsWith(prefix.into()))
+    }
+
+    /// Runs an `endswith("{suffix}")` operation
+    fn endswith<Str>(self, suffix: Str) -> Transform<Self>
+    where
+        Self: Sized,
+        Str: Into<Rc<str>>,
+    {
+        Transform::new(self, TransformOp::EndsWith(suffix.into()))
+    }
+
+    /// Runs a `split("{separator}")` operation
+    fn split<Str>(self, separator: Str) -> Transform<Self>
+    where
+        Self: Sized,
+        Str: Into<Rc<str>>,
+    {
+        Transform::new(self, TransformOp::Split(separator.into()))
+    }
+
+    /// Runs a `join("{separator}")` operation
+    fn join<Str>(self, separator: Str) -> Transform<Self>
+    where
+        Self: Sized,
+        Str: Into<Rc<str>>,
+    {
+        Transform::new(self, TransformOp::Join(separator.into()))
+    }
+
+    /// Spends `budget` on every token pulled through this stream, failing
+    /// once it runs out or is cancelled. The builtins underneath, however
+    /// deeply they're wrapped, spend it too, such as `until(cond; update)`
+    /// on every turn of its loop.
+    fn with_budget(self, budget: &ExecutionBudget) -> Budgeted<Self>
+    where
+        Self: Sized,
+    {
+        Budgeted::new(self, budget.clone())
+    }
+
+    /// Runs a `limit({n}; f)` operation, where `f` is this stream. Nothing
+    /// more is pulled from this stream once `n` values have been produced.
+    fn limit(self, n: usize) -> Limit<Self>
+    where
+        Self: Sized,
+    {
+        Limit::new(self, LimitMode::Limit(n))
+    }
+
+    /// Runs a `first(f)` operation, where `f` is this stream. Nothing more
+    /// is pulled from this stream once the first value has been produced.
+    fn first(self) -> Limit<Self>
+    where
+        Self: Sized,
+    {
+        Limit::new(self, LimitMode::Limit(1))
+    }
+
+    /// Runs a `last(f)` operation, where `f` is this stream
+    fn json_last(self) -> Limit<Self>
+    where
+        Self: Sized,
+    {
+        Limit::new(self, LimitMode::Last)
+    }
+
+    /// Runs a `nth({n}; f)` operation, where `f` is this stream
+    fn json_nth(self, n: usize) -> Limit<Self>
+    where
+        Self: Sized,
+    {
+        Limit::new(self, LimitMode::Nth(n))
+    }
+
+    /// Runs an `isempty(f)` operation, where `f` is this stream
+    fn isempty(self) -> Limit<Self>
+    where
+        Self: Sized,
+    {
+        Limit::new(self, LimitMode::IsEmpty)
+    }
+
+    /// Runs an `empty` operation
+    fn empty(self) -> Empty<Self>
+    where
+        Self: Sized,
+    {
+        Empty::new(self)
+    }
+
+    /// Runs an `input` operation
+    fn input(self) -> Input<false, Self>
+    where
+        Self: Sized,
+    {
+        Input::new(self)
+    }
+
+    /// Runs an `inputs` operation
+    fn inputs(self) -> Input<true, Self>
+    where
+        Self: Sized,
+    {
+        Input::new(self)
+    }
+
+    /// Runs a `while(cond; update)` operation
+    fn json_while<Cond, Update>(self, cond: Cond, update: Update) -> Iterate<Self, Cond, Update>
+    where
+        Self: Sized,
+        Cond: Filter,
+        Update: Filter,
+    {
+        Iterate::new(self, cond, update, IterateMode::While)
+    }
+
+    /// Runs an `until(cond; update)` operation
+    fn until<Cond, Update>(self, cond: Cond, update: Update) -> Iterate<Self, Cond, Update>
+    where
+        Self: Sized,
+        Cond: Filter,
+        Update: Filter,
+    {
+        Iterate::new(self, cond, update, IterateMode::Until)
+    }
+
+    /// Runs a `repeat(update)` operation
+    fn repeat<Update>(self, update: Update) -> Iterate<Self, Identity, Update>
+    where
+        Self: Sized,
+        Update: Filter,
+    {
+        Iterate::new(self, Identity, update, IterateMode::Repeat)
+    }
+
+    /// Runs an `INDEX(idx_expr)` operation over the values of each input
+    fn sql_index<Key>(self, idx_expr: Key) -> Index<Self, Identity, Key>
+    where
+        Self: Sized,
+        Key: Filter,
+    {
+        Index::new(self, None, idx_expr, IndexMode::Index)
+    }
+
+    /// Runs an `INDEX(stream; idx_expr)` operation
+    fn sql_index_of<Rows, Key>(self, stream: Rows, idx_expr: Key) -> Index<Self, Rows, Key>
+    where
+        Self: Sized,
+        Rows: Filter,
+        Key: Filter,
+    {
+        Index::new(self, Some(stream), idx_expr, IndexMode::Index)
+    }
+
+    /// Runs a `GROUP_BY(f)` operation, which is like `INDEX(f)` except that
+    /// every row with the same key is kept in an array
+    fn sql_group_by<Key>(self, f: Key) -> Index<Self, Identity, Key>
+    where
+        Self: Sized,
+        Key: Filter,
+    {
+        Index::new(self, None, f, IndexMode::GroupBy)
+    }
+
+    /// Runs a `UNIQUE_BY(f)` operation, which is like `INDEX(f)` except that
+    /// the first row with each key is kept
+    fn sql_unique_by<Key>(self, f: Key) -> Index<Self, Identity, Key>
+    where
+        Self: Sized,
+        Key: Filter,
+    {
+        Index::new(self, None, f, IndexMode::UniqueBy)
+    }
+
+    /// Runs an `IN(s)` operation
+    fn sql_in<Set>(self, s: Set) -> In<Self, Identity, Set>
+    where
+        Self: Sized,
+        Set: Filter,
+    {
+        In::new(self, None, s)
+    }
+
+    /// Runs an `IN(source; s)` operation
+    fn sql_in_of<Source, Set>(self, source: Source, s: Set) -> In<Self, Source, Set>
+    where
+        Self: Sized,
+        Source: Filter,
+        Set: Filter,
+    {
+        In::new(self, Some(source), s)
+    }
+
+    /// Runs a `getpath(path)` operation once for every value in `path`
+    fn getpath<Path>(self, path: Path) -> Transform<Self>
+    where
+        Self: Sized,
+        Path: JQStream,
+    {
+        Transform::with_args(self, TransformOp::GetPath, path)
+    }
+
+    /// Runs a `pick(pathexps)` operation, where `paths` holds the paths
+    /// that `pathexps` would produce, such as `["a", 0]` for `.a[0]`
+    fn pick<Paths>(self, paths: Paths) -> Transform<Self>
+    where
+        Self: Sized,
+        Paths: JQStream,
+    {
+        Transform::with_args(self, TransformOp::Pick, paths)
+    }
+
+    /// Runs a `have_decnum` operation, which reports whether adding and
+    /// comparing number literals is exact (the `decimal` feature)
+    fn have_decnum(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(
+            self,
+            TransformOp::Constant(value::Value::from(decimal::HAVE_DECNUM)),
+        )
+    }
+
+    /// Runs a `have_literal_numbers` operation. Unmodified number literals
+    /// are always printed exactly as written.
+    fn have_literal_numbers(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::Constant(value::Value::True))
+    }
+
+    /// Runs an `abs` operation
+    fn abs(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::Abs)
+    }
+
+    /// Runs a `toarray` operation
+    fn toarray(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::ToArray)
+    }
+
+    /// Runs a `splits("{regex}")` operation
+    fn splits<Re>(self, regex: Re) -> Transform<Self>
+    where
+        Self: Sized,
+        Re: AsRef<str>,
+    {
+        self.splits_with_flags(regex, "")
+    }
+
+    /// Runs a `splits("{regex}"; "{flags}")` operation
+    fn splits_with_flags<Re, Flags>(self, regex: Re, flags: Flags) -> Transform<Self>
+    where
+        Self: Sized,
+        Re: AsRef<str>,
+        Flags: AsRef<str>,
+    {
+        let pattern = Pattern::new(regex.as_ref(), flags.as_ref());
+        Transform::new(self, TransformOp::Splits(pattern))
+    }
+
+    /// Runs a `scan("{regex}")` operation. This isn't named `scan` to avoid
+    /// conflicting with [`Iterator::scan`]
+    fn json_scan<Re>(self, regex: Re) -> Transform<Self>
+    where
+        Self: Sized,
+        Re: AsRef<str>,
+    {
+        self.json_scan_with_flags(regex, "")
+    }
+
+    /// Runs a `scan("{regex}"; "{flags}")` operation
+    fn json_scan_with_flags<Re, Flags>(self, regex: Re, flags: Flags) -> Transform<Self>
+    where
+        Self: Sized,
+        Re: AsRef<str>,
+        Flags: AsRef<str>,
+    {
+        let pattern = Pattern::new(regex.as_ref(), flags.as_ref());
+        Transform::new(self, TransformOp::Scan(pattern))
+    }
+
+    /// Runs a `debug` operation
+    fn debug(self) -> Debug<Self, Identity>
+    where
+        Self: Sized,
+    {
+        Debug::new(self, None)
+    }
+
+    /// Runs a `debug(msg)` operation
+    fn debug_msg<Msg>(self, msg: Msg) -> Debug<Self, Msg>
+    where
+        Self: Sized,
+        Msg: Filter,
+    {
+        Debug::new(self, Some(msg))
+    }
+
+    /// Runs a `$__loc__` operation. As there is no jq program text, the
+    /// location is that of the Rust code which called this method.
+    #[track_caller]
+    fn loc(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        let caller = std::panic::Location::caller();
+        let loc = value::Value::Object(vec![
+            ("file".into(), value::Value::String(caller.file().into())),
+            (
+                "line".into(),
+                value::Value::ParsedNumber(caller.line() as f64),
+            ),
+        ]);
+        Transform::new(self, TransformOp::Constant(loc))
+    }
+
+    /// Runs a `builtins` operation, listing every builtin implemented here
+    fn builtins(self) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        let builtins = builtins::BUILTINS
+            .iter()
+            .map(|builtin| value::Value::String((*builtin).into()))
+            .collect();
+        Transform::new(self, TransformOp::Constant(value::Value::Array(builtins)))
+    }
+
+    /// Runs a `tostream` operation, producing the same `[path, leaf]` and
+    /// `[path]` events as jq's `--stream` flag
+    fn to_stream_events(self) -> ToStream<Self>
+    where
+        Self: Sized,
+    {
+        ToStream::new(self)
+    }
+
+    /// Runs a `fromstream(f)` operation, where `f` is this stream of events
+    fn fromstream(self) -> FromStream<Self>
+    where
+        Self: Sized,
+    {
+        FromStream::new(self)
+    }
+
+    /// Runs a `{depth} | truncate_stream(f)` operation, where `f` is this
+    /// stream of events
+    fn truncate_stream(self, depth: usize) -> Transform<Self>
+    where
+        Self: Sized,
+    {
+        Transform::new(self, TransformOp::TruncateStream(depth))
+    }
+
+    /// Runs a `acos` operation
+    fn acos(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::acos))
+    }
+
+    /// Runs a `acosh` operation
+    fn acosh(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::acosh))
+    }
+
+    /// Runs a `asin` operation
+    fn asin(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::asin))
+    }
+
+    /// Runs a `asinh` operation
+    fn asinh(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::asinh))
+    }
+
+    /// Runs a `atan` operation
+    fn atan(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::atan))
+    }
+
+    /// Runs a `atanh` operation
+    fn atanh(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::atanh))
+    }
+
+    /// Runs a `cbrt` operation
+    fn cbrt(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::cbrt))
+    }
+
+    /// Runs a `ceil` operation
+    fn ceil(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::ceil))
+    }
+
+    /// Runs a `cos` operation
+    fn cos(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::cos))
+    }
+
+    /// Runs a `cosh` operation
+    fn cosh(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::cosh))
+    }
+
+    /// Runs a `exp` operation
+    fn exp(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::exp))
+    }
+
+    /// Runs a `exp10` operation
+    fn exp10(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::exp10))
+    }
+
+    /// Runs a `exp2` operation
+    fn exp2(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::exp2))
+    }
+
+    /// Runs a `expm1` operation
+    fn expm1(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::expm1))
+    }
+
+    /// Runs a `fabs` operation
+    fn fabs(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::fabs))
+    }
+
+    /// Runs a `floor` operation
+    fn floor(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::floor))
+    }
+
+    /// Runs a `gamma` operation
+    fn gamma(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::lgamma))
+    }
+
+    /// Runs a `j0` operation
+    fn j0(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::j0))
+    }
+
+    /// Runs a `j1` operation
+    fn j1(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::j1))
+    }
+
+    /// Runs a `lgamma` operation
+    fn lgamma(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::lgamma))
+    }
+
+    /// Runs a `log` operation
+    fn log(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::log))
+    }
+
+    /// Runs a `log10` operation
+    fn log10(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::log10))
+    }
+
+    /// Runs a `log1p` operation
+    fn log1p(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::log1p))
+    }
+
+    /// Runs a `log2` operation
+    fn log2(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::log2))
+    }
+
+    /// Runs a `logb` operation
+    fn logb(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(math::logb))
+    }
+
+    /// Runs a `nearbyint` operation
+    fn nearbyint(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::rint))
+    }
+
+    /// Runs a `pow10` operation
+    fn pow10(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::exp10))
+    }
+
+    /// Runs a `rint` operation
+    fn rint(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::rint))
+    }
+
+    /// Runs a `round` operation
+    fn round(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::round))
+    }
+
+    /// Runs a `significand` operation
+    fn significand(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(math::significand))
+    }
+
+    /// Runs a `sin` operation
+    fn sin(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::sin))
+    }
+
+    /// Runs a `sinh` operation
+    fn sinh(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::sinh))
+    }
+
+    /// Runs a `sqrt` operation
+    fn sqrt(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::sqrt))
+    }
+
+    /// Runs a `tan` operation
+    fn tan(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::tan))
+    }
+
+    /// Runs a `tanh` operation
+    fn tanh(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::tanh))
+    }
+
+    /// Runs a `tgamma` operation
+    fn tgamma(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::tgamma))
+    }
+
+    /// Runs a `trunc` operation
+    fn trunc(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::trunc))
+    }
+
+    /// Runs a `y0` operation
+    fn y0(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::y0))
+    }
+
+    /// Runs a `y1` operation
+    fn y1(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Unary(libm::y1))
+    }
+
+    /// Runs a `atan2(.; {x})` operation
+    fn atan2(self, x: f64) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Binary(libm::atan2, x))
+    }
+
+    /// Runs a `copysign(.; {sign})` operation
+    fn copysign(self, sign: f64) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Binary(libm::copysign, sign))
+    }
+
+    /// Runs a `drem(.; {y})` operation
+    fn drem(self, y: f64) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Binary(libm::remainder, y))
+    }
+
+    /// Runs a `fdim(.; {y})` operation
+    fn fdim(self, y: f64) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Binary(libm::fdim, y))
+    }
+
+    /// Runs a `fmax(.; {y})` operation
+    fn fmax(self, y: f64) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Binary(libm::fmax, y))
+    }
+
+    /// Runs a `fmin(.; {y})` operation
+    fn fmin(self, y: f64) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Binary(libm::fmin, y))
+    }
+
+    /// Runs a `fmod(.; {y})` operation
+    fn fmod(self, y: f64) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Binary(libm::fmod, y))
+    }
+
+    /// Runs a `hypot(.; {y})` operation
+    fn hypot(self, y: f64) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Binary(libm::hypot, y))
+    }
+
+    /// Runs a `nextafter(.; {y})` operation
+    fn nextafter(self, y: f64) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Binary(libm::nextafter, y))
+    }
+
+    /// Runs a `nexttoward(.; {y})` operation
+    fn nexttoward(self, y: f64) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Binary(libm::nextafter, y))
+    }
+
+    /// Runs a `pow(.; {exponent})` operation
+    fn pow(self, exponent: f64) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Binary(libm::pow, exponent))
+    }
+
+    /// Runs a `scalb(.; {exponent})` operation
+    fn scalb(self, exponent: f64) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Binary(math::scalb, exponent))
+    }
+
+    /// Runs a `scalbln(.; {exponent})` operation
+    fn scalbln(self, exponent: f64) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Binary(math::scalb, exponent))
+    }
+
+    /// Runs a `ldexp(.; {exponent})` operation
+    fn ldexp(self, exponent: i32) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Binary(math::scalb, exponent as f64))
+    }
+
+    /// Runs a `fma(.; {y}; {z})` operation
+    fn fma(self, y: f64, z: f64) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Ternary(libm::fma, y, z))
+    }
+
+    /// Runs a `frexp` operation, producing `[mantissa, exponent]`
+    fn frexp(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Pair(math::frexp))
+    }
+
+    /// Runs a `modf` operation, producing `[fraction, integer]`
+    fn modf(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Pair(libm::modf))
+    }
+
+    /// Runs a `lgamma_r` operation, producing `[lgamma, sign]`
+    fn lgamma_r(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Pair(math::lgamma_r))
+    }
+
+    /// Runs an `isinfinite` operation
+    fn isinfinite(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Predicate(f64::is_infinite))
+    }
+
+    /// Runs an `isnan` operation
+    fn isnan(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Predicate(f64::is_nan))
+    }
+
+    /// Runs an `isnormal` operation
+    fn isnormal(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Predicate(f64::is_normal))
+    }
+
+    /// Runs an `infinite` operation, replacing each input with infinity
+    fn infinite(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Constant(f64::INFINITY))
+    }
+
+    /// Runs a `nan` operation, replacing each input with NaN
+    fn nan(self) -> Math<Self>
+    where
+        Self: Sized,
+    {
+        Math::new(self, MathOp::Constant(f64::NAN))
+    }
+}
+
+impl<T> JQStream for T where T: Iterator<Item = crate::Item> {}
+
+#[cfg(test)]
+mod test_support {
+    use std::str::Chars;
+
+    use crate::{CharStream, RawTokenStream};
+
+    /// Tokenizes `json` as the input to a builtin under test.
+    pub(crate) fn values(json: &str) -> RawTokenStream<Chars<'_>> {
+        json.chars().into_json_tokens()
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+
+use crate::{value::Value, JQStream, Sanitized, SanitizedJQStream, Token};
+
+/// The builtins which only need some of the values a generator produces.
+pub(crate) enum LimitMode {
+    /// `limit(n; f)` and `first(f)`.
+    Limit(usize),
+    /// `nth(n; f)`.
+    Nth(usize),
+    /// `last(f)`.
+    Last,
+    /// `isempty(f)`.
+    IsEmpty,
+}
+
+/// A struct for handling the `limit(n; f)`, `first(f)`, `nth(n; f)`,
+/// `last(f)` and `isempty(f)` jq queries, where `f` is the stream being
+/// limited. Apart from `last(f)`, the values are passed through token by
+/// token and nothing more is pulled from `f` once the result is known, so
+/// taking the head of a huge input only reads as much of it as is needed.
+pub struct Limit<Stream>
+where
+    Stream: JQStream,
+{
+    finished: bool,
+    stream: Sanitized<Stream>,
+    mode: LimitMode,
+    buf: VecDeque<Token>,
+}
+
+impl<Stream> Limit<Stream>
+where
+    Stream: JQStream,
+{
+    pub(crate) fn new(stream: Stream, mode: LimitMode) -> Self {
+        Self {
+            finished: false,
+            stream: stream.sanitize(),
+            mode,
+            buf: VecDeque::new(),
+        }
+    }
+
+    /// Passes the next token of the current value through, counting the
+    /// value off once its last token has been read.
+    fn pass_through(&mut self) -> Option<crate::Item> {
+        let remaining = match &mut self.mode {
+            LimitMode::Limit(remaining) => remaining,
+            _ => unreachable!(),
+        };
+
+        if *remaining == 0 {
+            self.finished = true;
+            return None;
+        }
+
+        let token = self.stream.next()?;
+        if token.is_ok() && self.stream.get_path().is_empty() {
+            *remaining -= 1;
+        }
+        Some(token)
+    }
+}
+
+impl<Stream> Iterator for Limit<Stream>
+where
+    Stream: JQStream,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.buf.pop_front() {
+            return Some(Ok(token));
+        }
+
+        if self.finished {
+            return None;
+        }
+
+        match self.mode {
+            LimitMode::Limit(_) => self.pass_through(),
+            LimitMode::Nth(n) => {
+                for _ in 0..n {
+                    let skipped = match self.stream.next() {
+                        None => Ok(()),
+                        Some(Err(err)) => Err(err),
+                        Some(Ok(_)) => self.stream.skip_value(),
+                    };
+                    if let Err(err) = skipped {
+                        self.finished = true;
+                        return Some(Err(err));
+                    }
+                }
+
+                self.mode = LimitMode::Limit(1);
+                self.pass_through()
+            }
+            LimitMode::Last => {
+                self.finished = true;
+                let mut last = None;
+                while let Some(value) = Value::next_from(&mut self.stream) {
+                    match value {
+                        Err(err) => return Some(Err(err)),
+                        Ok(value) => last = Some(value),
+                    }
+                }
+
+                last?.push_tokens(&mut self.buf);
+                self.buf.pop_front().map(Ok)
+            }
+            LimitMode::IsEmpty => {
+                self.finished = true;
+                match self.stream.next() {
+                    None => Some(Ok(Token::True)),
+                    Some(Err(err)) => Some(Err(err)),
+                    Some(Ok(_)) => Some(Ok(Token::False)),
+                }
+            }
+        }
+    }
+}
+
+impl<Stream> SanitizedJQStream for Limit<Stream> where Stream: JQStream {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_support::values, SanitizedJQStream};
+
+    #[test]
+    fn takes_the_head_of_the_input() {
+        assert_eq!(
+            values("1 [2, 3] 4").limit(2).to_string().unwrap(),
+            "1\n[2,3]\n"
+        );
+        assert_eq!(values("1 2").limit(0).to_string().unwrap(), "");
+        assert_eq!(values("1 2").first().to_string().unwrap(), "1\n");
+        assert_eq!(values("").first().to_string().unwrap(), "");
+    }
+
+    #[test]
+    fn stops_reading_once_the_result_is_known() {
+        assert_eq!(values("1 2 }").limit(2).to_string().unwrap(), "1\n2\n");
+        assert_eq!(values("1 }").isempty().to_string().unwrap(), "false\n");
+        assert!(values("1 }").limit(2).to_string().is_err());
+    }
+
+    #[test]
+    fn picks_later_values() {
+        assert_eq!(values("1 2 3").json_last().to_string().unwrap(), "3\n");
+        assert_eq!(values("1 [2] 3").json_nth(1).to_string().unwrap(), "[2]\n");
+        assert_eq!(values("1").json_nth(1).to_string().unwrap(), "");
+        assert_eq!(values("").isempty().to_string().unwrap(), "true\n");
+    }
+}
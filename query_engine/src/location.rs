@@ -22,8 +22,6 @@ impl Location {
 
 impl Display for Location {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let line: usize = self.line.into();
-        let col: usize = self.col.into();
-        write!(f, "line: {0}, col: {1}", line, col)
+        write!(f, "line: {0}, col: {1}", self.line, self.col)
     }
 }
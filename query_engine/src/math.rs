@@ -0,0 +1,209 @@
+use std::collections::VecDeque;
+
+use crate::{budget::Spending, value::Value, JQErr, JQStream, Sanitized, SanitizedJQStream, Token};
+
+/// The shape of a math builtin. Most of jq's math builtins are thin
+/// wrappers around a libm function, so they only differ in arity and
+/// in what kind of value they produce.
+pub(crate) enum MathOp {
+    /// `f(.)`, producing a number.
+    Unary(fn(f64) -> f64),
+    /// `f(.; y)`, producing a number.
+    Binary(fn(f64, f64) -> f64, f64),
+    /// `f(.; y; z)`, producing a number.
+    Ternary(fn(f64, f64, f64) -> f64, f64, f64),
+    /// `f(.)`, producing a two element array such as `frexp`'s
+    /// `[mantissa, exponent]`.
+    Pair(fn(f64) -> (f64, f64)),
+    /// `f(.)`, producing a boolean.
+    Predicate(fn(f64) -> bool),
+    /// A constant such as `nan` or `infinite`, which ignores its input.
+    Constant(f64),
+}
+
+/// A struct for handling jq's math builtins such as `floor`, `sqrt`,
+/// `pow` or `isnan`. The active budget is spent on every token of their
+/// inputs, including those a constant skips over.
+pub struct Math<Stream>
+where
+    Stream: JQStream,
+{
+    finished: bool,
+    stream: Sanitized<Spending<Stream>>,
+    op: MathOp,
+    buf: VecDeque<Token>,
+}
+
+impl<Stream> Math<Stream>
+where
+    Stream: JQStream,
+{
+    pub(crate) fn new(stream: Stream, op: MathOp) -> Self {
+        Self {
+            finished: false,
+            stream: Spending::new(stream).sanitize(),
+            op,
+            buf: VecDeque::new(),
+        }
+    }
+}
+
+impl<Stream> Iterator for Math<Stream>
+where
+    Stream: JQStream,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.buf.pop_front() {
+            return Some(Ok(token));
+        }
+
+        if self.finished {
+            return None;
+        }
+
+        let token = match self.stream.next()? {
+            Err(err) => {
+                self.finished = true;
+                return Some(Err(err));
+            }
+            Ok(token) => token,
+        };
+
+        if let MathOp::Constant(constant) = self.op {
+            if let Err(err) = self.stream.skip_value() {
+                self.finished = true;
+                return Some(Err(err));
+            }
+            return Some(Ok(Token::ParsedNumber(constant)));
+        }
+
+        let value = match token.as_f64() {
+            Some(value) => value,
+            None => {
+                self.finished = true;
+                return match Value::parse(token, &mut self.stream) {
+                    Err(err) => Some(Err(err)),
+                    Ok(value) => Some(Err(JQErr::StreamOperationFailed(
+                        format!("{} number required", value.describe()).into(),
+                    ))),
+                };
+            }
+        };
+
+        match self.op {
+            MathOp::Unary(op) => Some(Ok(Token::ParsedNumber(op(value)))),
+            MathOp::Binary(op, y) => Some(Ok(Token::ParsedNumber(op(value, y)))),
+            MathOp::Ternary(op, y, z) => Some(Ok(Token::ParsedNumber(op(value, y, z)))),
+            MathOp::Pair(op) => {
+                let (first, second) = op(value);
+                self.buf.extend([
+                    Token::ParsedNumber(first),
+                    Token::Comma,
+                    Token::ParsedNumber(second),
+                    Token::ArrayEnd,
+                ]);
+                Some(Ok(Token::ArrayStart))
+            }
+            MathOp::Predicate(op) => Some(Ok(if op(value) { Token::True } else { Token::False })),
+            MathOp::Constant(_) => unreachable!("constants are handled above"),
+        }
+    }
+}
+
+impl<Stream> SanitizedJQStream for Math<Stream> where Stream: JQStream {}
+
+/// Returns the fraction `x` is made of, scaled into the range `[1, 2)`.
+pub(crate) fn significand(x: f64) -> f64 {
+    if x == 0.0 || !x.is_finite() {
+        x
+    } else {
+        libm::scalbn(x, -libm::ilogb(x))
+    }
+}
+
+/// Returns the unbiased exponent of `x` as a float.
+pub(crate) fn logb(x: f64) -> f64 {
+    if x == 0.0 {
+        f64::NEG_INFINITY
+    } else if x.is_infinite() {
+        f64::INFINITY
+    } else if x.is_nan() {
+        x
+    } else {
+        libm::ilogb(x) as f64
+    }
+}
+
+/// `x * 2^y`, with `y` truncated towards zero the same way C's `scalb` does.
+pub(crate) fn scalb(x: f64, y: f64) -> f64 {
+    if y.is_nan() {
+        f64::NAN
+    } else {
+        libm::scalbn(x, y.clamp(i32::MIN as f64, i32::MAX as f64) as i32)
+    }
+}
+
+pub(crate) fn frexp(x: f64) -> (f64, f64) {
+    let (mantissa, exponent) = libm::frexp(x);
+    (mantissa, exponent as f64)
+}
+
+pub(crate) fn lgamma_r(x: f64) -> (f64, f64) {
+    let (value, sign) = libm::lgamma_r(x);
+    (value, sign as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_support::values, JQErr, SanitizedJQStream};
+
+    #[test]
+    fn runs_unary_functions_over_each_input() {
+        assert_eq!(
+            values("4 9 2.25").sqrt().to_string().unwrap(),
+            "2\n3\n1.5\n"
+        );
+        assert_eq!(values("-1.5 1.5").floor().to_string().unwrap(), "-2\n1\n");
+        assert_eq!(values("2.5 3.5").rint().to_string().unwrap(), "2\n4\n");
+        assert_eq!(values("8").logb().to_string().unwrap(), "3\n");
+        assert_eq!(values("12").significand().to_string().unwrap(), "1.5\n");
+        assert_eq!(
+            values("1 3").gamma().to_string().unwrap(),
+            "0\n0.6931471805599453\n"
+        );
+    }
+
+    #[test]
+    fn runs_functions_with_arguments() {
+        assert_eq!(values("2").pow(10.0).to_string().unwrap(), "1024\n");
+        assert_eq!(values("3").ldexp(2).to_string().unwrap(), "12\n");
+        assert_eq!(values("7").fmod(4.0).to_string().unwrap(), "3\n");
+        assert_eq!(values("7").drem(4.0).to_string().unwrap(), "-1\n");
+        assert_eq!(values("2").fma(3.0, 4.0).to_string().unwrap(), "10\n");
+    }
+
+    #[test]
+    fn produces_pairs_and_booleans() {
+        assert_eq!(values("8").frexp().to_string().unwrap(), "[0.5,4]\n");
+        assert_eq!(values("3.5").modf().to_string().unwrap(), "[0.5,3]\n");
+        assert_eq!(
+            values("1 0").isnormal().to_string().unwrap(),
+            "true\nfalse\n"
+        );
+        assert_eq!(values("null").nan().isnan().to_string().unwrap(), "true\n");
+        assert_eq!(
+            values("[1]").infinite().isinfinite().to_string().unwrap(),
+            "true\n"
+        );
+    }
+
+    #[test]
+    fn rejects_inputs_which_are_not_values() {
+        assert!(matches!(
+            values("\"a\"").sqrt().to_string(),
+            Err(JQErr::StreamOperationFailed(msg)) if &*msg == "string (\"a\") number required"
+        ));
+    }
+}
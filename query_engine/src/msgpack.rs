@@ -0,0 +1,492 @@
+use std::collections::VecDeque;
+
+use crate::{
+    binary::{integer, tagged_value, BinaryBytes, BinaryTags, ByteReader},
+    raw::LimitCheck,
+    value::{ObjectEntries, Value},
+    JQErr, JQStream, ParseLimits, Sanitized, SanitizedJQStream, Token,
+};
+
+/// Reads MessagePack into a stream of JSON tokens, producing every
+/// top-level object in the input as a separate value. Map keys which
+/// aren't strings become their compact JSON, since JSON only allows string
+/// keys, and extension types are handled like CBOR tags.
+pub struct MsgpackTokens<Bytes>
+where
+    Bytes: Iterator<Item = u8>,
+{
+    finished: bool,
+    reader: ByteReader<Bytes>,
+    bytes: BinaryBytes,
+    tags: BinaryTags,
+    buf: VecDeque<Token>,
+}
+
+impl<Bytes> MsgpackTokens<Bytes>
+where
+    Bytes: Iterator<Item = u8>,
+{
+    pub(crate) fn new(bytes: Bytes, bytes_mode: BinaryBytes, tags: BinaryTags) -> Self {
+        Self {
+            finished: false,
+            reader: ByteReader::new(bytes, "MessagePack"),
+            bytes: bytes_mode,
+            tags,
+            buf: VecDeque::new(),
+        }
+    }
+
+    /// Sets limits on the input, such as how deeply it may be nested.
+    /// Nesting is always limited, as objects are decoded recursively, to a
+    /// depth of 256 unless `limits` sets another.
+    ///
+    /// Strings are measured before they're read and items are counted as
+    /// they're decoded, so neither an oversized string nor too many items
+    /// are ever buffered. The tokens of each top-level value are checked
+    /// exactly once it's decoded, with errors at the offset of its last
+    /// byte.
+    pub fn with_limits(mut self, limits: ParseLimits) -> Self {
+        self.reader.limits = LimitCheck::new(limits);
+        self
+    }
+
+    fn array(&mut self, len: u64) -> Result<Value, JQErr> {
+        self.reader.enter()?;
+        let mut items = Vec::new();
+        for _ in 0..len {
+            items.push(self.value()?);
+        }
+        self.reader.leave();
+        Ok(Value::Array(items))
+    }
+
+    fn map(&mut self, len: u64) -> Result<Value, JQErr> {
+        self.reader.enter()?;
+        let mut entries = ObjectEntries::default();
+        for _ in 0..len {
+            let key = match self.value()? {
+                Value::String(key) => key,
+                key => key.to_json()?.into(),
+            };
+            entries.insert(key, self.value()?);
+        }
+        self.reader.leave();
+        Ok(entries.into_value())
+    }
+
+    fn ext(&mut self, len: u64) -> Result<Value, JQErr> {
+        let kind = self.reader.byte()? as i8;
+        let data = self.reader.bytes(len, self.bytes)?;
+        Ok(tagged_value(
+            Value::Number(kind.to_string().into()),
+            data,
+            self.tags,
+        ))
+    }
+
+    fn value(&mut self) -> Result<Value, JQErr> {
+        let int = |num: i128| Value::Number(num.to_string().into());
+
+        let marker = self.reader.byte()?;
+        self.reader.count_item()?;
+        Ok(match marker {
+            0x00..=0x7f => int(marker.into()),
+            0x80..=0x8f => self.map(u64::from(marker & 0x0f))?,
+            0x90..=0x9f => self.array(u64::from(marker & 0x0f))?,
+            0xa0..=0xbf => Value::String(self.reader.text(u64::from(marker & 0x1f))?.into()),
+            0xc0 => Value::Null,
+            0xc2 => Value::False,
+            0xc3 => Value::True,
+            0xc4..=0xc6 => {
+                let len = self.reader.uint(1 << (marker - 0xc4))?;
+                self.reader.bytes(len, self.bytes)?
+            }
+            0xc7..=0xc9 => {
+                let len = self.reader.uint(1 << (marker - 0xc7))?;
+                self.ext(len)?
+            }
+            0xca => Value::ParsedNumber(f64::from(f32::from_bits(self.reader.uint(4)? as u32))),
+            0xcb => Value::ParsedNumber(f64::from_bits(self.reader.uint(8)?)),
+            0xcc..=0xcf => int(self.reader.uint(1 << (marker - 0xcc))?.into()),
+            0xd0..=0xd3 => {
+                let len = 1 << (marker - 0xd0);
+                let unsigned = self.reader.uint(len)?;
+                // Sign extends the integer from its width.
+                let shift = 64 - 8 * len;
+                int((((unsigned << shift) as i64) >> shift).into())
+            }
+            0xd4..=0xd8 => self.ext(1 << (marker - 0xd4))?,
+            0xd9..=0xdb => {
+                let len = self.reader.uint(1 << (marker - 0xd9))?;
+                Value::String(self.reader.text(len)?.into())
+            }
+            0xdc | 0xdd => {
+                let len = self.reader.uint(2 << (marker - 0xdc))?;
+                self.array(len)?
+            }
+            0xde | 0xdf => {
+                let len = self.reader.uint(2 << (marker - 0xde))?;
+                self.map(len)?
+            }
+            0xe0..=0xff => int((marker as i8).into()),
+            0xc1 => return Err(self.reader.invalid("0xc1 is never used")),
+        })
+    }
+}
+
+impl<Bytes> Iterator for MsgpackTokens<Bytes>
+where
+    Bytes: Iterator<Item = u8>,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.buf.pop_front() {
+            if let Err(err) = self.reader.check(&token) {
+                self.finished = true;
+                self.buf.clear();
+                return Some(Err(err));
+            }
+            return Some(Ok(token));
+        }
+
+        if self.finished || self.reader.is_empty() {
+            return None;
+        }
+
+        self.reader.begin();
+        match self.value() {
+            Ok(value) => {
+                value.push_tokens(&mut self.buf);
+                self.next()
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl<Bytes> SanitizedJQStream for MsgpackTokens<Bytes>
+where
+    Bytes: Iterator<Item = u8>,
+{
+    // Maps are decoded into objects which keep the last of each key.
+    fn has_unique_keys(&self) -> bool {
+        true
+    }
+}
+
+/// Writes a length with the smallest of the markers for 8, 16 and 32 bit
+/// lengths. Formats without an 8 bit marker pass [`None`] for it.
+fn length(len: usize, markers: [Option<u8>; 3], out: &mut VecDeque<u8>) {
+    match (len, markers) {
+        (0..=0xff, [Some(marker), _, _]) => out.extend([marker, len as u8]),
+        (0..=0xffff, [_, Some(marker), _]) => {
+            out.push_back(marker);
+            out.extend((len as u16).to_be_bytes());
+        }
+        (_, [_, _, marker]) => {
+            out.extend(marker);
+            out.extend((len as u32).to_be_bytes());
+        }
+    }
+}
+
+fn encode(value: &Value, out: &mut VecDeque<u8>) {
+    match value {
+        Value::Null => out.push_back(0xc0),
+        Value::False => out.push_back(0xc2),
+        Value::True => out.push_back(0xc3),
+        Value::Number(_) | Value::ParsedNumber(_) => match integer(value) {
+            Some(int @ 0..=0x7f) => out.push_back(int as u8),
+            Some(int @ -32..=-1) => out.push_back(int as i8 as u8),
+            Some(int @ 0..) => match int {
+                0..=0xff => out.extend([0xcc, int as u8]),
+                0x100..=0xffff => {
+                    out.push_back(0xcd);
+                    out.extend((int as u16).to_be_bytes());
+                }
+                0x1_0000..=0xffff_ffff => {
+                    out.push_back(0xce);
+                    out.extend((int as u32).to_be_bytes());
+                }
+                _ => {
+                    out.push_back(0xcf);
+                    out.extend((int as u64).to_be_bytes());
+                }
+            },
+            Some(int) if int >= i128::from(i64::MIN) => match int {
+                -0x80.. => out.extend([0xd0, int as i8 as u8]),
+                -0x8000.. => {
+                    out.push_back(0xd1);
+                    out.extend((int as i16).to_be_bytes());
+                }
+                -0x8000_0000.. => {
+                    out.push_back(0xd2);
+                    out.extend((int as i32).to_be_bytes());
+                }
+                _ => {
+                    out.push_back(0xd3);
+                    out.extend((int as i64).to_be_bytes());
+                }
+            },
+            // Integers which are too small for int 64 are written as floats.
+            Some(_) | None => {
+                out.push_back(0xcb);
+                out.extend(value.as_f64().unwrap_or(f64::NAN).to_be_bytes());
+            }
+        },
+        Value::String(str) => {
+            write_str(str, out);
+        }
+        Value::Array(items) => {
+            match items.len() {
+                len @ 0..=0x0f => out.push_back(0x90 | len as u8),
+                len => length(len, [None, Some(0xdc), Some(0xdd)], out),
+            }
+            for item in items {
+                encode(item, out);
+            }
+        }
+        Value::Object(entries) => {
+            match entries.len() {
+                len @ 0..=0x0f => out.push_back(0x80 | len as u8),
+                len => length(len, [None, Some(0xde), Some(0xdf)], out),
+            }
+            for (key, value) in entries {
+                write_str(key, out);
+                encode(value, out);
+            }
+        }
+    }
+}
+
+fn write_str(str: &str, out: &mut VecDeque<u8>) {
+    match str.len() {
+        len @ 0..=0x1f => out.push_back(0xa0 | len as u8),
+        len => length(len, [Some(0xd9), Some(0xda), Some(0xdb)], out),
+    }
+    out.extend(str.as_bytes());
+}
+
+/// Writes values as MessagePack, one top-level object per value, using
+/// the smallest encoding of every integer and length. Numbers which
+/// aren't integers are written as 64 bit floats.
+pub struct MsgpackBytes<Stream>
+where
+    Stream: JQStream,
+{
+    finished: bool,
+    stream: Sanitized<Stream>,
+    buf: VecDeque<u8>,
+}
+
+impl<Stream> MsgpackBytes<Stream>
+where
+    Stream: JQStream,
+{
+    pub(crate) fn new(stream: Stream) -> Self {
+        Self {
+            finished: false,
+            stream: stream.sanitize(),
+            buf: VecDeque::new(),
+        }
+    }
+}
+
+impl<Stream> Iterator for MsgpackBytes<Stream>
+where
+    Stream: JQStream,
+{
+    type Item = Result<u8, JQErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(byte) = self.buf.pop_front() {
+            return Some(Ok(byte));
+        }
+
+        if self.finished {
+            return None;
+        }
+
+        match Value::next_from(&mut self.stream)? {
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+            Ok(value) => {
+                encode(&value, &mut self.buf);
+                self.buf.pop_front().map(Ok)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        BinaryBytes, BinaryTags, ByteStream, CharStream, JQErr, ParseLimits, SanitizedJQStream,
+    };
+
+    fn decode(bytes: &[u8], tags: BinaryTags) -> Result<String, JQErr> {
+        bytes
+            .iter()
+            .copied()
+            .into_msgpack_tokens(BinaryBytes::Array, tags)
+            .to_string()
+    }
+
+    #[test]
+    fn decodes_every_family() {
+        let bytes = [
+            0x86, // a map of 6 entries
+            0xa1, b'a', 0x7f, // "a": 127
+            0xa1, b'b', 0xd0, 0x9c, // "b": -100
+            0xa1, b'c', 0x92, 0xc3, 0xc0, // "c": [true, null]
+            0xa1, b'd', 0xc4, 0x02, 1, 2, // "d": bin [1, 2]
+            0xa1, b'e', 0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0, // "e": 1.5
+            0x01, 0xc2, // 1: false
+        ];
+
+        assert_eq!(
+            decode(&bytes, BinaryTags::Surface).unwrap(),
+            "{\"a\":127,\"b\":-100,\"c\":[true,null],\"d\":[1,2],\"e\":1.5,\"1\":false}\n"
+        );
+    }
+
+    #[test]
+    fn reads_extension_types_like_tags() {
+        let bytes = [0xd4, 0x05, 0x07, 0xff];
+
+        assert_eq!(
+            decode(&bytes, BinaryTags::Surface).unwrap(),
+            "{\"tag\":5,\"value\":[7]}\n-1\n"
+        );
+        assert_eq!(decode(&bytes, BinaryTags::Drop).unwrap(), "[7]\n-1\n");
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(matches!(
+            decode(&[0x92, 0x01], BinaryTags::Surface),
+            Err(JQErr::UnexpectedEOF)
+        ));
+        assert!(matches!(
+            decode(&[0xc1], BinaryTags::Surface),
+            Err(JQErr::InvalidBinary { offset: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn round_trips_values() {
+        let json = "{\"a\":[1,-2,3.5,\"x\"],\"b\":{\"c\":null,\"d\":true}} -9223372036854775808";
+        let bytes = json
+            .chars()
+            .into_json_tokens()
+            .to_bytes_msgpack()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(
+            decode(&bytes, BinaryTags::Surface).unwrap(),
+            "{\"a\":[1,-2,3.5,\"x\"],\"b\":{\"c\":null,\"d\":true}}\n-9223372036854775808\n"
+        );
+    }
+
+    #[test]
+    fn limits_nesting_instead_of_overflowing_the_stack() {
+        let mut arrays = vec![0x91; 2_000_000];
+        arrays.push(0x00);
+        assert!(matches!(
+            decode(&arrays, BinaryTags::Surface),
+            Err(JQErr::DepthLimitExceeded(_))
+        ));
+
+        let mut maps = [0x81, 0xa0].repeat(1_000_000);
+        maps.push(0x00);
+        assert!(matches!(
+            decode(&maps, BinaryTags::Surface),
+            Err(JQErr::DepthLimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn limits_nesting_to_the_given_depth() {
+        let decode = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .copied()
+                .into_msgpack_tokens(BinaryBytes::Array, BinaryTags::Surface)
+                .with_limits(ParseLimits::default().with_max_depth(2))
+                .to_string()
+        };
+
+        assert_eq!(decode(&[0x91, 0x91, 0x00]).unwrap(), "[[0]]\n");
+        assert!(matches!(
+            decode(&[0x91, 0x91, 0x91, 0x00]),
+            Err(JQErr::DepthLimitExceeded(location)) if location.col() == 2
+        ));
+    }
+
+    #[test]
+    fn limits_strings_and_tokens() {
+        let decode = |bytes: &[u8], limits: ParseLimits| {
+            bytes
+                .iter()
+                .copied()
+                .into_msgpack_tokens(BinaryBytes::Array, BinaryTags::Surface)
+                .with_limits(limits)
+                .to_string()
+        };
+
+        let text = [0xa3, b'a', b'b', b'c'];
+        assert_eq!(
+            decode(&text, ParseLimits::default().with_max_string_len(3)).unwrap(),
+            "\"abc\"\n"
+        );
+        assert!(matches!(
+            decode(&text, ParseLimits::default().with_max_string_len(2)),
+            Err(JQErr::StringLimitExceeded(location)) if location.col() == 0
+        ));
+
+        let array = [0x93, 0x01, 0x02, 0x03];
+        assert_eq!(
+            decode(&array, ParseLimits::default().with_max_tokens(7)).unwrap(),
+            "[1,2,3]\n"
+        );
+        assert!(matches!(
+            decode(&array, ParseLimits::default().with_max_tokens(4)),
+            Err(JQErr::TokenLimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn limits_strings_and_items_before_reading_them() {
+        let decode = |bytes: &[u8], limits: ParseLimits| {
+            bytes
+                .iter()
+                .copied()
+                .into_msgpack_tokens(BinaryBytes::Array, BinaryTags::Surface)
+                .with_limits(limits)
+                .to_string()
+        };
+
+        assert!(matches!(
+            decode(&[0xdb, 0xff, 0xff, 0xff, 0xff], ParseLimits::default().with_max_string_len(10)),
+            Err(JQErr::StringLimitExceeded(location)) if location.col() == 4
+        ));
+        assert!(matches!(
+            decode(&[0xc6, 0xff, 0xff, 0xff, 0xff], ParseLimits::default().with_max_tokens(100)),
+            Err(JQErr::TokenLimitExceeded(location)) if location.col() == 4
+        ));
+
+        let mut array = vec![0xdd, 0x00, 0xff, 0xff, 0xff];
+        array.extend([0x00; 50]);
+        assert!(matches!(
+            decode(&array, ParseLimits::default().with_max_tokens(10)),
+            Err(JQErr::TokenLimitExceeded(location)) if location.col() == 14
+        ));
+    }
+}
@@ -0,0 +1,153 @@
+use std::iter::Fuse;
+
+use crate::{value::Value, CharStream, JQErr, ValueTokens};
+
+/// Reads newline-delimited JSON (JSON Lines), producing each line as its
+/// own document. Blank lines are skipped and a trailing `\r` is ignored.
+///
+/// Unlike [`crate::RawTokenStream`], a malformed line doesn't end the
+/// stream: it produces a [`JQErr::InvalidLine`] with its line number and
+/// reading resumes on the next line. Each document is a separate stream
+/// so it can be queried on its own. To treat the valid lines as one
+/// stream instead, use `documents.filter_map(Result::ok).flatten().sanitize()`.
+pub struct NdjsonDocuments<Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    chars: Fuse<Chars>,
+    line: usize,
+}
+
+impl<Chars> NdjsonDocuments<Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    pub(crate) fn new(chars: Chars) -> Self {
+        Self {
+            chars: chars.fuse(),
+            line: 0,
+        }
+    }
+
+    fn parse_line(line: &str) -> Result<ValueTokens, JQErr> {
+        let mut values = Value::all_from(line.chars().into_json_tokens())?;
+        if values.len() != 1 {
+            return Err(JQErr::StreamOperationFailed(
+                format!(
+                    "Expected exactly one JSON value per line, found {}",
+                    values.len()
+                )
+                .into(),
+            ));
+        }
+
+        Ok(values.pop().expect("one value").into_stream())
+    }
+}
+
+impl<Chars> Iterator for NdjsonDocuments<Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    type Item = Result<ValueTokens, JQErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line_number = self.line;
+            let mut line = String::new();
+            let mut read_any = false;
+            for ch in &mut self.chars {
+                read_any = true;
+                if ch == '\n' {
+                    break;
+                }
+                line.push(ch);
+            }
+
+            if !read_any {
+                return None;
+            }
+
+            self.line += 1;
+            if line.ends_with('\r') {
+                line.pop();
+            }
+
+            if line.trim_matches([' ', '\t']).is_empty() {
+                continue;
+            }
+
+            return Some(Self::parse_line(&line).map_err(|err| JQErr::InvalidLine {
+                line: line_number,
+                err: Box::new(err),
+            }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CharStream, JQErr, SanitizedJQStream};
+
+    fn read(ndjson: &str) -> Vec<Result<String, JQErr>> {
+        ndjson
+            .chars()
+            .into_ndjson_documents()
+            .map(|document| document.and_then(|tokens| tokens.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn reads_each_line_and_skips_blank_ones() {
+        let documents = read("{\"a\": 1}\r\n\n  \n[1, 2]\n3");
+        assert_eq!(documents.len(), 3);
+        assert_eq!(documents[0].as_ref().unwrap(), "{\"a\":1}\n");
+        assert_eq!(documents[1].as_ref().unwrap(), "[1,2]\n");
+        assert_eq!(documents[2].as_ref().unwrap(), "3\n");
+    }
+
+    #[test]
+    fn recovers_after_a_malformed_line() {
+        let documents = read("1\n{\"a\" 1}\n[1,\n2 3\n\"ok\"\n");
+        assert_eq!(documents.len(), 5);
+        assert_eq!(documents[0].as_ref().unwrap(), "1\n");
+        assert!(matches!(
+            documents[1],
+            Err(JQErr::InvalidLine { line: 1, .. })
+        ));
+        assert!(matches!(
+            documents[2],
+            Err(JQErr::InvalidLine { line: 2, .. })
+        ));
+        assert!(matches!(
+            &documents[3],
+            Err(JQErr::InvalidLine { line: 3, err })
+                if matches!(&**err, JQErr::StreamOperationFailed(msg)
+                    if &**msg == "Expected exactly one JSON value per line, found 2")
+        ));
+        assert_eq!(documents[4].as_ref().unwrap(), "\"ok\"\n");
+    }
+
+    #[test]
+    fn writes_one_compact_value_per_line() {
+        let ndjson = "{\"a\": [1, 2]} \"b\" null"
+            .chars()
+            .into_json_tokens()
+            .to_chars_ndjson()
+            .collect::<Result<String, _>>()
+            .unwrap();
+        assert_eq!(ndjson, "{\"a\":[1,2]}\n\"b\"\nnull\n");
+    }
+
+    #[test]
+    fn escapes_line_breaks_inside_strings() {
+        let ndjson = "[\"a\\nb\", {\"c\\r\": \"d\\u2028\"}] \"e\""
+            .chars()
+            .into_json_tokens()
+            .to_chars_ndjson()
+            .collect::<Result<String, _>>()
+            .unwrap();
+        assert_eq!(ndjson, "[\"a\\nb\",{\"c\\r\":\"d\u{2028}\"}]\n\"e\"\n");
+        assert_eq!(ndjson.lines().count(), 2);
+    }
+}
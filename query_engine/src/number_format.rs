@@ -0,0 +1,184 @@
+/// Formats a number for output the same way jq does. JSON has no
+/// representation for NaN or infinity, so NaN is printed as `null`
+/// and infinities are clamped to the largest finite double.
+///
+/// Like jq's `jvp_dtoa_fmt`, this writes the shortest digits which read
+/// back as the same double (at most 17 significant digits). Integers have
+/// no `.0`, and numbers which would need more than 15 trailing zeros or
+/// more than 3 leading zeros after the decimal point are written with an
+/// exponent, which always has a sign and at least two digits (`1e+100`,
+/// `1e-05`).
+pub(crate) fn format_number(value: f64) -> String {
+    if value.is_nan() {
+        return "null".to_string();
+    }
+    let value = value.clamp(f64::MIN, f64::MAX);
+    let (digits, point) = shortest_digits(value);
+
+    let mut out = String::new();
+    if value.is_sign_negative() {
+        out.push('-');
+    }
+
+    if point <= -4 || point > digits.len() as i32 + 15 {
+        out.push_str(&digits[..1]);
+        if digits.len() > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        let exponent = point - 1;
+        out.push_str(&format!(
+            "e{}{:02}",
+            if exponent < 0 { '-' } else { '+' },
+            exponent.abs()
+        ));
+    } else if point <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat_n('0', point.unsigned_abs() as usize));
+        out.push_str(&digits);
+    } else if point as usize >= digits.len() {
+        out.push_str(&digits);
+        out.extend(std::iter::repeat_n('0', point as usize - digits.len()));
+    } else {
+        out.push_str(&digits[..point as usize]);
+        out.push('.');
+        out.push_str(&digits[point as usize..]);
+    }
+    out
+}
+
+/// Formats a number the way ECMAScript's `Number.prototype.toString`
+/// does, as RFC 8785 requires, or returns [`None`] for NaN and infinities.
+/// Exponents are only used outside of `1e-7..1e21`, and have no padding.
+pub(crate) fn format_number_ecmascript(value: f64) -> Option<String> {
+    if !value.is_finite() {
+        return None;
+    }
+    if value == 0.0 {
+        // Negative zero is written as `0` too.
+        return Some("0".to_string());
+    }
+    let (digits, point) = shortest_digits(value);
+    let len = digits.len() as i32;
+
+    let mut out = String::new();
+    if value.is_sign_negative() {
+        out.push('-');
+    }
+
+    if len <= point && point <= 21 {
+        out.push_str(&digits);
+        out.extend(std::iter::repeat_n('0', (point - len) as usize));
+    } else if 0 < point && point <= 21 {
+        out.push_str(&digits[..point as usize]);
+        out.push('.');
+        out.push_str(&digits[point as usize..]);
+    } else if -6 < point && point <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat_n('0', point.unsigned_abs() as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if len > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        let exponent = point - 1;
+        out.push_str(&format!(
+            "e{}{}",
+            if exponent < 0 { '-' } else { '+' },
+            exponent.abs()
+        ));
+    }
+    Some(out)
+}
+
+/// Gets the shortest significant digits which read back as the same
+/// finite double, along with the position of the decimal point relative
+/// to the start of them.
+fn shortest_digits(value: f64) -> (String, i32) {
+    // Rust's `{:e}` formatting writes the shortest round-trip digits as
+    // `d.ddde-x`.
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("`{:e}` always writes an exponent");
+    let point = exponent.parse::<i32>().expect("exponents are integers") + 1;
+    (mantissa.replace('.', ""), point)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_number, format_number_ecmascript};
+    use crate::{CharStream, SanitizedJQStream};
+
+    #[test]
+    fn formats_numbers_like_jq() {
+        let cases = [
+            (1.0, "1"),
+            (-0.0, "-0"),
+            (123.456, "123.456"),
+            (0.1 + 0.2, "0.30000000000000004"),
+            (1e15, "1000000000000000"),
+            (1e16, "1e+16"),
+            (1.5e16, "15000000000000000"),
+            (1.5e17, "1.5e+17"),
+            (1e100, "1e+100"),
+            (0.0001, "0.0001"),
+            (0.00001, "1e-05"),
+            (1.5e-7, "1.5e-07"),
+            (5e-324, "5e-324"),
+            (f64::MAX, "1.7976931348623157e+308"),
+            (f64::INFINITY, "1.7976931348623157e+308"),
+            (f64::NEG_INFINITY, "-1.7976931348623157e+308"),
+            (f64::NAN, "null"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(format_number(value), expected, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn formats_numbers_like_ecmascript() {
+        let cases = [
+            (1.0, "1"),
+            (-0.0, "0"),
+            (123.456, "123.456"),
+            (1e20, "100000000000000000000"),
+            (1e21, "1e+21"),
+            (0.000001, "0.000001"),
+            (1e-7, "1e-7"),
+            (-1.5e300, "-1.5e+300"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(
+                format_number_ecmascript(value).as_deref(),
+                Some(expected),
+                "{value:?}"
+            );
+        }
+        assert_eq!(format_number_ecmascript(f64::NAN), None);
+        assert_eq!(format_number_ecmascript(f64::INFINITY), None);
+    }
+
+    #[test]
+    fn writes_computed_numbers_but_keeps_literals() {
+        assert_eq!(
+            "1.000 1e1000 0.00001"
+                .chars()
+                .into_json_tokens()
+                .fabs()
+                .to_string()
+                .unwrap(),
+            "1\n1.7976931348623157e+308\n1e-05\n"
+        );
+        assert_eq!(
+            "[1.000, 1e1000]"
+                .chars()
+                .into_json_tokens()
+                .to_string()
+                .unwrap(),
+            "[1.000,1e1000]\n"
+        );
+    }
+}
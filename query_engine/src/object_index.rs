@@ -1,7 +1,14 @@
-use std::rc::Rc;
+use std::{collections::VecDeque, rc::Rc};
 
-use crate::{JQStream, JQErr, Sanitized, SanitizedJQStream, Scope, Token};
+use crate::{value::Value, JQErr, JQStream, Sanitized, SanitizedJQStream, Token};
 
+/// Looks up a key in each top-level object, producing `null` for objects
+/// without it and for `null` itself.
+///
+/// Like jq, the last entry wins when an object repeats the key, so unless
+/// the stream [has unique keys](SanitizedJQStream::has_unique_keys), the
+/// matching value is held until the end of the object. Otherwise, it's
+/// streamed as soon as it's found, and the rest of the object is skipped.
 pub struct ObjectKeyIndex<const EMIT_ERRS: bool, Stream>
 where
     Stream: JQStream,
@@ -9,21 +16,128 @@ where
     finished: bool,
     stream: Sanitized<Stream>,
     key: Rc<str>,
-    matching: bool,
+    buf: VecDeque<Token>,
+    unique: bool,
+    /// Whether the tokens of a matching value are being streamed.
+    streaming: bool,
+    /// Whether the rest of an object is left to skip after streaming its
+    /// matching value.
+    skipping: bool,
 }
 
 impl<const EMIT_ERRS: bool, Stream> ObjectKeyIndex<EMIT_ERRS, Stream>
 where
     Stream: JQStream,
 {
-    pub(crate) fn new(stream: Stream, key: Rc<str>) -> Self {
+    pub(crate) fn new(stream: Stream, key: Rc<str>, unique: bool) -> Self {
         Self {
             finished: false,
             stream: stream.sanitize(),
             key,
-            matching: false,
+            buf: VecDeque::new(),
+            unique,
+            streaming: false,
+            skipping: false,
         }
     }
+
+    fn next_token(&mut self) -> Result<Token, JQErr> {
+        self.stream.next().unwrap_or(Err(JQErr::UnexpectedEOF))
+    }
+
+    /// Reads the entries of an object after its `{`, returning the value
+    /// of the last entry with the key.
+    fn lookup(&mut self) -> Result<Option<Value>, JQErr> {
+        let mut found = None;
+        loop {
+            let key = match self.next_token()? {
+                Token::ObjectEnd => return Ok(found),
+                Token::Comma => continue,
+                Token::String(key) => key,
+                _ => return Err(JQErr::InvalidStream),
+            };
+
+            if !matches!(self.next_token()?, Token::Colon) {
+                return Err(JQErr::InvalidStream);
+            }
+
+            let first = self.next_token()?;
+            if !first.is_value_start() {
+                return Err(JQErr::InvalidStream);
+            }
+
+            if *key == *self.key && self.unique {
+                self.buf.push_back(first);
+                self.stream_match();
+                return Ok(None);
+            } else if *key == *self.key {
+                found = Some(Value::parse(first, &mut self.stream)?);
+            } else {
+                // Skips the rest of the value, leaving the object's entry
+                // as the only scope.
+                while self.stream.get_path().len() > 1 {
+                    self.next_token()?;
+                }
+            }
+        }
+    }
+
+    /// Streams the value after the token just put in `buf`, then skips the
+    /// rest of the object once the value is done.
+    fn stream_match(&mut self) {
+        self.streaming = self.stream.get_path().len() > 1;
+        self.skipping = !self.streaming;
+    }
+
+    /// Skips the rest of the object a value was streamed out of.
+    fn skip_rest(&mut self) -> Result<(), JQErr> {
+        while !self.stream.get_path().is_empty() {
+            self.next_token()?;
+        }
+        self.skipping = false;
+        Ok(())
+    }
+
+    fn cannot_index(&self, kind: &str) -> JQErr {
+        JQErr::StreamOperationFailed(
+            format!("Cannot index {kind} with string \"{}\"", self.key).into(),
+        )
+    }
+
+    /// Indexes the next top-level value, filling `buf` with the result.
+    /// Returns `false` once the stream is exhausted.
+    fn index_next(&mut self) -> Result<bool, JQErr> {
+        let kind = match self.stream.next() {
+            None => return Ok(false),
+            Some(token) => match token? {
+                Token::ObjectStart => {
+                    match self.lookup()? {
+                        Some(value) => value.push_tokens(&mut self.buf),
+                        None if self.buf.is_empty() => self.buf.push_back(Token::Null),
+                        None => {}
+                    }
+                    return Ok(true);
+                }
+                Token::Null => {
+                    self.buf.push_back(Token::Null);
+                    return Ok(true);
+                }
+                Token::ArrayStart => "array",
+                Token::True | Token::False => "boolean",
+                Token::String(_) => "string",
+                Token::Number(_) | Token::ParsedNumber(_) => "number",
+                Token::Colon | Token::Comma | Token::ArrayEnd | Token::ObjectEnd => {
+                    return Err(JQErr::InvalidStream)
+                }
+            },
+        };
+
+        if EMIT_ERRS {
+            return Err(self.cannot_index(kind));
+        }
+        self.stream.skip_value()?;
+        Ok(true)
+    }
 }
 
 impl<const EMIT_ERRS: bool, Stream> Iterator for ObjectKeyIndex<EMIT_ERRS, Stream>
@@ -33,245 +147,38 @@ where
     type Item = crate::Item;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.finished {
-            return None;
-        }
-
         loop {
-            let path = self.stream.get_path();
-            if path.len() > 1 {
-                let next = self.stream.next();
-                if self.matching {
-                    match next {
-                        None => {
-                            self.finished = true;
-                            return Some(Err(JQErr::InvalidStream));
-                        }
-                        Some(Err(err)) => {
-                            self.finished = true;
-                            return Some(Err(err));
-                        }
-                        Some(Ok(token)) => {
-                            return Some(Ok(token));
-                        }
-                    }
-                } else {
-                    continue;
-                }
+            if let Some(token) = self.buf.pop_front() {
+                return Some(Ok(token));
             }
 
-            match path.last().cloned() {
-                None => match self.stream.next() {
-                    None => return None,
-                    Some(Err(err)) => {
-                        self.finished = true;
-                        return Some(Err(err));
-                    }
-                    Some(Ok(token_kind)) => match token_kind {
-                        Token::ArrayStart => {
-                            self.matching = false;
-                            if EMIT_ERRS {
-                                self.finished = true;
-                                return Some(Err(JQErr::StreamOperationFailed(
-                                    format!("Cannot index array with string \"{}\"", self.key)
-                                        .into(),
-                                )));
-                            }
-                        }
-                        Token::ObjectStart => {
-                            let key = match self.stream.next() {
-                                None => {
-                                    self.finished = true;
-                                    return Some(Err(JQErr::InvalidStream));
-                                }
-                                Some(Err(err)) => {
-                                    self.finished = true;
-                                    return Some(Err(err));
-                                }
-                                Some(Ok(Token::String(key))) => key,
-                                Some(Ok(Token::ObjectEnd)) => return Some(Ok(Token::Null)),
-                                Some(Ok(_)) => {
-                                    self.finished = true;
-                                    return Some(Err(JQErr::InvalidStream));
-                                }
-                            };
-
-                            match self.stream.next() {
-                                None => {
-                                    self.finished = true;
-                                    return Some(Err(JQErr::InvalidStream));
-                                }
-                                Some(Err(err)) => {
-                                    self.finished = true;
-                                    return Some(Err(err));
-                                }
-                                Some(Ok(Token::Colon)) => {}
-                                Some(Ok(_)) => {
-                                    self.finished = true;
-                                    return Some(Err(JQErr::InvalidStream));
-                                }
-                            }
-
-                            match self.stream.next() {
-                                None => {
-                                    self.finished = true;
-                                    return Some(Err(JQErr::UnexpectedEOF));
-                                }
-                                Some(Err(err)) => {
-                                    self.finished = true;
-                                    return Some(Err(err));
-                                }
-                                Some(Ok(token_kind)) => {
-                                    if token_kind.is_value_start() {
-                                        self.matching = *key == *self.key;
-                                        if self.matching {
-                                            return Some(Ok(token_kind));
-                                        }
-                                    } else {
-                                        self.finished = true;
-                                        return Some(Err(JQErr::InvalidStream));
-                                    }
-                                }
-                            }
-                        }
-                        Token::True | Token::False => {
-                            if EMIT_ERRS {
-                                self.finished = true;
-                                return Some(Err(JQErr::StreamOperationFailed(
-                                    format!("Cannot index boolean with string \"{}\"", self.key)
-                                        .into(),
-                                )));
-                            } else {
-                                continue;
-                            }
-                        }
-                        Token::Null => return Some(Ok(Token::Null)),
-                        Token::String(_) => {
-                            if EMIT_ERRS {
-                                self.finished = true;
-                                return Some(Err(JQErr::StreamOperationFailed(
-                                    format!("Cannot index string with string \"{}\"", self.key)
-                                        .into(),
-                                )));
-                            } else {
-                                continue;
-                            }
-                        }
-                        Token::Number(_) | Token::ParsedNumber(_) => {
-                            if EMIT_ERRS {
-                                self.finished = true;
-                                return Some(Err(JQErr::StreamOperationFailed(
-                                    format!("Cannot index number with string \"{}\"", self.key)
-                                        .into(),
-                                )));
-                            } else {
-                                continue;
-                            }
-                        }
-                        Token::Colon | Token::Comma | Token::ArrayEnd | Token::ObjectEnd => {
-                            self.finished = true;
-                            return Some(Err(JQErr::InvalidStream));
-                        }
-                    },
-                },
-                Some(Scope::Array(_)) => match self.stream.next() {
-                    None => {
-                        self.finished = true;
-                        return Some(Err(JQErr::InvalidStream));
-                    }
-                    Some(Err(err)) => {
-                        self.finished = true;
-                        return Some(Err(err));
-                    }
-                    Some(Ok(_)) => {
-                        continue;
-                    }
-                },
-                Some(Scope::Object | Scope::ObjectAtKey { .. }) => match self.stream.next() {
-                    None => {
-                        self.finished = true;
-                        return Some(Err(JQErr::InvalidStream));
-                    }
-                    Some(Err(err)) => {
-                        self.finished = true;
-                        return Some(Err(err));
-                    }
-                    Some(Ok(mut token_kind)) => {
-                        match token_kind {
-                            Token::Comma => {}
-                            Token::ObjectEnd => continue,
-                            Token::ArrayEnd
-                            | Token::ArrayStart
-                            | Token::Colon
-                            | Token::False
-                            | Token::Null
-                            | Token::Number(_)
-                            | Token::ObjectStart
-                            | Token::ParsedNumber(_)
-                            | Token::String(_)
-                            | Token::True => {
-                                self.finished = true;
-                                return Some(Err(JQErr::InvalidStream));
-                            }
-                        }
-
-                        match self.stream.next() {
-                            None => {
-                                self.finished = true;
-                                return Some(Err(JQErr::InvalidStream));
-                            }
-                            Some(Err(err)) => {
-                                self.finished = true;
-                                return Some(Err(err));
-                            }
-                            Some(Ok(inner_token_kind)) => token_kind = inner_token_kind,
-                        }
-
-                        let key = if let Token::String(key) = token_kind {
-                            key
-                        } else {
-                            unreachable!();
-                        };
+            if self.finished {
+                return None;
+            }
 
-                        match self.stream.next() {
-                            None => {
-                                self.finished = true;
-                                return Some(Err(JQErr::InvalidStream));
-                            }
-                            Some(Err(err)) => {
-                                self.finished = true;
-                                return Some(Err(err));
-                            }
-                            Some(Ok(Token::Colon)) => {}
-                            Some(Ok(_)) => {
-                                self.finished = true;
-                                return Some(Err(JQErr::InvalidStream));
-                            }
-                        }
+            if self.streaming || self.skipping {
+                let result = if self.streaming {
+                    self.next_token().map(|token| {
+                        self.buf.push_back(token);
+                        self.stream_match();
+                    })
+                } else {
+                    self.skip_rest()
+                };
+                if let Err(err) = result {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+                continue;
+            }
 
-                        match self.stream.next() {
-                            Some(Err(err)) => {
-                                self.finished = true;
-                                return Some(Err(err));
-                            }
-                            Some(Ok(token_kind)) => {
-                                if token_kind.is_value_start() {
-                                    self.matching = *key == *self.key;
-                                    if self.matching {
-                                        return Some(Ok(token_kind));
-                                    }
-                                } else {
-                                    self.finished = true;
-                                    return Some(Err(JQErr::InvalidStream));
-                                }
-                            }
-                            None => {
-                                self.finished = true;
-                                return Some(Err(JQErr::InvalidStream));
-                            }
-                        }
-                    }
-                },
+            match self.index_next() {
+                Ok(true) => {}
+                Ok(false) => self.finished = true,
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
             }
         }
     }
@@ -281,3 +188,63 @@ impl<const EMIT_ERRS: bool, Stream> SanitizedJQStream for ObjectKeyIndex<EMIT_ER
     Stream: JQStream
 {
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{CharStream, DuplicateKeys, JQErr, JQStream, SanitizedJQStream, Token};
+
+    #[test]
+    fn streams_the_match_when_keys_are_unique() {
+        let tokens = "{\"a\":[1,2],\"b\":"
+            .chars()
+            .into_json_tokens()
+            .with_duplicate_keys(DuplicateKeys::KeepFirst)
+            .at_key("a")
+            .collect::<Vec<_>>();
+        assert_eq!(tokens.len(), 6);
+        assert!(matches!(tokens[0], Ok(Token::ArrayStart)));
+        assert!(matches!(tokens[4], Ok(Token::ArrayEnd)));
+        assert!(matches!(tokens[5], Err(JQErr::UnexpectedEOF)));
+    }
+
+    #[test]
+    fn holds_the_last_match_when_keys_may_repeat() {
+        // A stream built by hand, which nothing says has unique keys.
+        let tokens = vec![
+            Ok(Token::ObjectStart),
+            Ok(Token::String("a".into())),
+            Ok(Token::Colon),
+            Ok(Token::Number("1".into())),
+            Ok(Token::Comma),
+            Ok(Token::String("a".into())),
+            Ok(Token::Colon),
+            Ok(Token::ArrayStart),
+            Ok(Token::Number("2".into())),
+            Ok(Token::ArrayEnd),
+            Ok(Token::ObjectEnd),
+            Ok(Token::ObjectStart),
+            Ok(Token::ObjectEnd),
+        ];
+        assert_eq!(
+            tokens
+                .into_iter()
+                .sanitize()
+                .at_key("a")
+                .to_string()
+                .unwrap(),
+            "[2]\nnull\n"
+        );
+    }
+
+    #[test]
+    fn reports_repeated_keys_after_a_streamed_match() {
+        let result = "{\"a\":1,\"a\":2}"
+            .chars()
+            .into_json_tokens()
+            .with_duplicate_keys(DuplicateKeys::Error)
+            .at_key("a")
+            .collect::<Vec<_>>();
+        assert!(matches!(result[0], Ok(Token::Number(ref num)) if &**num == "1"));
+        assert!(matches!(result[1], Err(JQErr::DuplicateKey { .. })));
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{value::Value, JQErr};
+
+/// The largest index jq lets `setpath` grow an array to, rather than
+/// filling memory with nulls.
+const MAX_ARRAY_INDEX: f64 = (i32::MAX >> 2) as f64;
+
+fn path_components(path: &Value) -> Result<&[Value], JQErr> {
+    match path {
+        Value::Array(components) => Ok(components),
+        _ => Err(JQErr::StreamOperationFailed(
+            "Path must be specified as an array".into(),
+        )),
+    }
+}
+
+fn cannot_index(value: &Value, key: &Value) -> JQErr {
+    let key = match key {
+        Value::String(key) => format!("string \"{key}\""),
+        other => other.type_name().to_string(),
+    };
+    JQErr::StreamOperationFailed(format!("Cannot index {} with {key}", value.type_name()).into())
+}
+
+/// jq's `.[key]` for a single path component.
+fn index(value: &Value, key: &Value) -> Result<Value, JQErr> {
+    match (value, key) {
+        (Value::Null, Value::String(_) | Value::Number(_) | Value::ParsedNumber(_)) => {
+            Ok(Value::Null)
+        }
+        (Value::Object(entries), Value::String(key)) => Ok(entries
+            .iter()
+            .rev()
+            .find(|(existing, _)| existing == key)
+            .map(|(_, value)| value.clone())
+            .unwrap_or(Value::Null)),
+        (Value::Array(items), key @ (Value::Number(_) | Value::ParsedNumber(_))) => {
+            let index = key.as_f64().unwrap_or(f64::NAN).floor();
+            let index = if index < 0.0 {
+                index + items.len() as f64
+            } else {
+                index
+            };
+            if index < 0.0 || index.is_nan() {
+                return Ok(Value::Null);
+            }
+            Ok(items.get(index as usize).cloned().unwrap_or(Value::Null))
+        }
+        (value, key) => Err(cannot_index(value, key)),
+    }
+}
+
+/// jq's `getpath(path)`.
+pub(crate) fn getpath(input: &Value, path: &Value) -> Result<Value, JQErr> {
+    let mut current = input.clone();
+    for component in path_components(path)? {
+        current = index(&current, component)?;
+    }
+    Ok(current)
+}
+
+/// jq's `setpath(path; value)`, creating any missing objects and arrays
+/// along the way.
+pub(crate) fn setpath(input: Value, path: &[Value], value: Value) -> Result<Value, JQErr> {
+    let (component, rest) = match path.split_first() {
+        None => return Ok(value),
+        Some(split) => split,
+    };
+
+    match (input, component) {
+        (input @ (Value::Null | Value::Object(_)), Value::String(key)) => {
+            let mut entries = match input {
+                Value::Object(entries) => entries,
+                _ => Vec::new(),
+            };
+            match entries.iter().position(|(existing, _)| existing == key) {
+                Some(position) => {
+                    let child = std::mem::replace(&mut entries[position].1, Value::Null);
+                    entries[position].1 = setpath(child, rest, value)?;
+                }
+                None => entries.push((key.clone(), setpath(Value::Null, rest, value)?)),
+            }
+            Ok(Value::Object(entries))
+        }
+        (
+            input @ (Value::Null | Value::Array(_)),
+            key @ (Value::Number(_) | Value::ParsedNumber(_)),
+        ) => {
+            let mut items = match input {
+                Value::Array(items) => items,
+                _ => Vec::new(),
+            };
+
+            let index = key.as_f64().unwrap_or(f64::NAN).floor();
+            let index = if index < 0.0 {
+                index + items.len() as f64
+            } else {
+                index
+            };
+            if index < 0.0 || index.is_nan() {
+                return Err(JQErr::StreamOperationFailed(
+                    "Out of bounds negative array index".into(),
+                ));
+            }
+            if index > MAX_ARRAY_INDEX {
+                return Err(JQErr::StreamOperationFailed("Array index too large".into()));
+            }
+
+            let index = index as usize;
+            if index >= items.len() {
+                items.resize(index + 1, Value::Null);
+            }
+            let child = std::mem::replace(&mut items[index], Value::Null);
+            items[index] = setpath(child, rest, value)?;
+            Ok(Value::Array(items))
+        }
+        (input, component) => Err(cannot_index(&input, component)),
+    }
+}
+
+/// jq's `pick(pathexps)`, with the paths given as arrays: everything but
+/// the values at `paths` is dropped, keeping the structure around them.
+pub(crate) fn pick(input: &Value, paths: &[Value]) -> Result<Value, JQErr> {
+    let mut picked = Value::Null;
+    for path in paths {
+        let value = getpath(input, path)?;
+        picked = setpath(picked, path_components(path)?, value)?;
+    }
+    Ok(picked)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_support::values, JQErr, SanitizedJQStream};
+
+    #[test]
+    fn gets_the_value_at_each_path() {
+        assert_eq!(
+            values(r#"{"a": [5, {"b": true}]}"#)
+                .getpath(values(r#"["a", 1, "b"] ["a", -2] ["x", "y"] []"#))
+                .to_string()
+                .unwrap(),
+            "true\n5\nnull\n{\"a\":[5,{\"b\":true}]}\n"
+        );
+        assert!(matches!(
+            values(r#"{"a": 1}"#)
+                .getpath(values(r#"["a", "b"]"#))
+                .to_string(),
+            Err(JQErr::StreamOperationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn picks_the_values_at_paths() {
+        assert_eq!(
+            values(r#"{"a": {"b": 1, "c": 2}, "d": [3, 4, 5]}"#)
+                .pick(values(r#"["a", "b"] ["d", 1] ["e"]"#))
+                .to_string()
+                .unwrap(),
+            "{\"a\":{\"b\":1},\"d\":[null,4],\"e\":null}\n"
+        );
+    }
+
+    #[test]
+    fn refuses_to_pick_huge_array_indexes() {
+        for path in ["[1e18]", "[\"a\", 1e1000]", "[536870912]"] {
+            match values("null").pick(values(path)).to_string() {
+                Err(JQErr::StreamOperationFailed(msg)) => {
+                    assert_eq!(&*msg, "Array index too large", "{path}")
+                }
+                other => panic!("{path}: {other:?}"),
+            }
+        }
+        assert!(matches!(
+            values("null").pick(values("[-1e1000]")).to_string(),
+            Err(JQErr::StreamOperationFailed(_))
+        ));
+    }
+}
@@ -0,0 +1,429 @@
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use crate::{JQErr, Location, LoneSurrogates, ParseLimits, RawTokenStream, Token};
+
+/// The characters fed to a [`PushParser`] which its tokenizer hasn't
+/// read yet.
+struct PushChars {
+    chars: Rc<RefCell<VecDeque<char>>>,
+}
+
+impl Iterator for PushChars {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chars.borrow_mut().pop_front()
+    }
+}
+
+/// Where the scan of the fed characters is, relative to the tokens in them.
+enum Scan {
+    /// Between tokens.
+    Between,
+    /// Inside of a string. `len` is the least number of bytes it can
+    /// decode to so far.
+    String {
+        start: Location,
+        len: usize,
+        escaped: bool,
+        hex_digits: u8,
+    },
+    /// Inside of a number or a literal such as `true`, which only ends at
+    /// the next character that can't be part of it.
+    Word {
+        start: Location,
+        len: usize,
+        number: bool,
+    },
+}
+
+/// Parses JSON which arrives in chunks, such as from a socket, without
+/// blocking for the rest of it. Every chunk is passed to
+/// [`feed`](Self::feed), then the tokens it completed are drained by
+/// iterating. [`finish`](Self::finish) ends the input, after which the
+/// rest of the tokens are drained the same way.
+///
+/// Tokens are only read once they're complete, so a string or number split
+/// across chunks waits, partially scanned, for the chunk that ends it. A
+/// number at the very end of the input is only known to be complete once
+/// the input is finished. Iteration returns [`None`] whenever no more
+/// tokens are ready, and stops for good after an error.
+///
+/// Objects which repeat a key are passed through, as
+/// [`DuplicateKeys::KeepLast`](crate::DuplicateKeys::KeepLast) does, so
+/// their entries are drained as soon as they're read.
+pub struct PushParser {
+    chars: Rc<RefCell<VecDeque<char>>>,
+    tokens: RawTokenStream<PushChars>,
+    limits: ParseLimits,
+    scan: Scan,
+    /// The location of the next character to be fed.
+    location: Location,
+    /// How many complete tokens have been scanned, but not read yet.
+    ready: usize,
+    /// The bytes of a character which was split between chunks.
+    partial: Vec<u8>,
+    /// An error found while scanning, to report after the ready tokens.
+    err: Option<JQErr>,
+    finished: bool,
+    failed: bool,
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        let chars = Rc::new(RefCell::new(VecDeque::new()));
+        Self {
+            tokens: RawTokenStream::new(PushChars {
+                chars: chars.clone(),
+            }),
+            chars,
+            limits: ParseLimits::default(),
+            scan: Scan::Between,
+            location: Location::default(),
+            ready: 0,
+            partial: Vec::new(),
+            err: None,
+            finished: false,
+            failed: false,
+        }
+    }
+}
+
+impl PushParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets limits on the input, like
+    /// [`RawTokenStream::with_limits`]. Strings and numbers which are
+    /// too long fail while they're still arriving, rather than being
+    /// buffered until they end.
+    pub fn with_limits(mut self, limits: ParseLimits) -> Self {
+        self.tokens = self.tokens.with_limits(limits);
+        self.limits = limits;
+        self
+    }
+
+    /// Sets how escapes of lone UTF-16 surrogates are read, like
+    /// [`RawTokenStream::with_lone_surrogates`].
+    pub fn with_lone_surrogates(mut self, policy: LoneSurrogates) -> Self {
+        self.tokens = self.tokens.with_lone_surrogates(policy);
+        self
+    }
+
+    /// Adds the next chunk of UTF-8 input. A character may be split
+    /// between chunks, and invalid UTF-8 is replaced with U+FFFD like jq
+    /// does.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        if self.finished {
+            return;
+        }
+
+        let mut bytes = if self.partial.is_empty() {
+            bytes
+        } else {
+            self.partial.extend_from_slice(bytes);
+            &std::mem::take(&mut self.partial)[..]
+        };
+
+        loop {
+            match std::str::from_utf8(bytes) {
+                Ok(str) => {
+                    self.push_str(str);
+                    return;
+                }
+                Err(err) => {
+                    let (valid, rest) = bytes.split_at(err.valid_up_to());
+                    self.push_str(std::str::from_utf8(valid).expect("to be valid UTF-8"));
+                    match err.error_len() {
+                        // The rest of the character is in the next chunk.
+                        None => {
+                            self.partial = rest.to_vec();
+                            return;
+                        }
+                        Some(len) => {
+                            self.push_str(&char::REPLACEMENT_CHARACTER.to_string());
+                            bytes = &rest[len..];
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Ends the input. The tokens which were waiting for it are drained
+    /// like any others, followed by [`JQErr::UnexpectedEOF`] if the last
+    /// document is incomplete.
+    pub fn finish(&mut self) {
+        if !self.partial.is_empty() {
+            self.partial.clear();
+            self.push_str(&char::REPLACEMENT_CHARACTER.to_string());
+        }
+        self.finished = true;
+    }
+
+    fn push_str(&mut self, str: &str) {
+        for ch in str.chars() {
+            // Nothing after an error is read, so there's no need to keep
+            // it.
+            if self.err.is_some() {
+                return;
+            }
+            self.chars.borrow_mut().push_back(ch);
+            self.scan(ch);
+
+            self.location = if ch == '\n' {
+                Location::new(self.location.line() + 1, 0)
+            } else {
+                Location::new(self.location.line(), self.location.col() + 1)
+            };
+        }
+    }
+
+    /// Moves the scan past the next character, counting the tokens it
+    /// completes.
+    fn scan(&mut self, ch: char) {
+        match &mut self.scan {
+            Scan::Between => match ch {
+                ' ' | '\n' | '\r' | '\t' => {}
+                '"' => {
+                    self.scan = Scan::String {
+                        start: self.location.clone(),
+                        len: 0,
+                        escaped: false,
+                        hex_digits: 0,
+                    }
+                }
+                '-' | '0'..='9' | 'a'..='z' | 'A'..='Z' => {
+                    self.scan = Scan::Word {
+                        start: self.location.clone(),
+                        len: 0,
+                        number: matches!(ch, '-' | '0'..='9'),
+                    };
+                    self.scan(ch);
+                }
+                // Anything else is a token of its own, even if it's only
+                // an unexpected character.
+                _ => self.ready += 1,
+            },
+            Scan::String {
+                start,
+                len,
+                escaped,
+                hex_digits,
+            } => {
+                if *hex_digits > 0 {
+                    *hex_digits -= 1;
+                } else if std::mem::take(escaped) {
+                    // Every escape decodes to at least one byte.
+                    *len += 1;
+                    if ch == 'u' {
+                        *hex_digits = 4;
+                    }
+                } else if ch == '\\' {
+                    *escaped = true;
+                } else if ch == '"' {
+                    self.scan = Scan::Between;
+                    self.ready += 1;
+                    return;
+                } else {
+                    *len += ch.len_utf8();
+                }
+
+                if self.limits.max_string_len.is_some_and(|max| *len > max) {
+                    self.err = Some(JQErr::StringLimitExceeded(start.clone()));
+                }
+            }
+            Scan::Word { start, len, number } => {
+                if ch.is_ascii_alphanumeric() || matches!(ch, '.' | '+' | '-') {
+                    *len += 1;
+                    if *number && self.limits.max_number_len.is_some_and(|max| *len > max) {
+                        self.err = Some(JQErr::NumberLimitExceeded(start.clone()));
+                    }
+                    return;
+                }
+
+                self.scan = Scan::Between;
+                self.ready += 1;
+                self.scan(ch);
+            }
+        }
+    }
+}
+
+impl Iterator for PushParser {
+    type Item = Result<Token, JQErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        if self.ready == 0 && (!self.finished || self.err.is_some()) {
+            let err = self.err.take()?;
+            self.failed = true;
+            return Some(Err(err));
+        }
+
+        self.ready = self.ready.saturating_sub(1);
+        let token = self.tokens.next();
+        if matches!(token, Some(Err(_))) {
+            self.failed = true;
+        }
+        token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        CharStream, JQErr, JQStream, Location, LoneSurrogates, ParseLimits, PushParser,
+        SanitizedJQStream, Token,
+    };
+
+    fn drain(parser: &mut PushParser, tokens: &mut Vec<crate::Item>) {
+        tokens.extend(parser.by_ref());
+    }
+
+    fn to_string(tokens: Vec<crate::Item>) -> String {
+        tokens.into_iter().sanitize().to_string().unwrap()
+    }
+
+    fn parse(chunks: &[&[u8]]) -> Vec<crate::Item> {
+        let mut parser = PushParser::new();
+        let mut tokens = Vec::new();
+        for chunk in chunks {
+            parser.feed(chunk);
+            drain(&mut parser, &mut tokens);
+        }
+        parser.finish();
+        drain(&mut parser, &mut tokens);
+        tokens
+    }
+
+    #[test]
+    fn reads_the_same_tokens_wherever_the_input_is_split() {
+        let json = r#"[-12.5e+3, "a\"b\\\u00e9\ud83d\ude00\nc", "éñ😀", true, null, {"k": 0}] 42"#;
+        let expected = json.chars().into_json_tokens().to_string().unwrap();
+        let bytes = json.as_bytes();
+        for split in 0..=bytes.len() {
+            let (first, second) = bytes.split_at(split);
+            assert_eq!(to_string(parse(&[first, second])), expected, "{split}");
+        }
+        let bytes = bytes.chunks(1).collect::<Vec<_>>();
+        assert_eq!(to_string(parse(&bytes)), expected);
+    }
+
+    #[test]
+    fn waits_for_the_chunk_which_ends_a_token() {
+        let mut parser = PushParser::new();
+        let mut tokens = Vec::new();
+        parser.feed(b"[12");
+        drain(&mut parser, &mut tokens);
+        assert!(matches!(tokens[..], [Ok(Token::ArrayStart)]));
+
+        parser.feed(b"3, \"a");
+        drain(&mut parser, &mut tokens);
+        assert!(matches!(
+            &tokens[1..],
+            [Ok(Token::Number(num)), Ok(Token::Comma)] if &**num == "123"
+        ));
+
+        parser.feed(b"b\"] 7");
+        drain(&mut parser, &mut tokens);
+        assert_eq!(tokens.len(), 5);
+
+        parser.finish();
+        drain(&mut parser, &mut tokens);
+        assert_eq!(to_string(tokens), "[123,\"ab\"]\n7\n");
+    }
+
+    #[test]
+    fn reports_an_incomplete_document_when_finished() {
+        let tokens = parse(&[b"[1, \"a"]);
+        assert!(matches!(
+            tokens[..],
+            [
+                Ok(Token::ArrayStart),
+                Ok(Token::Number(_)),
+                Ok(Token::Comma),
+                Err(JQErr::UnexpectedEOF)
+            ]
+        ));
+        assert!(matches!(
+            &parse(&[b"{\"a\":"])[..],
+            [
+                Ok(Token::ObjectStart),
+                Ok(Token::String(key)),
+                Ok(Token::Colon),
+                Err(JQErr::UnexpectedEOF)
+            ] if &**key == "a"
+        ));
+    }
+
+    #[test]
+    fn replaces_invalid_and_unfinished_utf8() {
+        assert_eq!(
+            to_string(parse(&[b"\"a\xffb\" \"\xc3", b"\xa9\""])),
+            "\"a\u{fffd}b\"\n\"é\"\n"
+        );
+        assert!(matches!(
+            parse(&[b"\"a\xe2\x82"])[..],
+            [Err(JQErr::UnexpectedEOF)]
+        ));
+    }
+
+    #[test]
+    fn fails_on_long_strings_while_they_arrive() {
+        let mut parser =
+            PushParser::new().with_limits(ParseLimits::default().with_max_string_len(3));
+        let mut tokens = Vec::new();
+        parser.feed(b"[\"ab\", \"abcd");
+        drain(&mut parser, &mut tokens);
+        assert!(matches!(
+            tokens[..],
+            [
+                Ok(Token::ArrayStart),
+                Ok(Token::String(_)),
+                Ok(Token::Comma),
+                Err(JQErr::StringLimitExceeded(ref location))
+            ] if *location == Location::new(0, 7)
+        ));
+
+        parser.feed(b"\"]");
+        parser.finish();
+        assert!(parser.next().is_none());
+    }
+
+    #[test]
+    fn applies_the_lone_surrogate_policy() {
+        let mut parser = PushParser::new().with_lone_surrogates(LoneSurrogates::Error);
+        parser.feed(br#""\ud83d""#);
+        parser.finish();
+        assert!(matches!(
+            parser.next(),
+            Some(Err(JQErr::InvalidEscapeSequence(location))) if location == Location::new(0, 1)
+        ));
+        assert!(parser.next().is_none());
+    }
+
+    #[test]
+    fn drains_the_entries_of_an_object_before_it_closes() {
+        let mut parser = PushParser::new();
+        let mut tokens = Vec::new();
+        parser.feed(b"{\"a\": [1,2,3], \"b\": ");
+        drain(&mut parser, &mut tokens);
+        assert_eq!(tokens.len(), 13);
+        assert!(matches!(tokens[0], Ok(Token::ObjectStart)));
+        assert!(matches!(tokens[12], Ok(Token::Colon)));
+    }
+
+    #[test]
+    fn reads_deeply_nested_objects_without_recursing() {
+        let depth = 50_000;
+        let json = format!("{}1{}", "{\"a\":".repeat(depth), "}".repeat(depth));
+        let tokens = parse(&[json.as_bytes()]);
+        assert_eq!(tokens.len(), depth * 4 + 1);
+        assert!(tokens.iter().all(Result::is_ok));
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{ExecutionBudget, JQStream, Sanitized, SanitizedJQStream, Token};
+
+/// A struct for handling the `range(upto)`, `range(from; upto)` and
+/// `range(from; upto; by)` jq queries. Like jq, the range is produced
+/// once for every input value and the numbers are generated lazily, so
+/// even an enormous range costs nothing until it is pulled.
+pub struct Range<Stream>
+where
+    Stream: JQStream,
+{
+    finished: bool,
+    stream: Sanitized<Stream>,
+    from: f64,
+    upto: f64,
+    by: f64,
+    current: Option<f64>,
+}
+
+impl<Stream> Range<Stream>
+where
+    Stream: JQStream,
+{
+    pub(crate) fn new(stream: Stream, from: f64, upto: f64, by: f64) -> Self {
+        Self {
+            finished: false,
+            stream: stream.sanitize(),
+            from,
+            upto,
+            by,
+            current: None,
+        }
+    }
+}
+
+impl<Stream> Iterator for Range<Stream>
+where
+    Stream: JQStream,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        // The active budget is spent on every number of every range, as
+        // well as on every input.
+        loop {
+            if let Err(err) = ExecutionBudget::step_active() {
+                self.finished = true;
+                return Some(Err(err));
+            }
+
+            if let Some(current) = self.current {
+                let in_range = if self.by > 0.0 {
+                    current < self.upto
+                } else if self.by < 0.0 {
+                    current > self.upto
+                } else {
+                    false
+                };
+
+                if in_range {
+                    self.current = Some(current + self.by);
+                    return Some(Ok(Token::ParsedNumber(current)));
+                }
+
+                self.current = None;
+            }
+
+            // The input itself is ignored, but there is one range per input.
+            match self.stream.next()? {
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+                Ok(_) => {
+                    if let Err(err) = self.stream.skip_value() {
+                        self.finished = true;
+                        return Some(Err(err));
+                    }
+                    self.current = Some(self.from);
+                }
+            }
+        }
+    }
+}
+
+impl<Stream> SanitizedJQStream for Range<Stream> where Stream: JQStream {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_support::values, SanitizedJQStream};
+
+    #[test]
+    fn counts_up_to_the_limit() {
+        assert_eq!(values("null").range(3.0).to_string().unwrap(), "0\n1\n2\n");
+        assert_eq!(
+            values("null").range_from(1.0, 3.0).to_string().unwrap(),
+            "1\n2\n"
+        );
+        assert_eq!(values("null").range(0.0).to_string().unwrap(), "");
+    }
+
+    #[test]
+    fn counts_by_a_step_in_either_direction() {
+        assert_eq!(
+            values("null")
+                .range_step(0.0, 1.0, 0.25)
+                .to_string()
+                .unwrap(),
+            "0\n0.25\n0.5\n0.75\n"
+        );
+        assert_eq!(
+            values("null")
+                .range_step(3.0, 0.0, -1.0)
+                .to_string()
+                .unwrap(),
+            "3\n2\n1\n"
+        );
+    }
+
+    #[test]
+    fn produces_the_range_once_per_input() {
+        assert_eq!(
+            values("1 \"a\"").range(2.0).to_string().unwrap(),
+            "0\n1\n0\n1\n"
+        );
+    }
+}
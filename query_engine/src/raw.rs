@@ -1,3 +1,8 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    rc::Rc,
+};
+
 use crate::{JQErr, SanitizedJQStream, Scope, Token};
 use crate::{CharLocations, Location, Span};
 
@@ -18,6 +23,147 @@ pub enum JsonParsingState {
     Finished,
 }
 
+/// How `\u` escapes of UTF-16 surrogates which aren't part of a pair,
+/// such as `"\ud83d"`, are read. Rust strings can't hold them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoneSurrogates {
+    /// Replaces each one with U+FFFD, like jq does.
+    Replace,
+    /// Fails with [`JQErr::InvalidEscapeSequence`] at the escape.
+    Error,
+}
+
+/// How objects which repeat a key, such as `{"a": 1, "a": 2}`, are read.
+/// JSON allows them without saying what they mean.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Passes every entry through so objects stay streamed, like jq. Key
+    /// lookups and materialized objects keep the value of the last entry,
+    /// in the position of the first.
+    KeepLast,
+    /// Drops every entry after the first one with the same key.
+    KeepFirst,
+    /// Fails with [`JQErr::DuplicateKey`] at the repeated key.
+    Error,
+}
+
+/// Limits on what a [`RawTokenStream`] reads, for input which can't be
+/// trusted not to exhaust memory. Nothing is limited by default. The YAML,
+/// TOML, CBOR and MessagePack sources take them too, through their own
+/// `with_limits`.
+///
+/// Strings and numbers are measured in bytes of UTF-8 as they're read, so
+/// oversized ones fail before they're buffered. Tokens are counted over
+/// the whole stream, so they also limit the size of every document in it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ParseLimits {
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) max_string_len: Option<usize>,
+    pub(crate) max_number_len: Option<usize>,
+    max_tokens: Option<usize>,
+}
+
+impl ParseLimits {
+    /// Sets how deeply arrays and objects may be nested, failing with
+    /// [`JQErr::DepthLimitExceeded`].
+    pub const fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Sets the longest string or object key allowed, failing with
+    /// [`JQErr::StringLimitExceeded`].
+    pub const fn with_max_string_len(mut self, len: usize) -> Self {
+        self.max_string_len = Some(len);
+        self
+    }
+
+    /// Sets the longest number literal allowed, failing with
+    /// [`JQErr::NumberLimitExceeded`].
+    pub const fn with_max_number_len(mut self, len: usize) -> Self {
+        self.max_number_len = Some(len);
+        self
+    }
+
+    /// Sets how many tokens may be read in total, failing with
+    /// [`JQErr::TokenLimitExceeded`].
+    pub const fn with_max_tokens(mut self, tokens: usize) -> Self {
+        self.max_tokens = Some(tokens);
+        self
+    }
+}
+
+/// Checks the tokens of a source which doesn't read JSON text against its
+/// [`ParseLimits`], counting them and tracking how deeply they're nested.
+#[derive(Default)]
+pub(crate) struct LimitCheck {
+    pub(crate) limits: ParseLimits,
+    tokens: usize,
+    depth: usize,
+}
+
+impl LimitCheck {
+    pub(crate) fn new(limits: ParseLimits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    /// Fails if `token` goes past the limits, with the error at `location`.
+    pub(crate) fn check(
+        &mut self,
+        token: &Token,
+        location: impl FnOnce() -> Location,
+    ) -> Result<(), JQErr> {
+        let limits = self.limits;
+        self.tokens += 1;
+        let err: Option<fn(Location) -> JQErr> = match token {
+            _ if limits.max_tokens.is_some_and(|max| self.tokens > max) => {
+                Some(JQErr::TokenLimitExceeded)
+            }
+            Token::ObjectStart | Token::ArrayStart => {
+                self.depth += 1;
+                limits
+                    .max_depth
+                    .is_some_and(|max| self.depth > max)
+                    .then_some(JQErr::DepthLimitExceeded)
+            }
+            Token::ObjectEnd | Token::ArrayEnd => {
+                self.depth = self.depth.saturating_sub(1);
+                None
+            }
+            Token::String(text) if limits.max_string_len.is_some_and(|max| text.len() > max) => {
+                Some(JQErr::StringLimitExceeded)
+            }
+            Token::Number(text) if limits.max_number_len.is_some_and(|max| text.len() > max) => {
+                Some(JQErr::NumberLimitExceeded)
+            }
+            _ => None,
+        };
+
+        match err {
+            Some(err) => Err(err(location())),
+            None => Ok(()),
+        }
+    }
+
+    /// Fails if `tokens` more tokens would go past the limits, for sources
+    /// which know they're coming before producing them.
+    pub(crate) fn reserve(
+        &self,
+        tokens: usize,
+        location: impl FnOnce() -> Location,
+    ) -> Result<(), JQErr> {
+        match self.limits.max_tokens {
+            Some(max) if self.tokens.saturating_add(tokens) > max => {
+                Err(JQErr::TokenLimitExceeded(location()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 pub struct RawTokenStream<Chars>
 where
     Chars: Iterator<Item = char>,
@@ -26,6 +172,29 @@ where
     state: JsonParsingState,
     source: Tokenizer<Chars>,
     current_object_key_index: usize,
+    lenient: bool,
+    /// A token read ahead of a comma to check for a trailing comma.
+    pending: Option<Result<TokenWithSpan, JQErr>>,
+    duplicate_keys: DuplicateKeys,
+    /// The keys of each open object and where they were first seen, under
+    /// [`DuplicateKeys::KeepFirst`] and [`DuplicateKeys::Error`].
+    keys: Vec<HashMap<Rc<str>, Location>>,
+    /// Where the most recent object key started.
+    key_location: Location,
+    /// Whether a comma between object entries is being held back until
+    /// the entry after it is known to be kept.
+    comma_held: bool,
+    /// A token to produce after a held comma.
+    queued: Option<Token>,
+    /// How many tokens have been read, for [`ParseLimits::with_max_tokens`].
+    tokens: usize,
+    /// The index of the current record, when reading a JSON text sequence.
+    record: Option<usize>,
+    /// Whether a token of the current record has been read.
+    record_started: bool,
+    /// Whether the value of the current record has been read, so that
+    /// another one before the next RS is an error.
+    record_finished: bool,
 }
 
 impl<Chars> RawTokenStream<Chars>
@@ -38,65 +207,233 @@ where
             state: JsonParsingState::Value,
             source: Tokenizer::new(chars),
             current_object_key_index: 0,
+            lenient: false,
+            pending: None,
+            duplicate_keys: DuplicateKeys::KeepLast,
+            keys: Vec::new(),
+            key_location: Location::default(),
+            comma_held: false,
+            queued: None,
+            tokens: 0,
+            record: None,
+            record_started: false,
+            record_finished: false,
         }
     }
-}
 
-impl<Chars> Iterator for RawTokenStream<Chars>
-where
-    Chars: Iterator<Item = char>,
-{
-    type Item = Result<Token, JQErr>;
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.state {
-            JsonParsingState::Finished => None,
-            JsonParsingState::Value | JsonParsingState::FirstArrayValue => match self.source.next()
+    /// Creates a token stream which also accepts the JSON5 and JSONC
+    /// extensions: `//` and `/* */` comments, trailing commas,
+    /// single-quoted strings, unquoted object keys, hexadecimal numbers,
+    /// leading `+` signs and leading or trailing decimal points, and
+    /// `Infinity` and `NaN`.
+    pub(crate) fn new_lenient(chars: Chars) -> Self {
+        let mut stream = Self::new(chars);
+        stream.lenient = true;
+        stream.source.lenient = true;
+        stream
+    }
+
+    /// Creates a token stream which reads an RFC 7464 JSON text sequence,
+    /// whose records each start with RS (0x1E).
+    ///
+    /// As the RFC requires, a record which fails to parse doesn't affect
+    /// the ones after it: it produces a [`JQErr::InvalidRecord`] and
+    /// reading resumes at the next RS. That includes a record cut short by
+    /// the next RS, a record holding more than one value, and a top-level
+    /// number, `true`, `false` or `null` which isn't followed by
+    /// whitespace, as it may have been truncated. Empty records are
+    /// skipped.
+    /// Errors past the [`ParseLimits`] still end the stream.
+    pub(crate) fn new_json_seq(chars: Chars) -> Self {
+        let mut stream = Self::new(chars);
+        stream.record = Some(0);
+        stream.source.record_separators = true;
+        stream
+    }
+
+    /// Sets how escapes of lone UTF-16 surrogates are read. By default,
+    /// they're replaced with U+FFFD like jq does.
+    pub fn with_lone_surrogates(mut self, policy: LoneSurrogates) -> Self {
+        self.source.lone_surrogates = policy;
+        self
+    }
+
+    /// Sets limits on the input, such as how deeply it may be nested.
+    pub fn with_limits(mut self, limits: ParseLimits) -> Self {
+        self.source.limits = limits;
+        self
+    }
+
+    /// Sets how objects which repeat a key are read. By default, every
+    /// entry is passed through and the last one wins, like jq.
+    pub fn with_duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// Reads ahead of a comma, returning whether it closes a container. The
+    /// token is kept for the next call to `next_token`.
+    fn trailing_comma(&mut self) -> bool {
+        self.pending = self.source.next();
+        matches!(
+            self.pending,
+            Some(Ok(TokenWithSpan {
+                kind: Token::ArrayEnd | Token::ObjectEnd,
+                ..
+            }))
+        )
+    }
+
+    /// Gets the next token, rejecting unquoted strings outside of object
+    /// keys, records cut short by a record separator and tokens past the
+    /// limits.
+    fn next_token(&mut self) -> Option<Result<TokenWithSpan, JQErr>> {
+        let token = self.pending.take().or_else(|| self.source.next());
+
+        if let Some(location) = self.source.record_separator.take() {
+            let between_values = self.scopes.is_empty()
+                && matches!(
+                    self.state,
+                    JsonParsingState::Value | JsonParsingState::AfterValue
+                );
+            if !between_values {
+                // The token after the separator starts the next record.
+                self.pending = token;
+                return Some(Err(JQErr::UnexpectedCharacter(location)));
+            }
+
+            if std::mem::take(&mut self.record_started) {
+                self.record = self.record.map(|record| record + 1);
+            }
+            self.record_finished = false;
+        }
+
+        // Unquoted strings are only allowed as object keys.
+        if std::mem::take(&mut self.source.unquoted)
+            && !matches!(
+                self.state,
+                JsonParsingState::FirstObjectKey | JsonParsingState::ObjectKey
+            )
+        {
+            if let Some(Ok(token)) = &token {
+                self.state = JsonParsingState::Finished;
+                return Some(Err(JQErr::UnexpectedCharacter(token.span.start.clone())));
+            }
+        }
+
+        if let Some(Ok(token)) = &token {
+            if let Err(err) = self.check_limits(token) {
+                self.state = JsonParsingState::Finished;
+                return Some(Err(err));
+            }
+        }
+
+        token
+    }
+
+    fn check_limits(&mut self, token: &TokenWithSpan) -> Result<(), JQErr> {
+        let limits = self.source.limits;
+        self.tokens += 1;
+        if limits.max_tokens.is_some_and(|max| self.tokens > max) {
+            return Err(JQErr::TokenLimitExceeded(token.span.start.clone()));
+        }
+
+        match &token.kind {
+            // Every open container has a scope, apart from an object
+            // before its first key, which can't hold another container.
+            Token::ObjectStart | Token::ArrayStart
+                if limits.max_depth.is_some_and(|max| self.scopes.len() >= max) =>
             {
-                None => {
-                    self.state = JsonParsingState::Finished;
-                    if self.scopes.is_empty() {
-                        None
-                    } else {
-                        Some(Err(JQErr::UnexpectedEOF))
-                    }
+                Err(JQErr::DepthLimitExceeded(token.span.start.clone()))
+            }
+            Token::String(text) | Token::Number(text) => self.source.check_len(
+                text,
+                matches!(token.kind, Token::Number(_)),
+                &token.span.start,
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reads the colon and value of an entry whose key is being dropped.
+    fn skip_entry(&mut self) -> Result<(), JQErr> {
+        let depth = self.scopes.len();
+        loop {
+            match self.next_unchecked() {
+                None => return Err(JQErr::UnexpectedEOF),
+                Some(Err(err)) => return Err(err),
+                Some(Ok(_))
+                    if self.scopes.len() == depth
+                        && matches!(self.state, JsonParsingState::AfterValue) =>
+                {
+                    break
                 }
-                Some(Err(err)) => Some(Err(err)),
-                Some(Ok(token)) => match token.kind {
-                    Token::ObjectStart => {
-                        self.current_object_key_index = 0;
-                        self.state = JsonParsingState::FirstObjectKey;
-                        Some(Ok(Token::ObjectStart))
-                    }
-                    Token::ArrayStart => {
-                        self.scopes.push(Scope::Array(0));
-                        self.state = JsonParsingState::FirstArrayValue;
-                        Some(Ok(Token::ArrayStart))
-                    }
-                    Token::ArrayEnd => {
-                        if matches!(self.state, JsonParsingState::FirstArrayValue) {
-                            self.state = JsonParsingState::Value;
-                            assert!(matches!(self.scopes.pop(), Some(Scope::Array(_))));
-                            Some(Ok(Token::ArrayEnd))
+                Some(Ok(_)) => {}
+            }
+        }
+
+        // The next entry takes the dropped entry's index.
+        if let Some(Scope::ObjectAtKey { index, .. }) = self.scopes.last_mut() {
+            *index -= 1;
+        }
+        Ok(())
+    }
+
+    /// Gets the next token without applying the duplicate key policy.
+    fn next_unchecked(&mut self) -> Option<Result<Token, JQErr>> {
+        match self.state {
+            JsonParsingState::Finished => None,
+            JsonParsingState::Value | JsonParsingState::FirstArrayValue => {
+                match self.next_token() {
+                    None => {
+                        self.state = JsonParsingState::Finished;
+                        if self.scopes.is_empty() {
+                            None
                         } else {
-                            Some(Err(JQErr::UnexpectedCharacter(token.span.start)))
+                            Some(Err(JQErr::UnexpectedEOF))
                         }
                     }
-                    Token::String(_)
-                    | Token::Number(_)
-                    | Token::ParsedNumber(_)
-                    | Token::True
-                    | Token::False
-                    | Token::Null => {
-                        self.state = JsonParsingState::AfterValue;
-                        Some(Ok(token.kind))
-                    }
-                    Token::ObjectEnd | Token::Colon | Token::Comma => {
-                        Some(Err(JQErr::UnexpectedCharacter(token.span.start)))
-                    }
-                },
-            },
+                    Some(Err(err)) => Some(Err(err)),
+                    Some(Ok(token)) => match token.kind {
+                        Token::ObjectStart => {
+                            self.current_object_key_index = 0;
+                            self.state = JsonParsingState::FirstObjectKey;
+                            Some(Ok(Token::ObjectStart))
+                        }
+                        Token::ArrayStart => {
+                            self.scopes.push(Scope::Array(0));
+                            self.state = JsonParsingState::FirstArrayValue;
+                            Some(Ok(Token::ArrayStart))
+                        }
+                        Token::ArrayEnd => {
+                            if matches!(self.state, JsonParsingState::FirstArrayValue) {
+                                assert!(matches!(self.scopes.pop(), Some(Scope::Array(_))));
+                                self.state = match self.scopes.last() {
+                                    None => JsonParsingState::Value,
+                                    Some(_) => JsonParsingState::AfterValue,
+                                };
+                                Some(Ok(Token::ArrayEnd))
+                            } else {
+                                Some(Err(JQErr::UnexpectedCharacter(token.span.start)))
+                            }
+                        }
+                        Token::String(_)
+                        | Token::Number(_)
+                        | Token::ParsedNumber(_)
+                        | Token::True
+                        | Token::False
+                        | Token::Null => {
+                            self.state = JsonParsingState::AfterValue;
+                            Some(Ok(token.kind))
+                        }
+                        Token::ObjectEnd | Token::Colon | Token::Comma => {
+                            Some(Err(JQErr::UnexpectedCharacter(token.span.start)))
+                        }
+                    },
+                }
+            }
             JsonParsingState::FirstObjectKey | JsonParsingState::ObjectKey => {
-                match self.source.next() {
+                match self.next_token() {
                     None => Some(Err(JQErr::UnexpectedEOF)),
                     Some(Err(err)) => Some(Err(err)),
                     Some(Ok(token)) => match token.kind {
@@ -114,6 +451,7 @@ where
                             }
                         }
                         Token::String(key) => {
+                            self.key_location = token.span.start;
                             if matches!(self.state, JsonParsingState::FirstObjectKey) {
                                 self.scopes.push(Scope::ObjectAtKey {
                                     index: 0,
@@ -142,7 +480,7 @@ where
                     },
                 }
             }
-            JsonParsingState::ObjectColon => match self.source.next() {
+            JsonParsingState::ObjectColon => match self.next_token() {
                 None => Some(Err(JQErr::UnexpectedEOF)),
                 Some(Err(err)) => Some(Err(err)),
                 Some(Ok(token)) => match token.kind {
@@ -163,7 +501,7 @@ where
                     | Token::Null => Some(Err(JQErr::UnexpectedCharacter(token.span.start))),
                 },
             },
-            JsonParsingState::AfterValue => match self.source.next() {
+            JsonParsingState::AfterValue => match self.next_token() {
                 None => {
                     if self.scopes.is_empty() {
                         None
@@ -173,6 +511,7 @@ where
                 }
                 Some(Err(err)) => Some(Err(err)),
                 Some(Ok(token)) => match token.kind {
+                    Token::Comma if self.lenient && self.trailing_comma() => self.next_unchecked(),
                     Token::Comma => match self.scopes.pop() {
                         None => Some(Err(JQErr::UnexpectedCharacter(token.span.start))),
                         Some(Scope::Array(index)) => {
@@ -246,7 +585,155 @@ where
     }
 }
 
-impl<Chars> SanitizedJQStream for RawTokenStream<Chars> where Chars: Iterator<Item = char> {}
+impl<Chars> RawTokenStream<Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    /// Gets the next token, applying the duplicate key policy.
+    fn next_checked(&mut self) -> Option<Result<Token, JQErr>> {
+        if self.duplicate_keys == DuplicateKeys::KeepLast {
+            return self.next_unchecked();
+        }
+
+        if let Some(token) = self.queued.take() {
+            return Some(Ok(token));
+        }
+
+        loop {
+            let token = match self.next_unchecked()? {
+                Ok(token) => token,
+                Err(err) => return Some(Err(err)),
+            };
+
+            match token {
+                Token::ObjectStart => self.keys.push(HashMap::new()),
+                Token::ObjectEnd => {
+                    self.keys.pop();
+                }
+                Token::Comma
+                    if self.duplicate_keys == DuplicateKeys::KeepFirst
+                        && matches!(self.state, JsonParsingState::ObjectKey) =>
+                {
+                    self.comma_held = true;
+                    continue;
+                }
+                Token::String(key) if matches!(self.state, JsonParsingState::ObjectColon) => {
+                    let keys = self.keys.last_mut().expect("keys to be inside of objects");
+                    match keys.entry(key.clone()) {
+                        Entry::Vacant(entry) => {
+                            entry.insert(self.key_location.clone());
+                        }
+                        Entry::Occupied(entry) if self.duplicate_keys == DuplicateKeys::Error => {
+                            self.state = JsonParsingState::Finished;
+                            return Some(Err(JQErr::DuplicateKey {
+                                key,
+                                first: entry.get().clone(),
+                                second: self.key_location.clone(),
+                            }));
+                        }
+                        Entry::Occupied(_) => {
+                            self.comma_held = false;
+                            if let Err(err) = self.skip_entry() {
+                                self.state = JsonParsingState::Finished;
+                                return Some(Err(err));
+                            }
+                            continue;
+                        }
+                    }
+
+                    if std::mem::take(&mut self.comma_held) {
+                        self.queued = Some(Token::String(key));
+                        return Some(Ok(Token::Comma));
+                    }
+                    return Some(Ok(Token::String(key)));
+                }
+                _ => {}
+            }
+            return Some(Ok(token));
+        }
+    }
+
+    /// Whether `token` ends its record without the whitespace which shows
+    /// it wasn't truncated.
+    fn truncates_record(&mut self, token: &Token) -> bool {
+        matches!(
+            token,
+            Token::Number(_) | Token::ParsedNumber(_) | Token::True | Token::False | Token::Null
+        ) && self.scopes.is_empty()
+            && !matches!(self.source.peek_char(), Some(' ' | '\n' | '\r' | '\t'))
+    }
+
+    /// Skips the rest of a record which failed to parse, so reading
+    /// resumes with the next one.
+    fn skip_record(&mut self) {
+        // The token of the next record when this one was cut short.
+        if !matches!(self.pending, Some(Ok(_))) {
+            self.pending = None;
+            self.source.skip_record();
+        }
+
+        self.scopes.clear();
+        self.keys.clear();
+        self.state = JsonParsingState::Value;
+        self.comma_held = false;
+        self.queued = None;
+        self.source.unquoted = false;
+        self.record = self.record.map(|record| record + 1);
+        self.record_started = false;
+        self.record_finished = false;
+    }
+}
+
+impl<Chars> Iterator for RawTokenStream<Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    type Item = Result<Token, JQErr>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.record.is_none() {
+            return self.next_checked();
+        }
+
+        let err = match self.next_checked()? {
+            Ok(_) if self.record_finished => {
+                JQErr::StreamOperationFailed("Expected exactly one JSON value per record".into())
+            }
+            Ok(token) if self.truncates_record(&token) => {
+                JQErr::StreamOperationFailed("Potentially truncated top-level value".into())
+            }
+            Ok(token) => {
+                self.record_started = true;
+                // Objects only have a scope once their first key is read.
+                self.record_finished = self.scopes.is_empty()
+                    && !matches!(self.state, JsonParsingState::FirstObjectKey);
+                return Some(Ok(token));
+            }
+            Err(
+                err @ (JQErr::DepthLimitExceeded(_)
+                | JQErr::StringLimitExceeded(_)
+                | JQErr::NumberLimitExceeded(_)
+                | JQErr::TokenLimitExceeded(_)),
+            ) => return Some(Err(err)),
+            Err(err) => err,
+        };
+
+        let record = self.record.unwrap_or_default();
+        self.skip_record();
+        Some(Err(JQErr::InvalidRecord {
+            record,
+            err: Box::new(err),
+        }))
+    }
+}
+
+impl<Chars> SanitizedJQStream for RawTokenStream<Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    fn has_unique_keys(&self) -> bool {
+        self.duplicate_keys != DuplicateKeys::KeepLast
+    }
+}
 
 pub(crate) struct Tokenizer<Chars>
 where
@@ -254,6 +741,17 @@ where
 {
     peeked: Option<char>,
     chars: CharLocations<Chars>,
+    /// Whether the JSON5 and JSONC extensions are accepted.
+    lenient: bool,
+    /// Whether the last token was an unquoted JSON5 object key.
+    unquoted: bool,
+    /// Whether RFC 7464 record separators (0x1E) are skipped between
+    /// tokens.
+    record_separators: bool,
+    /// The location of the last record separator skipped, if any.
+    record_separator: Option<Location>,
+    lone_surrogates: LoneSurrogates,
+    limits: ParseLimits,
 }
 
 impl<Chars> Tokenizer<Chars>
@@ -264,6 +762,26 @@ where
         Self {
             peeked: None,
             chars: CharLocations::new(source),
+            lenient: false,
+            unquoted: false,
+            record_separators: false,
+            record_separator: None,
+            lone_surrogates: LoneSurrogates::Replace,
+            limits: ParseLimits::default(),
+        }
+    }
+
+    /// Fails if the text of a string or number starting at `start` is
+    /// longer than the limits allow.
+    fn check_len(&self, text: &str, number: bool, start: &Location) -> Result<(), JQErr> {
+        let (max, err): (_, fn(Location) -> JQErr) = if number {
+            (self.limits.max_number_len, JQErr::NumberLimitExceeded)
+        } else {
+            (self.limits.max_string_len, JQErr::StringLimitExceeded)
+        };
+        match max {
+            Some(max) if text.len() > max => Err(err(start.clone())),
+            _ => Ok(()),
         }
     }
 
@@ -275,6 +793,244 @@ where
         }
     }
 
+    /// Skips the rest of a JSON text sequence record, up to and including
+    /// the record separator which ends it.
+    fn skip_record(&mut self) {
+        while let Some(ch) = self.next_char() {
+            if ch == '\u{1e}' {
+                let peeked = self.peek_location();
+                self.record_separator =
+                    Some(Location::new(peeked.line(), peeked.col().saturating_sub(1)));
+                return;
+            }
+        }
+    }
+
+    /// Skips over a `//` or `/* */` comment, after its first '/'.
+    fn skip_comment(&mut self) -> Result<(), JQErr> {
+        match self.next_char() {
+            Some('/') => {
+                while !matches!(self.next_char(), None | Some('\n')) {}
+                Ok(())
+            }
+            Some('*') => {
+                let mut previous = None;
+                loop {
+                    match self.next_char() {
+                        None => return Err(JQErr::UnexpectedEOF),
+                        Some('/') if previous == Some('*') => return Ok(()),
+                        next => previous = next,
+                    }
+                }
+            }
+            None => Err(JQErr::UnexpectedEOF),
+            Some(_) => {
+                let peeked = self.peek_location();
+                Err(JQErr::UnexpectedCharacter(Location::new(
+                    peeked.line(),
+                    peeked.col().saturating_sub(1),
+                )))
+            }
+        }
+    }
+
+    /// Reads the rest of a run of characters which can make up a JSON5
+    /// identifier or number.
+    fn read_word(&mut self, first: char, number: bool, start: &Location) -> Result<String, JQErr> {
+        let mut word = String::new();
+        word.push(first);
+        loop {
+            self.check_len(&word, number, start)?;
+            match self.next_char() {
+                Some(ch) if ch.is_alphanumeric() || ch == '_' || ch == '$' || ch == '.' => {
+                    word.push(ch)
+                }
+                // Exponent signs, as in `1e+5`.
+                Some(ch @ ('+' | '-'))
+                    if number && word.ends_with(['e', 'E']) && !word.contains(['x', 'X']) =>
+                {
+                    word.push(ch)
+                }
+                Some(ch) => {
+                    self.peeked = Some(ch);
+                    return Ok(word);
+                }
+                None => return Ok(word),
+            }
+        }
+    }
+
+    fn lenient_identifier(&mut self, first: char, start: Location) -> Result<TokenWithSpan, JQErr> {
+        let word = self.read_word(first, false, &start)?;
+        let kind = match &*word {
+            "true" => Token::True,
+            "false" => Token::False,
+            "null" => Token::Null,
+            "Infinity" => Token::ParsedNumber(f64::INFINITY),
+            "NaN" => Token::ParsedNumber(f64::NAN),
+            _ if word.contains('.') => return Err(JQErr::UnexpectedCharacter(start)),
+            _ => {
+                self.unquoted = true;
+                Token::String(word.into())
+            }
+        };
+
+        Ok(TokenWithSpan {
+            span: Span {
+                start,
+                end: self.peek_location(),
+            },
+            kind,
+        })
+    }
+
+    fn lenient_number(&mut self, first: char, start: Location) -> Result<TokenWithSpan, JQErr> {
+        let word = self.read_word(first, true, &start)?;
+        let (negative, unsigned) = match word.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, word.strip_prefix('+').unwrap_or(&word)),
+        };
+        let sign = if negative { -1.0 } else { 1.0 };
+
+        let kind = if unsigned == "Infinity" {
+            Token::ParsedNumber(sign * f64::INFINITY)
+        } else if unsigned == "NaN" {
+            Token::ParsedNumber(f64::NAN)
+        } else if let Some(hex) = unsigned
+            .strip_prefix("0x")
+            .or_else(|| unsigned.strip_prefix("0X"))
+        {
+            match u64::from_str_radix(hex, 16) {
+                Ok(value) if negative => Token::Number(format!("-{value}").into()),
+                Ok(value) => Token::Number(value.to_string().into()),
+                Err(_) => return Err(JQErr::UnexpectedCharacter(start)),
+            }
+        } else {
+            match decimal_token(&word) {
+                Some(token) => token,
+                None => return Err(JQErr::UnexpectedCharacter(start)),
+            }
+        };
+
+        Ok(TokenWithSpan {
+            span: Span {
+                start,
+                end: self.peek_location(),
+            },
+            kind,
+        })
+    }
+
+    fn peek_char(&mut self) -> Option<char> {
+        if self.peeked.is_none() {
+            self.peeked = self.chars.next();
+        }
+        self.peeked
+    }
+
+    /// Reads an escape sequence in a string, after its backslash, which is
+    /// at `start`.
+    fn escape(&mut self, string: &mut String, start: Location) -> Result<(), JQErr> {
+        match self.next_char() {
+            None => return Err(JQErr::UnexpectedEOF),
+            Some('u') => return self.unicode_escape(string, start),
+            Some('"') => string.push('"'),
+            Some('\'') if self.lenient => string.push('\''),
+            Some('\\') => string.push('\\'),
+            Some('/') => string.push('/'),
+            Some('b') => string.push('\u{0008}'), // backspace
+            Some('f') => string.push('\u{000C}'), // form feed
+            Some('n') => string.push('\n'),
+            Some('r') => string.push('\r'),
+            Some('t') => string.push('\t'),
+            Some('\u{0000}'..='\u{001F}') => {
+                let peeked = self.peek_location();
+                return Err(JQErr::UnescapedEscapeCharacter(Location::new(
+                    peeked.line(),
+                    peeked.col() - 1,
+                )));
+            }
+            _ => {
+                let peeked = self.peek_location();
+                return Err(JQErr::InvalidEscapeSequence(Location::new(
+                    peeked.line(),
+                    peeked.col() - 1,
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the four hex digits of a `\u` escape as a UTF-16 code unit.
+    fn hex_escape(&mut self) -> Result<u16, JQErr> {
+        let mut unit = 0;
+        for _ in 0..4 {
+            match self.next_char().and_then(|digit| digit.to_digit(16)) {
+                Some(digit) => unit = unit << 4 | digit as u16,
+                None => {
+                    let peeked = self.peek_location();
+                    return Err(JQErr::UnexpectedCharacter(Location::new(
+                        peeked.line(),
+                        peeked.col() - 1,
+                    )));
+                }
+            }
+        }
+        Ok(unit)
+    }
+
+    /// Reads a `\u` escape, after its `u`, joining a high surrogate with the
+    /// low surrogate escaped right after it. Surrogates which aren't part
+    /// of a pair are handled by the [`LoneSurrogates`] policy.
+    fn unicode_escape(&mut self, string: &mut String, mut start: Location) -> Result<(), JQErr> {
+        let mut unit = self.hex_escape()?;
+        // Loops for as long as high surrogates are followed by escapes
+        // which can't finish their pair, but may start one themselves.
+        loop {
+            if !(0xD800..=0xDBFF).contains(&unit) {
+                return match char::from_u32(u32::from(unit)) {
+                    Some(ch) => {
+                        string.push(ch);
+                        Ok(())
+                    }
+                    None => self.lone_surrogate(string, start),
+                };
+            }
+
+            if self.peek_char() != Some('\\') {
+                return self.lone_surrogate(string, start);
+            }
+            let next = self.peek_location();
+            self.next_char();
+            if self.peek_char() != Some('u') {
+                self.lone_surrogate(string, start)?;
+                return self.escape(string, next);
+            }
+            self.next_char();
+
+            let low = self.hex_escape()?;
+            if (0xDC00..=0xDFFF).contains(&low) {
+                let code = 0x10000 + ((u32::from(unit) - 0xD800) << 10 | (u32::from(low) - 0xDC00));
+                string.push(char::from_u32(code).expect("surrogate pairs are valid chars"));
+                return Ok(());
+            }
+
+            self.lone_surrogate(string, start)?;
+            unit = low;
+            start = next;
+        }
+    }
+
+    fn lone_surrogate(&mut self, string: &mut String, start: Location) -> Result<(), JQErr> {
+        match self.lone_surrogates {
+            LoneSurrogates::Replace => {
+                string.push(char::REPLACEMENT_CHARACTER);
+                Ok(())
+            }
+            LoneSurrogates::Error => Err(JQErr::InvalidEscapeSequence(start)),
+        }
+    }
+
     fn peek_location(&mut self) -> Location {
         let loc = self.chars.peek_location();
 
@@ -296,7 +1052,7 @@ where
     type Item = Result<TokenWithSpan, JQErr>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let start = self.peek_location();
+        let mut start = self.peek_location();
 
         loop {
             match self.next_char() {
@@ -357,7 +1113,33 @@ where
                                 kind: Token::Comma,
                             }))
                         }
-                        ' ' | '\n' | '\r' | '\t' => continue,
+                        // Tokens start after any whitespace or comments
+                        // before them.
+                        ' ' | '\n' | '\r' | '\t' => {
+                            start = self.peek_location();
+                            continue;
+                        }
+                        // JSON text sequences separate their records with RS.
+                        '\u{1e}' if self.record_separators => {
+                            let peeked = self.peek_location();
+                            self.record_separator =
+                                Some(Location::new(peeked.line(), peeked.col().saturating_sub(1)));
+                            start = peeked;
+                            continue;
+                        }
+                        '/' if self.lenient => match self.skip_comment() {
+                            Ok(()) => {
+                                start = self.peek_location();
+                                continue;
+                            }
+                            Err(err) => return Some(Err(err)),
+                        },
+                        ch if self.lenient && (ch.is_alphabetic() || ch == '_' || ch == '$') => {
+                            return Some(self.lenient_identifier(ch, start))
+                        }
+                        '+' | '-' | '.' | '0'..='9' if self.lenient => {
+                            return Some(self.lenient_number(ch, start))
+                        }
                         't' => {
                             if let Some('r') = self.next_char() {
                                 if let Some('u') = self.next_char() {
@@ -423,110 +1205,53 @@ where
                                 peeked.col() - 1,
                             ))));
                         }
-                        '"' => {
+                        '"' | '\'' if ch == '"' || self.lenient => {
+                            let quote = ch;
                             let mut string = String::new();
                             loop {
                                 match self.next_char() {
                                     None => {
                                         return Some(Err(JQErr::UnexpectedEOF));
                                     }
-                                    Some(ch) => {
-                                        match ch {
-                                            '"' => {
-                                                return Some(Ok(TokenWithSpan {
-                                                    span: Span {
-                                                        start,
-                                                        end: self.peek_location(),
-                                                    },
-                                                    kind: Token::String(string.into()),
-                                                }));
+                                    Some(ch) => match ch {
+                                        ch if ch == quote => {
+                                            return Some(Ok(TokenWithSpan {
+                                                span: Span {
+                                                    start,
+                                                    end: self.peek_location(),
+                                                },
+                                                kind: Token::String(string.into()),
+                                            }));
+                                        }
+                                        '\\' => {
+                                            let peeked = self.peek_location();
+                                            let escape =
+                                                Location::new(peeked.line(), peeked.col() - 1);
+                                            if let Err(err) = self.escape(&mut string, escape) {
+                                                return Some(Err(err));
                                             }
-                                            '\\' => {
-                                                match self.next_char() {
-                                                    None => {
-                                                        return Some(Err(JQErr::UnexpectedEOF))
-                                                    }
-                                                    Some('u') => {
-                                                        let mut unicode_code =
-                                                            String::with_capacity(4);
-                                                        for _ in 0..4 {
-                                                            if let Some(hex_digit) =
-                                                                self.next_char()
-                                                            {
-                                                                if hex_digit.is_ascii_hexdigit() {
-                                                                    unicode_code.push(hex_digit);
-                                                                    continue;
-                                                                }
-                                                            }
-
-                                                            let peeked = self.peek_location();
-                                                            return Some(Err(
-                                                                JQErr::UnexpectedCharacter(
-                                                                    Location::new(
-                                                                        peeked.line(),
-                                                                        peeked.col() - 1,
-                                                                    ),
-                                                                ),
-                                                            ));
-                                                        }
-
-                                                        let code = unicode_code
-                                                            .parse::<u32>()
-                                                            .expect(
-                                                            "Hex code to infallibly parse to u32",
-                                                        );
-                                                        string.push(char::from_u32(code).expect(
-                                                            "Hex code to translate to char",
-                                                        ));
-                                                    }
-                                                    Some('"') => string.push('"'),
-                                                    Some('\\') => string.push('\\'),
-                                                    Some('/') => string.push('/'),
-                                                    Some('b') => string.push('\u{0008}'), // backspace
-                                                    Some('f') => string.push('\u{000C}'), // form feed
-                                                    Some('n') => string.push('\n'),
-                                                    Some('r') => string.push('\r'),
-                                                    Some('t') => string.push('\t'),
-                                                    Some('\u{0000}'..='\u{001F}') => {
-                                                        let peeked = self.peek_location();
-                                                        return Some(Err(
-                                                            JQErr::UnescapedEscapeCharacter(
-                                                                Location::new(
-                                                                    peeked.line(),
-                                                                    peeked.col() - 1,
-                                                                ),
-                                                            ),
-                                                        ));
-                                                    }
-                                                    _ => {
-                                                        let peeked = self.peek_location();
-                                                        return Some(Err(
-                                                            JQErr::InvalidEscapeSequence(
-                                                                Location::new(
-                                                                    peeked.line(),
-                                                                    peeked.col() - 1,
-                                                                ),
-                                                            ),
-                                                        ));
-                                                    }
-                                                }
+                                            if let Err(err) = self.check_len(&string, false, &start)
+                                            {
+                                                return Some(Err(err));
                                             }
-                                            '\u{0000}'..='\u{001F}' | '/' => {
-                                                let peeked = self.peek_location();
-                                                return Some(Err(
-                                                    JQErr::UnescapedEscapeCharacter(
-                                                        Location::new(
-                                                            peeked.line(),
-                                                            peeked.col() - 1,
-                                                        ),
-                                                    ),
-                                                ));
+                                        }
+                                        '\u{0000}'..='\u{001F}' => {
+                                            let peeked = self.peek_location();
+                                            return Some(Err(JQErr::UnescapedEscapeCharacter(
+                                                Location::new(
+                                                    peeked.line(),
+                                                    peeked.col().saturating_sub(1),
+                                                ),
+                                            )));
+                                        }
+                                        ch => {
+                                            string.push(ch);
+                                            if let Err(err) = self.check_len(&string, false, &start)
+                                            {
+                                                return Some(Err(err));
                                             }
-                                            ch => {
-                                                string.push(ch);
-                                            } // just continue
                                         }
-                                    }
+                                    },
                                 }
                             }
                         }
@@ -535,9 +1260,10 @@ where
                             digit.push(ch);
 
                             let first_digit = if matches!(ch, '-') {
-                                if let Some(digit) = self.next_char() {
-                                    if matches!(digit, '0'..='9') {
-                                        digit
+                                if let Some(first_digit) = self.next_char() {
+                                    if first_digit.is_ascii_digit() {
+                                        digit.push(first_digit);
+                                        first_digit
                                     } else {
                                         let peeked = self.peek_location();
                                         return Some(Err(JQErr::UnexpectedCharacter(
@@ -574,6 +1300,7 @@ where
                                     }
                                 }
                                 Some('.') => digit.push('.'),
+                                Some(exponent @ ('e' | 'E')) => digit.push(exponent),
                                 Some(other) => {
                                     self.peeked = Some(other);
                                     return Some(Ok(TokenWithSpan {
@@ -586,7 +1313,7 @@ where
                                 }
                             }
 
-                            if !matches!(digit.chars().last(), Some('.')) {
+                            if !matches!(digit.chars().last(), Some('.' | 'e' | 'E')) {
                                 loop {
                                     let next = self.next_char();
                                     match next {
@@ -601,9 +1328,12 @@ where
                                         }
                                         Some('0'..='9') => {
                                             digit.push(next.unwrap());
+                                            if let Err(err) = self.check_len(&digit, true, &start) {
+                                                return Some(Err(err));
+                                            }
                                         }
-                                        Some('.') => {
-                                            digit.push('.');
+                                        Some('.' | 'e' | 'E') => {
+                                            digit.push(next.unwrap());
                                             break;
                                         }
                                         Some(other) => {
@@ -621,45 +1351,12 @@ where
                             }
 
                             // If we matched a '.', at least one digit is required.
-                            let mut matched_one_digit = !matches!(digit.chars().last(), Some('.'));
-                            loop {
-                                let next = self.next_char();
-                                match next {
-                                    None => {
-                                        if !matched_one_digit {
-                                            let peeked = self.peek_location();
-                                            return Some(Err(JQErr::UnexpectedCharacter(
-                                                Location::new(peeked.line(), peeked.col() - 1),
-                                            )));
-                                        }
-
-                                        return Some(Ok(TokenWithSpan {
-                                            span: Span {
-                                                start,
-                                                end: self.peek_location(),
-                                            },
-                                            kind: Token::Number(digit.into()),
-                                        }));
-                                    }
-                                    Some(next) => match next {
-                                        '0'..='9' => {
-                                            matched_one_digit = true;
-                                            digit.push(next);
-                                        }
-                                        'e' | 'E' => {
-                                            if !matched_one_digit {
-                                                let peeked = self.peek_location();
-                                                return Some(Err(JQErr::UnexpectedCharacter(
-                                                    Location::new(peeked.line(), peeked.col() - 1),
-                                                )));
-                                            }
-
-                                            digit.push(next);
-                                            break;
-                                        }
-                                        other => {
-                                            self.peeked = Some(other);
-
+                            if digit.ends_with('.') {
+                                let mut matched_one_digit = false;
+                                loop {
+                                    let next = self.next_char();
+                                    match next {
+                                        None => {
                                             if !matched_one_digit {
                                                 let peeked = self.peek_location();
                                                 return Some(Err(JQErr::UnexpectedCharacter(
@@ -675,13 +1372,63 @@ where
                                                 kind: Token::Number(digit.into()),
                                             }));
                                         }
-                                    },
+                                        Some(next) => match next {
+                                            '0'..='9' => {
+                                                matched_one_digit = true;
+                                                digit.push(next);
+                                                if let Err(err) =
+                                                    self.check_len(&digit, true, &start)
+                                                {
+                                                    return Some(Err(err));
+                                                }
+                                            }
+                                            'e' | 'E' => {
+                                                if !matched_one_digit {
+                                                    let peeked = self.peek_location();
+                                                    return Some(Err(JQErr::UnexpectedCharacter(
+                                                        Location::new(
+                                                            peeked.line(),
+                                                            peeked.col() - 1,
+                                                        ),
+                                                    )));
+                                                }
+
+                                                digit.push(next);
+                                                break;
+                                            }
+                                            other => {
+                                                self.peeked = Some(other);
+
+                                                if !matched_one_digit {
+                                                    let peeked = self.peek_location();
+                                                    return Some(Err(JQErr::UnexpectedCharacter(
+                                                        Location::new(
+                                                            peeked.line(),
+                                                            peeked.col() - 1,
+                                                        ),
+                                                    )));
+                                                }
+
+                                                return Some(Ok(TokenWithSpan {
+                                                    span: Span {
+                                                        start,
+                                                        end: self.peek_location(),
+                                                    },
+                                                    kind: Token::Number(digit.into()),
+                                                }));
+                                            }
+                                        },
+                                    }
                                 }
                             }
-
-                            // If we matched an 'e' or 'E', at least one digit is required.
-                            let mut matched_one_digit =
-                                !matches!(digit.chars().last(), Some('e' | 'E'));
+                            // The exponent may be signed, and at least one digit is
+                            // required after it.
+                            match self.next_char() {
+                                Some(sign @ ('+' | '-')) => digit.push(sign),
+                                Some(other) => self.peeked = Some(other),
+                                None => {}
+                            }
+                            let mut matched_one_digit = false;
                             loop {
                                 let next = self.next_char();
                                 match next {
@@ -705,6 +1452,9 @@ where
                                         '0'..='9' => {
                                             matched_one_digit = true;
                                             digit.push(next);
+                                            if let Err(err) = self.check_len(&digit, true, &start) {
+                                                return Some(Err(err));
+                                            }
                                         }
                                         other => {
                                             if !matched_one_digit {
@@ -741,8 +1491,429 @@ where
     }
 }
 
+/// Converts a decimal number which might not be valid JSON, such as `+1`,
+/// `.5` or `007`, into a token. Numbers which are valid JSON keep their
+/// original text.
+pub(crate) fn decimal_token(text: &str) -> Option<Token> {
+    let (negative, unsigned) = match text.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+
+    if !unsigned.starts_with(|ch: char| ch.is_ascii_digit() || ch == '.')
+        || unsigned.contains(|ch: char| !ch.is_ascii_digit() && !".eE+-".contains(ch))
+    {
+        return None;
+    }
+
+    if is_json_number(unsigned) {
+        let text = if negative {
+            format!("-{unsigned}")
+        } else {
+            unsigned.to_string()
+        };
+        return Some(Token::Number(text.into()));
+    }
+
+    // Leading and trailing decimal points aren't valid JSON, so these
+    // numbers can't keep their original text.
+    let value = unsigned.parse::<f64>().ok()?;
+    Some(Token::ParsedNumber(if negative { -value } else { value }))
+}
+
+/// Checks whether `text` is an unsigned number in the strict JSON grammar.
+fn is_json_number(text: &str) -> bool {
+    let mut chars = text.chars().peekable();
+    let digits = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+        let mut count = 0;
+        while chars.next_if(|ch| ch.is_ascii_digit()).is_some() {
+            count += 1;
+        }
+        count
+    };
+
+    let leading_zero = chars.peek() == Some(&'0');
+    match digits(&mut chars) {
+        0 => return false,
+        count if leading_zero && count > 1 => return false,
+        _ => {}
+    }
+
+    if chars.next_if_eq(&'.').is_some() && digits(&mut chars) == 0 {
+        return false;
+    }
+
+    if chars.next_if(|ch| matches!(ch, 'e' | 'E')).is_some() {
+        chars.next_if(|ch| matches!(ch, '+' | '-'));
+        if digits(&mut chars) == 0 {
+            return false;
+        }
+    }
+
+    chars.next().is_none()
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct TokenWithSpan {
     pub(crate) span: Span,
     pub(crate) kind: Token,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        CharStream, DuplicateKeys, JQErr, Location, LoneSurrogates, ParseLimits, SanitizedJQStream,
+        Token,
+    };
+
+    fn read(json: &str, limits: ParseLimits) -> Result<String, JQErr> {
+        json.chars()
+            .into_json_tokens()
+            .with_limits(limits)
+            .to_string()
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let limits = ParseLimits::default().with_max_depth(2);
+        assert_eq!(read("[{\"a\":1}]", limits).unwrap(), "[{\"a\":1}]\n");
+        assert!(matches!(
+            read("[[[1]]]", limits),
+            Err(JQErr::DepthLimitExceeded(location)) if location.col() == 2
+        ));
+    }
+
+    #[test]
+    fn limits_strings_keys_and_numbers() {
+        let limits = ParseLimits::default()
+            .with_max_string_len(3)
+            .with_max_number_len(3);
+        assert_eq!(read("{\"abc\":123}", limits).unwrap(), "{\"abc\":123}\n");
+        assert!(matches!(
+            read("{\"abcd\":1}", limits),
+            Err(JQErr::StringLimitExceeded(location)) if location.col() == 1
+        ));
+        assert!(matches!(
+            read("[\"abcd\"]", limits),
+            Err(JQErr::StringLimitExceeded(_))
+        ));
+        assert!(matches!(
+            read("[1, 1234]", limits),
+            Err(JQErr::NumberLimitExceeded(location)) if location.col() == 4
+        ));
+    }
+
+    #[test]
+    fn limits_tokens_across_documents() {
+        let limits = ParseLimits::default().with_max_tokens(7);
+        assert_eq!(read("[1,2,3]", limits).unwrap(), "[1,2,3]\n");
+        assert!(matches!(
+            read("[1,2] 3 4 5", limits),
+            Err(JQErr::TokenLimitExceeded(location)) if location.col() == 10
+        ));
+    }
+
+    fn numbers(json: &str) -> Result<Vec<String>, JQErr> {
+        json.chars()
+            .into_json_tokens()
+            .filter_map(|token| match token {
+                Ok(Token::Number(number)) => Some(Ok(number.to_string())),
+                Ok(_) => None,
+                Err(err) => Some(Err(err)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reads_negative_numbers() {
+        assert_eq!(numbers("[-5, -0.5, -0]").unwrap(), ["-5", "-0.5", "-0"]);
+        assert!(matches!(numbers("-"), Err(JQErr::UnexpectedEOF)));
+    }
+
+    #[test]
+    fn reads_number_exponents() {
+        assert_eq!(
+            numbers("[1e3, 1E+3, -2.5e-3, 0e0]").unwrap(),
+            ["1e3", "1E+3", "-2.5e-3", "0e0"]
+        );
+        assert_eq!(numbers("1e3").unwrap(), ["1e3"]);
+        assert!(matches!(
+            numbers("[1e]"),
+            Err(JQErr::UnexpectedCharacter(_))
+        ));
+        assert!(matches!(
+            numbers("[1.5e+]"),
+            Err(JQErr::UnexpectedCharacter(_))
+        ));
+    }
+
+    #[test]
+    fn starts_tokens_after_whitespace() {
+        let err = "[1,\n  ]".chars().into_json_tokens().find_map(Result::err);
+        assert!(matches!(
+            err,
+            Some(JQErr::UnexpectedCharacter(location)) if location.line() == 1 && location.col() == 2
+        ));
+    }
+
+    #[test]
+    fn accepts_unescaped_slashes_in_strings() {
+        for json in ["\"a/b\"", "\"a\\/b\""] {
+            assert_eq!(
+                json.chars().into_json_tokens().to_string().unwrap(),
+                "\"a/b\"\n"
+            );
+        }
+    }
+
+    fn lenient(json: &str) -> Result<String, JQErr> {
+        json.chars().into_lenient_json_tokens().to_string()
+    }
+
+    #[test]
+    fn lenient_mode_accepts_json5_and_jsonc() {
+        assert_eq!(
+            lenient("{a: 1, $b_2: 'x\\'y', } // line\n/* block */").unwrap(),
+            "{\"a\":1,\"$b_2\":\"x'y\"}\n"
+        );
+        assert_eq!(
+            lenient("[Infinity, -Infinity, NaN, +1, 0x1F, -0xa, .5, 5., ]").unwrap(),
+            "[1.7976931348623157e+308,-1.7976931348623157e+308,null,1,31,-10,0.5,5]\n"
+        );
+    }
+
+    #[test]
+    fn lenient_mode_rejects_what_json5_rejects() {
+        assert!(matches!(
+            lenient("[abc]"),
+            Err(JQErr::UnexpectedCharacter(location)) if location == Location::new(0, 1)
+        ));
+        assert!(matches!(
+            lenient("{a.b: 1}"),
+            Err(JQErr::UnexpectedCharacter(location)) if location == Location::new(0, 1)
+        ));
+        assert!(matches!(
+            lenient("[1,,]"),
+            Err(JQErr::UnexpectedCharacter(location)) if location == Location::new(0, 3)
+        ));
+        assert!(matches!(lenient("1 /* open"), Err(JQErr::UnexpectedEOF)));
+    }
+
+    #[test]
+    fn strict_mode_rejects_json5_and_jsonc() {
+        for json in ["{a: 1}", "'a'", "[1, ]", "1 // c", "0x1F", "NaN"] {
+            assert!(
+                json.chars().into_json_tokens().to_string().is_err(),
+                "{json}"
+            );
+        }
+    }
+
+    fn surrogates(json: &str, policy: LoneSurrogates) -> Result<String, JQErr> {
+        json.chars()
+            .into_json_tokens()
+            .with_lone_surrogates(policy)
+            .to_string()
+    }
+
+    #[test]
+    fn decodes_escapes_and_surrogate_pairs() {
+        assert_eq!(
+            surrogates(
+                r#""\u00e9\u0041\ud83d\ude00\uD83D\uDE00""#,
+                LoneSurrogates::Error
+            )
+            .unwrap(),
+            "\"éA😀😀\"\n"
+        );
+        assert!(matches!(
+            surrogates(r#""\u12g4""#, LoneSurrogates::Replace),
+            Err(JQErr::UnexpectedCharacter(location)) if location == Location::new(0, 5)
+        ));
+    }
+
+    #[test]
+    fn replaces_lone_surrogates() {
+        let cases = [
+            (r#""a\ud83d""#, "\"a\u{fffd}\"\n"),
+            (r#""\ude00x""#, "\"\u{fffd}x\"\n"),
+            (r#""\ud83d\/""#, "\"\u{fffd}/\"\n"),
+            (r#""\ud83d\ud83d\ude00""#, "\"\u{fffd}😀\"\n"),
+            (r#""\ud83dA""#, "\"\u{fffd}A\"\n"),
+        ];
+        for (json, expected) in cases {
+            assert_eq!(
+                surrogates(json, LoneSurrogates::Replace).unwrap(),
+                expected,
+                "{json}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_lone_surrogates_at_their_escape() {
+        let cases = [
+            (r#""ab\ud83dx""#, 3),
+            (r#""\ude00""#, 1),
+            (r#""\ud83d\ud83d\ude00""#, 1),
+            (r#""\ud83d\ude00\ude01""#, 13),
+        ];
+        for (json, col) in cases {
+            assert!(
+                matches!(
+                    surrogates(json, LoneSurrogates::Error),
+                    Err(JQErr::InvalidEscapeSequence(location)) if location == Location::new(0, col)
+                ),
+                "{json}"
+            );
+        }
+    }
+
+    #[test]
+    fn json_seq_tokens_skip_malformed_records() {
+        let tokens = "\u{1e}{\"a\":1}\n\u{1e}[1,\u{1e}{\"b\" 2}\n\u{1e}\u{1e}3\n\u{1e}4"
+            .chars()
+            .into_json_seq_tokens()
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            &tokens[..],
+            [
+                Ok(Token::ObjectStart),
+                Ok(Token::String(_)),
+                Ok(Token::Colon),
+                Ok(Token::Number(_)),
+                Ok(Token::ObjectEnd),
+                Ok(Token::ArrayStart),
+                Ok(Token::Number(_)),
+                Ok(Token::Comma),
+                Err(JQErr::InvalidRecord { record: 1, err: cut_short }),
+                Ok(Token::ObjectStart),
+                Ok(Token::String(_)),
+                Err(JQErr::InvalidRecord { record: 2, .. }),
+                Ok(Token::Number(three)),
+                Err(JQErr::InvalidRecord { record: 4, .. }),
+            ] if matches!(
+                **cut_short,
+                JQErr::UnexpectedCharacter(ref location) if *location == Location::new(1, 4)
+            ) && &**three == "3"
+        ));
+    }
+
+    #[test]
+    fn json_seq_tokens_read_one_value_per_record() {
+        let tokens = "\u{1e}1 2\n\u{1e}12\u{1e}34\n"
+            .chars()
+            .into_json_seq_tokens()
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            &tokens[..],
+            [
+                Ok(Token::Number(_)),
+                Err(JQErr::InvalidRecord { record: 0, .. }),
+                Err(JQErr::InvalidRecord { record: 1, .. }),
+                Ok(Token::Number(last)),
+            ] if &**last == "34"
+        ));
+    }
+
+    #[test]
+    fn record_separators_are_only_read_in_text_sequences() {
+        let mut tokens = "1\u{1e}2".chars().into_json_tokens();
+
+        assert!(matches!(tokens.next(), Some(Ok(_))));
+        assert!(matches!(
+            tokens.next(),
+            Some(Err(JQErr::UnexpectedCharacter(_)))
+        ));
+    }
+
+    #[test]
+    fn json_seq_tokens_read_whole_records() {
+        assert_eq!(
+            "\u{1e}{\"a\":[1,2]}\n\u{1e}true\n"
+                .chars()
+                .into_json_seq_tokens()
+                .to_string()
+                .unwrap(),
+            "{\"a\":[1,2]}\ntrue\n"
+        );
+    }
+
+    const REPEATED: &str = "{\"a\":1,\"b\":2,\"a\":3}";
+
+    #[test]
+    fn lookups_and_materialized_objects_keep_the_last_of_repeated_keys() {
+        let tokens = || REPEATED.chars().into_json_tokens();
+        assert_eq!(tokens().at_key("a").to_string().unwrap(), "3\n");
+        assert_eq!(
+            tokens().tojson().to_string().unwrap(),
+            "\"{\\\"a\\\":3,\\\"b\\\":2}\"\n"
+        );
+        assert_eq!(
+            tokens()
+                .to_chars_canonical()
+                .collect::<Result<String, _>>()
+                .unwrap(),
+            "{\"a\":3,\"b\":2}"
+        );
+        // Streamed output passes every entry through.
+        assert_eq!(tokens().to_string().unwrap(), format!("{REPEATED}\n"));
+    }
+
+    #[test]
+    fn streams_objects_before_they_close() {
+        let mut tokens = std::iter::once('{')
+            .chain("\"a\":1,".chars().cycle())
+            .into_json_tokens();
+        assert!(matches!(tokens.next(), Some(Ok(Token::ObjectStart))));
+        assert!(matches!(tokens.next(), Some(Ok(Token::String(key))) if &*key == "a"));
+    }
+
+    #[test]
+    fn reads_deeply_nested_objects_without_recursing() {
+        let depth = 200_000;
+        let json = format!("{}1{}", "{\"a\":".repeat(depth), "}".repeat(depth));
+        let mut count = 0;
+        for token in json.chars().into_json_tokens() {
+            token.unwrap();
+            count += 1;
+        }
+        assert_eq!(count, depth * 4 + 1);
+    }
+
+    #[test]
+    fn keep_first_drops_later_entries() {
+        let tokens = || {
+            REPEATED
+                .chars()
+                .into_json_tokens()
+                .with_duplicate_keys(DuplicateKeys::KeepFirst)
+        };
+        assert_eq!(tokens().to_string().unwrap(), "{\"a\":1,\"b\":2}\n");
+        assert_eq!(tokens().values().to_string().unwrap(), "1\n2\n");
+        assert_eq!(tokens().at_key("a").to_string().unwrap(), "1\n");
+        assert_eq!(
+            "{\"a\":1,\"a\":{\"b\":[2]},\"c\":3}"
+                .chars()
+                .into_json_tokens()
+                .with_duplicate_keys(DuplicateKeys::KeepFirst)
+                .to_string()
+                .unwrap(),
+            "{\"a\":1,\"c\":3}\n"
+        );
+    }
+
+    #[test]
+    fn error_rejects_repeated_keys() {
+        let result = REPEATED
+            .chars()
+            .into_json_tokens()
+            .with_duplicate_keys(DuplicateKeys::Error)
+            .to_string();
+        assert!(matches!(
+            result,
+            Err(JQErr::DuplicateKey { key, first, second })
+                if &*key == "a" && first == Location::new(0, 1) && second == Location::new(0, 13)
+        ));
+    }
+}
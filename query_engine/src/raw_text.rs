@@ -0,0 +1,207 @@
+use std::{collections::VecDeque, iter::Fuse};
+
+use crate::{
+    to_string_compact::write_token, JQErr, JQStream, Sanitized, SanitizedJQStream, StringEscapes,
+    Token,
+};
+
+/// Reads plain text the way jq's `-R` flag does: every line becomes a
+/// string without its trailing newline. With `SLURP` (jq's `-Rs`), the
+/// whole input becomes a single string instead.
+pub struct RawInput<const SLURP: bool, Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    chars: Fuse<Chars>,
+    finished: bool,
+}
+
+impl<const SLURP: bool, Chars> RawInput<SLURP, Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    pub(crate) fn new(chars: Chars) -> Self {
+        Self {
+            chars: chars.fuse(),
+            finished: false,
+        }
+    }
+}
+
+impl<const SLURP: bool, Chars> Iterator for RawInput<SLURP, Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        if SLURP {
+            self.finished = true;
+            return Some(Ok(Token::String(
+                self.chars.by_ref().collect::<String>().into(),
+            )));
+        }
+
+        let mut line = String::new();
+        let mut read_any = false;
+        for ch in &mut self.chars {
+            read_any = true;
+            if ch == '\n' {
+                break;
+            }
+            line.push(ch);
+        }
+
+        if !read_any {
+            self.finished = true;
+            return None;
+        }
+
+        Some(Ok(Token::String(line.into())))
+    }
+}
+
+impl<const SLURP: bool, Chars> SanitizedJQStream for RawInput<SLURP, Chars> where
+    Chars: Iterator<Item = char>
+{
+}
+
+/// Writes values the way jq's `-r`, `-j` and `--raw-output0` flags do:
+/// top-level strings are written as their raw contents, without quotes or
+/// escapes, and anything else is written as compact JSON. Every value is
+/// followed by `separator`, if there is one.
+pub struct RawChars<Stream>
+where
+    Stream: JQStream,
+{
+    finished: bool,
+    stream: Sanitized<Stream>,
+    separator: Option<char>,
+    buf: VecDeque<char>,
+}
+
+impl<Stream> RawChars<Stream>
+where
+    Stream: JQStream,
+{
+    pub(crate) fn new(stream: Stream, separator: Option<char>) -> Self {
+        Self {
+            finished: false,
+            stream: stream.sanitize(),
+            separator,
+            buf: VecDeque::new(),
+        }
+    }
+}
+
+impl<Stream> Iterator for RawChars<Stream>
+where
+    Stream: JQStream,
+{
+    type Item = Result<char, JQErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(ch) = self.buf.pop_front() {
+                return Some(Ok(ch));
+            }
+
+            if self.finished {
+                return None;
+            }
+
+            let token = match self.stream.next()? {
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+                Ok(token) => token,
+            };
+
+            let top_level = self.stream.get_path().is_empty();
+            match token {
+                Token::String(str) if top_level => {
+                    if self.separator == Some('\0') && str.contains('\0') {
+                        self.finished = true;
+                        return Some(Err(JQErr::StreamOperationFailed(
+                            "Cannot dump a string containing NUL with --raw-output0 option".into(),
+                        )));
+                    }
+                    self.buf.extend(str.chars());
+                }
+                token => write_token(token, StringEscapes::default(), &mut self.buf),
+            }
+
+            if top_level {
+                self.buf.extend(self.separator);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CharStream, JQErr, SanitizedJQStream};
+
+    fn write<Chars>(chars: Chars) -> Result<String, JQErr>
+    where
+        Chars: Iterator<Item = Result<char, JQErr>>,
+    {
+        chars.collect()
+    }
+
+    #[test]
+    fn reads_each_line_as_a_string() {
+        assert_eq!(
+            "a\nb\n\nc".chars().into_raw_input().to_string().unwrap(),
+            "\"a\"\n\"b\"\n\"\"\n\"c\"\n"
+        );
+        assert_eq!(
+            write("a\n\"b\"\n".chars().into_raw_input().to_chars_raw()).unwrap(),
+            "a\n\"b\"\n"
+        );
+        assert_eq!("".chars().into_raw_input().to_string().unwrap(), "");
+    }
+
+    #[test]
+    fn reads_the_whole_input_as_one_string() {
+        assert_eq!(
+            write("a\nb\n".chars().into_raw_input_slurped().to_chars_raw()).unwrap(),
+            "a\nb\n\n"
+        );
+        assert_eq!(
+            "".chars().into_raw_input_slurped().to_string().unwrap(),
+            "\"\"\n"
+        );
+    }
+
+    #[test]
+    fn writes_top_level_strings_raw() {
+        let values = r#""a\tb" ["c"] 1"#;
+        assert_eq!(
+            write(values.chars().into_json_tokens().to_chars_raw()).unwrap(),
+            "a\tb\n[\"c\"]\n1\n"
+        );
+        assert_eq!(
+            write(values.chars().into_json_tokens().to_chars_raw_joined()).unwrap(),
+            "a\tb[\"c\"]1"
+        );
+        assert_eq!(
+            write(values.chars().into_json_tokens().to_chars_raw0()).unwrap(),
+            concat!("a\tb\0", "[\"c\"]\0", "1\0")
+        );
+    }
+
+    #[test]
+    fn rejects_nul_in_raw_output0_strings() {
+        assert!(matches!(
+            write(r#""a\u0000""#.chars().into_json_tokens().to_chars_raw0()),
+            Err(JQErr::StreamOperationFailed(msg))
+                if &*msg == "Cannot dump a string containing NUL with --raw-output0 option"
+        ));
+    }
+}
@@ -0,0 +1,179 @@
+use regex::{Regex, RegexBuilder};
+
+use crate::{value::Value, JQErr};
+
+/// A regular expression along with the jq flags it was given. Like jq, an
+/// invalid regex or flag string is only reported once there is an input
+/// to match against.
+pub(crate) struct Pattern {
+    regex: Result<Regex, String>,
+    skip_empty: bool,
+}
+
+impl Pattern {
+    pub(crate) fn new(regex: &str, flags: &str) -> Self {
+        let mut builder = RegexBuilder::new(regex);
+        let mut skip_empty = false;
+        for flag in flags.chars() {
+            match flag {
+                // Every match is always produced by `scan` and `splits`.
+                'g' => {}
+                'i' => {
+                    builder.case_insensitive(true);
+                }
+                'x' => {
+                    builder.ignore_whitespace(true);
+                }
+                's' => {
+                    builder.dot_matches_new_line(true);
+                }
+                'p' => {
+                    builder.dot_matches_new_line(true).multi_line(true);
+                }
+                'n' => skip_empty = true,
+                // Oniguruma's longest match has no equivalent, so the
+                // leftmost match is used instead.
+                'l' => {}
+                _ => {
+                    return Self {
+                        regex: Err(format!("{flags} is not a valid modifier string")),
+                        skip_empty,
+                    }
+                }
+            }
+        }
+
+        Self {
+            regex: builder
+                .build()
+                .map_err(|err| format!("{regex} is not a valid regex: {err}")),
+            skip_empty,
+        }
+    }
+
+    fn regex<'a>(&'a self, input: &'a Value) -> Result<(&'a Regex, &'a str), JQErr> {
+        let str = match input {
+            Value::String(str) => str,
+            other => {
+                return Err(JQErr::StreamOperationFailed(
+                    format!(
+                        "{} cannot be matched, as it is not a string",
+                        other.describe()
+                    )
+                    .into(),
+                ))
+            }
+        };
+
+        match &self.regex {
+            Ok(regex) => Ok((regex, str)),
+            Err(err) => Err(JQErr::StreamOperationFailed(err.clone().into())),
+        }
+    }
+}
+
+/// jq's `scan($re; $flags)`: every match, or the array of its capture
+/// groups if the regex has any.
+pub(crate) fn scan(input: &Value, pattern: &Pattern) -> Result<Vec<Value>, JQErr> {
+    let (regex, str) = pattern.regex(input)?;
+
+    Ok(regex
+        .captures_iter(str)
+        .filter(|captures| !pattern.skip_empty || !captures[0].is_empty())
+        .map(|captures| {
+            if captures.len() == 1 {
+                return Value::String(captures[0].into());
+            }
+
+            Value::Array(
+                captures
+                    .iter()
+                    .skip(1)
+                    .map(|group| match group {
+                        Some(group) => Value::String(group.as_str().into()),
+                        None => Value::Null,
+                    })
+                    .collect(),
+            )
+        })
+        .collect())
+}
+
+/// jq's `splits($re; $flags)`: the pieces of the input between matches.
+pub(crate) fn splits(input: &Value, pattern: &Pattern) -> Result<Vec<Value>, JQErr> {
+    let (regex, str) = pattern.regex(input)?;
+
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for found in regex.find_iter(str) {
+        if pattern.skip_empty && found.is_empty() {
+            continue;
+        }
+        pieces.push(Value::String(str[start..found.start()].into()));
+        start = found.end();
+    }
+    pieces.push(Value::String(str[start..].into()));
+    Ok(pieces)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_support::values, JQErr, SanitizedJQStream};
+
+    #[test]
+    fn splits_on_each_match() {
+        assert_eq!(
+            values(r#""a, b,c""#).splits(", *").to_string().unwrap(),
+            "\"a\"\n\"b\"\n\"c\"\n"
+        );
+        assert_eq!(
+            values(r#""aXbxc""#)
+                .splits_with_flags("x", "i")
+                .to_string()
+                .unwrap(),
+            "\"a\"\n\"b\"\n\"c\"\n"
+        );
+    }
+
+    #[test]
+    fn scans_for_matches_and_capture_groups() {
+        assert_eq!(
+            values(r#""abcAbc""#)
+                .json_scan_with_flags("b", "gi")
+                .to_string()
+                .unwrap(),
+            "\"b\"\n\"b\"\n"
+        );
+        assert_eq!(
+            values(r#""a1 b2 c""#)
+                .json_scan("([a-z])([0-9])?")
+                .to_string()
+                .unwrap(),
+            "[\"a\",\"1\"]\n[\"b\",\"2\"]\n[\"c\",null]\n"
+        );
+        assert_eq!(
+            values(r#""ab""#)
+                .json_scan_with_flags("x*", "n")
+                .to_string()
+                .unwrap(),
+            ""
+        );
+    }
+
+    #[test]
+    fn reports_bad_patterns_and_inputs() {
+        assert!(matches!(
+            values(r#""a""#).json_scan("(").to_string(),
+            Err(JQErr::StreamOperationFailed(msg)) if msg.starts_with("( is not a valid regex")
+        ));
+        assert!(matches!(
+            values(r#""a""#).splits_with_flags("a", "q").to_string(),
+            Err(JQErr::StreamOperationFailed(msg)) if &*msg == "q is not a valid modifier string"
+        ));
+        assert!(matches!(
+            values("1").splits("a").to_string(),
+            Err(JQErr::StreamOperationFailed(msg))
+                if &*msg == "number (1) cannot be matched, as it is not a string"
+        ));
+    }
+}
@@ -1,4 +1,6 @@
-use crate::{fuse::FuseOnErr, stream_context::StreamContext, JQStream, SanitizedJQStream, Scope};
+use crate::{
+    fuse::FuseOnErr, stream_context::StreamContext, JQErr, JQStream, SanitizedJQStream, Scope,
+};
 
 /// A struct that transforms any [`JQStream`] into a
 /// [`crate::SanitizedJQStream`]
@@ -22,6 +24,19 @@ where
     pub fn get_path(&self) -> &[Scope] {
         self.stream.get_path()
     }
+
+    /// Consumes the rest of the top-level value currently being read
+    /// without materializing it.
+    pub(crate) fn skip_value(&mut self) -> Result<(), JQErr> {
+        while !self.get_path().is_empty() {
+            match self.stream.next() {
+                None => return Err(JQErr::UnexpectedEOF),
+                Some(Err(err)) => return Err(err),
+                Some(Ok(_)) => {}
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<Stream> Iterator for Sanitized<Stream>
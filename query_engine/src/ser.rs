@@ -0,0 +1,676 @@
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::{JQErr, Token, ValueTokens};
+
+/// Serializes any type implementing [`serde::Serialize`] into a token
+/// stream, so jq filters can run on in-memory Rust data without printing
+/// it as JSON first.
+pub fn to_stream<T>(value: &T) -> Result<ValueTokens, JQErr>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::new();
+    value.serialize(&mut serializer)?;
+    Ok(serializer.into_stream())
+}
+
+/// A [`serde::Serializer`] which writes the tokens of every value given to
+/// it. Values are laid out the same way as `serde_json` lays them out, with
+/// enum variants other than unit variants written as `{"Variant": ...}`.
+pub struct Serializer {
+    tokens: Vec<Token>,
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Self { tokens: Vec::new() }
+    }
+
+    /// Converts the tokens written so far into a stream.
+    pub fn into_stream(self) -> ValueTokens {
+        ValueTokens::new(self.tokens)
+    }
+
+    fn number(&mut self, num: impl ToString) {
+        self.tokens.push(Token::Number(num.to_string().into()));
+    }
+
+    /// Starts an array or object, wrapping it in `{"Variant": ...}` if it
+    /// is the value of an enum variant.
+    fn start(&mut self, variant: Option<&'static str>, start: Token) -> Compound<'_> {
+        if let Some(variant) = variant {
+            self.tokens.extend([
+                Token::ObjectStart,
+                Token::String(variant.into()),
+                Token::Colon,
+            ]);
+        }
+        self.tokens.push(start);
+        Compound {
+            ser: self,
+            first: true,
+            variant: variant.is_some(),
+        }
+    }
+}
+
+impl Default for Serializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = JQErr;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), JQErr> {
+        self.tokens.push(if v { Token::True } else { Token::False });
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), JQErr> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), JQErr> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), JQErr> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), JQErr> {
+        self.number(v);
+        Ok(())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), JQErr> {
+        self.number(v);
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), JQErr> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), JQErr> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), JQErr> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), JQErr> {
+        self.number(v);
+        Ok(())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), JQErr> {
+        self.number(v);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), JQErr> {
+        // Widening to f64 would write 0.1 as 0.10000000149011612, so finite
+        // values keep the shortest text which reads back as the same f32.
+        if v.is_finite() {
+            self.number(v);
+            Ok(())
+        } else {
+            self.serialize_f64(v.into())
+        }
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), JQErr> {
+        self.tokens.push(Token::ParsedNumber(v));
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), JQErr> {
+        self.tokens.push(Token::String(v.to_string().into()));
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), JQErr> {
+        self.tokens.push(Token::String(v.into()));
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), JQErr> {
+        let mut seq = self.start(None, Token::ArrayStart);
+        for byte in v {
+            ser::SerializeSeq::serialize_element(&mut seq, byte)?;
+        }
+        ser::SerializeSeq::end(seq)
+    }
+
+    fn serialize_none(self) -> Result<(), JQErr> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<(), JQErr>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), JQErr> {
+        self.tokens.push(Token::Null);
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), JQErr> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), JQErr> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<(), JQErr>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), JQErr>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.tokens.extend([
+            Token::ObjectStart,
+            Token::String(variant.into()),
+            Token::Colon,
+        ]);
+        value.serialize(&mut *self)?;
+        self.tokens.push(Token::ObjectEnd);
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>, JQErr> {
+        Ok(self.start(None, Token::ArrayStart))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a>, JQErr> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a>, JQErr> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, JQErr> {
+        Ok(self.start(Some(variant), Token::ArrayStart))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a>, JQErr> {
+        Ok(self.start(None, Token::ObjectStart))
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Compound<'a>, JQErr> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, JQErr> {
+        Ok(self.start(Some(variant), Token::ObjectStart))
+    }
+}
+
+/// Writes the members of an array or object.
+pub struct Compound<'a> {
+    ser: &'a mut Serializer,
+    first: bool,
+    /// Whether the container is wrapped in `{"Variant": ...}`.
+    variant: bool,
+}
+
+impl Compound<'_> {
+    fn separator(&mut self) {
+        if !std::mem::take(&mut self.first) {
+            self.ser.tokens.push(Token::Comma);
+        }
+    }
+
+    fn end(self, end: Token) -> Result<(), JQErr> {
+        self.ser.tokens.push(end);
+        if self.variant {
+            self.ser.tokens.push(Token::ObjectEnd);
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = JQErr;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), JQErr>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.separator();
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), JQErr> {
+        Compound::end(self, Token::ArrayEnd)
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = JQErr;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), JQErr>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), JQErr> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = JQErr;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), JQErr>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), JQErr> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = JQErr;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), JQErr>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), JQErr> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = JQErr;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), JQErr>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.separator();
+        let key = key.serialize(MapKeySerializer)?;
+        self.ser
+            .tokens
+            .extend([Token::String(key.into()), Token::Colon]);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), JQErr>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), JQErr> {
+        Compound::end(self, Token::ObjectEnd)
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = JQErr;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), JQErr>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeMap::serialize_entry(self, key, value)
+    }
+
+    fn end(self) -> Result<(), JQErr> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = JQErr;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), JQErr>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeMap::serialize_entry(self, key, value)
+    }
+
+    fn end(self) -> Result<(), JQErr> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+/// Writes object keys. Like `serde_json`, keys which are integers,
+/// booleans, chars or unit variants are written as strings, and anything
+/// else is an error.
+struct MapKeySerializer;
+
+fn key_must_be_a_string() -> JQErr {
+    ser::Error::custom("map keys must be strings")
+}
+
+impl ser::Serializer for MapKeySerializer {
+    type Ok = String;
+    type Error = JQErr;
+    type SerializeSeq = Impossible<String, JQErr>;
+    type SerializeTuple = Impossible<String, JQErr>;
+    type SerializeTupleStruct = Impossible<String, JQErr>;
+    type SerializeTupleVariant = Impossible<String, JQErr>;
+    type SerializeMap = Impossible<String, JQErr>;
+    type SerializeStruct = Impossible<String, JQErr>;
+    type SerializeStructVariant = Impossible<String, JQErr>;
+
+    fn serialize_bool(self, v: bool) -> Result<String, JQErr> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String, JQErr> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String, JQErr> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String, JQErr> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String, JQErr> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<String, JQErr> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String, JQErr> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String, JQErr> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String, JQErr> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String, JQErr> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<String, JQErr> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String, JQErr> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String, JQErr> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, JQErr> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String, JQErr> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, JQErr> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_none(self) -> Result<String, JQErr> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<String, JQErr>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit(self) -> Result<String, JQErr> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, JQErr> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, JQErr> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<String, JQErr>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, JQErr>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, JQErr> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, JQErr> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, JQErr> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, JQErr> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, JQErr> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, JQErr> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, JQErr> {
+        Err(key_must_be_a_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{from_stream, to_stream, SanitizedJQStream};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Point(i32, i32),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        id: u64,
+        name: String,
+        tags: Vec<String>,
+        parent: Option<Box<Record>>,
+        shapes: Vec<Shape>,
+        counts: BTreeMap<i32, bool>,
+        unit: (),
+        pair: (char, i8),
+    }
+
+    fn record() -> Record {
+        Record {
+            id: u64::MAX,
+            name: "a \"quoted\"\nname".to_string(),
+            tags: vec!["x".to_string(), "é".to_string()],
+            parent: Some(Box::new(Record {
+                id: 1,
+                name: String::new(),
+                tags: Vec::new(),
+                parent: None,
+                shapes: Vec::new(),
+                counts: BTreeMap::new(),
+                unit: (),
+                pair: ('b', 0),
+            })),
+            shapes: vec![
+                Shape::Empty,
+                Shape::Circle(1.5),
+                Shape::Point(-1, 2),
+                Shape::Rect { w: 3, h: 4 },
+            ],
+            counts: BTreeMap::from([(-1, true), (2, false)]),
+            unit: (),
+            pair: ('a', -8),
+        }
+    }
+
+    #[test]
+    fn lays_values_out_like_serde_json() {
+        let record = Record {
+            name: "a b".into(),
+            ..record()
+        };
+        assert_eq!(
+            to_stream(&record).unwrap().to_string().unwrap(),
+            concat!(
+                "{\"id\":18446744073709551615,\"name\":\"a b\",",
+                "\"tags\":[\"x\",\"é\"],\"parent\":{\"id\":1,\"name\":\"\",\"tags\":[],",
+                "\"parent\":null,\"shapes\":[],\"counts\":{},\"unit\":null,\"pair\":[\"b\",0]},",
+                "\"shapes\":[\"Empty\",{\"Circle\":1.5},{\"Point\":[-1,2]},{\"Rect\":{\"w\":3,\"h\":4}}],",
+                "\"counts\":{\"-1\":true,\"2\":false},\"unit\":null,\"pair\":[\"a\",-8]}\n"
+            )
+        );
+    }
+
+    #[test]
+    fn round_trips_through_a_stream() {
+        let value = record();
+        assert_eq!(
+            from_stream::<Record, _>(to_stream(&value).unwrap()).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn round_trips_through_a_jq_filter() {
+        let shapes = record().shapes;
+        let stream = to_stream(&record()).unwrap().at_key("shapes");
+        assert_eq!(from_stream::<Vec<Shape>, _>(stream).unwrap(), shapes);
+    }
+}
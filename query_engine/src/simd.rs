@@ -0,0 +1,171 @@
+use simd_json::{ErrorType, Node, StaticNode};
+
+use crate::{JQErr, Token, ValueTokens};
+
+/// Parses a single JSON document held in memory with `simd-json`,
+/// producing its tokens. This trades the incremental parsing of
+/// [`CharStream::into_json_tokens`](crate::CharStream::into_json_tokens)
+/// for throughput: the whole buffer is parsed up front, and is used as
+/// scratch space while doing so.
+///
+/// Integers keep their exact value, but other numbers are read as f64s,
+/// so their original text (`1.0` vs `1`) isn't preserved.
+pub fn from_simd_json(buf: &mut [u8]) -> Result<ValueTokens, JQErr> {
+    let err = match simd_json::to_tape(buf) {
+        Err(err) => err,
+        Ok(tape) => {
+            let mut tokens = Vec::new();
+            let mut nodes = tape.0.into_iter();
+            while nodes.len() > 0 {
+                push_tokens(&mut nodes, &mut tokens)?;
+            }
+            return Ok(ValueTokens::new(tokens));
+        }
+    };
+
+    if is_truncated(buf, &err) {
+        Err(JQErr::UnexpectedEOF)
+    } else {
+        Err(JQErr::StreamOperationFailed(
+            format!("Invalid JSON: {err}").into(),
+        ))
+    }
+}
+
+/// Whether `simd-json` failed because `buf` ends before its document does.
+/// Only strings are rewritten in place while parsing, so the bytes the
+/// error points at are still there.
+fn is_truncated(buf: &[u8], err: &simd_json::Error) -> bool {
+    let rest = buf.get(err.index()..).unwrap_or_default();
+    let cut_short = |literal: &[u8]| {
+        !rest.is_empty() && rest.len() < literal.len() && literal.starts_with(rest)
+    };
+    match err.error() {
+        ErrorType::Eof => true,
+        // Errors about a character come from building the tape, which
+        // only fails this way when it runs out of input.
+        ErrorType::Syntax if err.character().is_some() => true,
+        // The rest come from the first pass, before anything is rewritten,
+        // and are either a string which never ends or a control character.
+        ErrorType::Syntax => ends_in_string(buf),
+        ErrorType::InvalidNumber => err.index() >= buf.len(),
+        ErrorType::ExpectedTrue => cut_short(b"true"),
+        ErrorType::ExpectedFalse => cut_short(b"false"),
+        ErrorType::ExpectedNull => cut_short(b"null"),
+        _ => false,
+    }
+}
+
+/// Whether `buf` ends inside a string.
+fn ends_in_string(buf: &[u8]) -> bool {
+    let mut in_string = false;
+    let mut bytes = buf.iter();
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'"' => in_string = !in_string,
+            b'\\' if in_string => {
+                bytes.next();
+            }
+            _ => {}
+        }
+    }
+    in_string
+}
+
+fn push_tokens<'a>(
+    nodes: &mut impl Iterator<Item = Node<'a>>,
+    out: &mut Vec<Token>,
+) -> Result<(), JQErr> {
+    match nodes.next().ok_or(JQErr::InvalidStream)? {
+        Node::String(str) => out.push(Token::String(str.into())),
+        Node::Static(StaticNode::Null) => out.push(Token::Null),
+        Node::Static(StaticNode::Bool(true)) => out.push(Token::True),
+        Node::Static(StaticNode::Bool(false)) => out.push(Token::False),
+        Node::Static(StaticNode::I64(num)) => out.push(Token::Number(num.to_string().into())),
+        Node::Static(StaticNode::U64(num)) => out.push(Token::Number(num.to_string().into())),
+        #[allow(clippy::useless_conversion)]
+        Node::Static(StaticNode::F64(num)) => out.push(Token::ParsedNumber(num.into())),
+        // `simd-json` only reads 128 bit integers when another crate
+        // enables its `128bit` feature.
+        #[allow(unreachable_patterns)]
+        Node::Static(num) => out.push(Token::Number(num.to_string().into())),
+        Node::Array { len, .. } => {
+            out.push(Token::ArrayStart);
+            for i in 0..len {
+                if i != 0 {
+                    out.push(Token::Comma);
+                }
+                push_tokens(nodes, out)?;
+            }
+            out.push(Token::ArrayEnd);
+        }
+        Node::Object { len, .. } => {
+            out.push(Token::ObjectStart);
+            for i in 0..len {
+                if i != 0 {
+                    out.push(Token::Comma);
+                }
+                match nodes.next() {
+                    Some(Node::String(key)) => {
+                        out.extend([Token::String(key.into()), Token::Colon])
+                    }
+                    _ => return Err(JQErr::InvalidStream),
+                }
+                push_tokens(nodes, out)?;
+            }
+            out.push(Token::ObjectEnd);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_simd_json, CharStream, JQErr, SanitizedJQStream};
+
+    #[test]
+    fn matches_the_incremental_parser() {
+        let json =
+            r#"{"a": [1, -2, 18446744073709551615, true, null], "b": {"c": "dé\n"}, "e": []}"#;
+        let mut buf = json.as_bytes().to_vec();
+        assert_eq!(
+            from_simd_json(&mut buf).unwrap().to_string().unwrap(),
+            json.chars().into_json_tokens().to_string().unwrap()
+        );
+    }
+
+    #[test]
+    fn reads_other_numbers_as_floats() {
+        let mut buf = b"[1.0, 2.5e1]".to_vec();
+        assert_eq!(
+            from_simd_json(&mut buf).unwrap().to_string().unwrap(),
+            "[1,25]\n"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_documents() {
+        assert!(matches!(
+            from_simd_json(&mut Vec::new()),
+            Err(JQErr::UnexpectedEOF)
+        ));
+        for json in ["[1, 2", "{\"a\":", "[\"a\\n", "[tru", "[1."] {
+            assert!(
+                matches!(
+                    from_simd_json(&mut json.as_bytes().to_vec()),
+                    Err(JQErr::UnexpectedEOF)
+                ),
+                "{json}"
+            );
+        }
+        for json in ["[1, }", "1 2", "\"\u{1}\"", "[tru]", "[1.]"] {
+            assert!(
+                matches!(
+                    from_simd_json(&mut json.as_bytes().to_vec()),
+                    Err(JQErr::StreamOperationFailed(msg)) if msg.starts_with("Invalid JSON: ")
+                ),
+                "{json}"
+            );
+        }
+    }
+}
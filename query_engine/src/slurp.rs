@@ -1,4 +1,4 @@
-use crate::{JQStream, JQErr, Sanitized, SanitizedJQStream, Token};
+use crate::{ExecutionBudget, JQErr, JQStream, Sanitized, SanitizedJQStream, Token};
 
 enum SlurpState {
     Start,
@@ -14,6 +14,8 @@ where
     state: SlurpState,
     comma_or_err_on_deck: Option<crate::Item>,
     value_on_deck: Option<Token>,
+    /// The size of the array so far, for the budget.
+    size: usize,
 }
 
 impl<Stream> Slurp<Stream>
@@ -26,6 +28,21 @@ where
             state: SlurpState::Start,
             comma_or_err_on_deck: None,
             value_on_deck: None,
+            size: 0,
+        }
+    }
+
+    /// Pulls the next token to slurp, spending the active budget on it.
+    /// The whole array is charged to the budget's maximum value size.
+    fn pull(&mut self) -> Option<crate::Item> {
+        let budget = ExecutionBudget::active();
+        if let Err(err) = budget.step() {
+            return Some(Err(err));
+        }
+
+        match self.stream.next()? {
+            Err(err) => Some(Err(err)),
+            Ok(token) => Some(budget.charge(&mut self.size, &token).map(|()| token)),
         }
     }
 }
@@ -46,12 +63,7 @@ where
                 self.state = SlurpState::Item;
                 Some(Ok(Token::ArrayStart))
             }
-            SlurpState::Item => match self
-                .value_on_deck
-                .take()
-                .map(|token| Ok(token))
-                .or_else(|| self.stream.next())
-            {
+            SlurpState::Item => match self.value_on_deck.take().map(Ok).or_else(|| self.pull()) {
                 Some(Err(err)) => {
                     self.state = SlurpState::Finished;
                     Some(Err(err))
@@ -66,7 +78,7 @@ where
                     | Token::ObjectEnd
                     | Token::ArrayEnd => {
                         if self.stream.get_path().is_empty() {
-                            match self.stream.next() {
+                            match self.pull() {
                                 None => {}
                                 Some(Err(err)) => {
                                     self.state = SlurpState::Finished;
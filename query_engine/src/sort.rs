@@ -0,0 +1,232 @@
+use std::collections::VecDeque;
+
+use crate::{
+    value::Value, ExecutionBudget, Filter, JQErr, JQStream, Sanitized, SanitizedJQStream, Token,
+};
+
+/// The builtins which are implemented on top of sorting an array by a key.
+pub(crate) enum SortMode {
+    /// `sort` and `sort_by(f)`.
+    Sort,
+    /// `group_by(f)`.
+    Group,
+    /// `unique` and `unique_by(f)`.
+    Unique,
+    /// `min` and `min_by(f)`.
+    Min,
+    /// `max` and `max_by(f)`.
+    Max,
+}
+
+/// A struct for handling the `sort`, `sort_by(f)`, `group_by(f)`,
+/// `unique`, `unique_by(f)`, `min`, `min_by(f)`, `max` and `max_by(f)`
+/// jq queries. Like jq, the key of each element is the array of all the
+/// values `f` produces for it and the sort is stable.
+pub struct SortBy<Stream, Key>
+where
+    Stream: JQStream,
+    Key: Filter,
+{
+    finished: bool,
+    stream: Sanitized<Stream>,
+    key: Key,
+    mode: SortMode,
+    buf: VecDeque<Token>,
+}
+
+impl<Stream, Key> SortBy<Stream, Key>
+where
+    Stream: JQStream,
+    Key: Filter,
+{
+    pub(crate) fn new(stream: Stream, key: Key, mode: SortMode) -> Self {
+        Self {
+            finished: false,
+            stream: stream.sanitize(),
+            key,
+            mode,
+            buf: VecDeque::new(),
+        }
+    }
+
+    /// Sorts `input`, spending `budget` on every token of the keys held in
+    /// memory while sorting. `size` is what `input` has been charged to
+    /// the budget's maximum value size, which its keys are added to.
+    fn apply(
+        &mut self,
+        budget: &ExecutionBudget,
+        input: Value,
+        mut size: usize,
+    ) -> Result<Value, JQErr> {
+        let items = match input {
+            Value::Array(items) => items,
+            other => {
+                return Err(JQErr::StreamOperationFailed(
+                    format!(
+                        "{} cannot be sorted, as it is not an array",
+                        other.describe()
+                    )
+                    .into(),
+                ))
+            }
+        };
+
+        let mut keyed = Vec::with_capacity(items.len());
+        for item in items {
+            let mut outputs = self.key.apply(item.clone().into_stream()).sanitize();
+            let mut key = Vec::new();
+            while let Some(output) = budget.next_value(&mut outputs, &mut size) {
+                key.push(output?);
+            }
+            keyed.push((Value::Array(key), item));
+        }
+
+        Ok(match self.mode {
+            SortMode::Sort => {
+                keyed.sort_by(|a, b| a.0.cmp(&b.0));
+                Value::Array(keyed.into_iter().map(|(_, item)| item).collect())
+            }
+            SortMode::Group => {
+                keyed.sort_by(|a, b| a.0.cmp(&b.0));
+                let mut groups: Vec<(Value, Vec<Value>)> = Vec::new();
+                for (key, item) in keyed {
+                    match groups.last_mut() {
+                        Some((last_key, group)) if *last_key == key => group.push(item),
+                        _ => groups.push((key, vec![item])),
+                    }
+                }
+                Value::Array(
+                    groups
+                        .into_iter()
+                        .map(|(_, group)| Value::Array(group))
+                        .collect(),
+                )
+            }
+            SortMode::Unique => {
+                keyed.sort_by(|a, b| a.0.cmp(&b.0));
+                keyed.dedup_by(|a, b| a.0 == b.0);
+                Value::Array(keyed.into_iter().map(|(_, item)| item).collect())
+            }
+            // The first of several equal minimums wins, while the last of
+            // several equal maximums wins.
+            SortMode::Min => keyed
+                .into_iter()
+                .reduce(|min, next| if next.0 < min.0 { next } else { min })
+                .map(|(_, item)| item)
+                .unwrap_or(Value::Null),
+            SortMode::Max => keyed
+                .into_iter()
+                .reduce(|max, next| if next.0 >= max.0 { next } else { max })
+                .map(|(_, item)| item)
+                .unwrap_or(Value::Null),
+        })
+    }
+}
+
+impl<Stream, Key> Iterator for SortBy<Stream, Key>
+where
+    Stream: JQStream,
+    Key: Filter,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.buf.pop_front() {
+            return Some(Ok(token));
+        }
+
+        if self.finished {
+            return None;
+        }
+
+        // The active budget is spent on every token of the arrays held in
+        // memory while sorting, as well as on their keys.
+        let budget = ExecutionBudget::active();
+        let mut size = 0;
+        let result = budget
+            .next_value(&mut self.stream, &mut size)?
+            .and_then(|input| self.apply(&budget, input, size));
+        match result {
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+            Ok(value) => {
+                value.push_tokens(&mut self.buf);
+                self.buf.pop_front().map(Ok)
+            }
+        }
+    }
+}
+
+impl<Stream, Key> SanitizedJQStream for SortBy<Stream, Key>
+where
+    Stream: JQStream,
+    Key: Filter,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_support::values, SanitizedJQStream, ValueTokens};
+
+    #[test]
+    fn sorts_in_jq_order() {
+        assert_eq!(
+            values(r#"[3, "a", null, [1], true, {"a": 1}, false, 1]"#)
+                .sort()
+                .to_string()
+                .unwrap(),
+            "[null,false,true,1,3,\"a\",[1],{\"a\":1}]\n"
+        );
+        assert_eq!(
+            values(r#"[{"a": 2, "b": 1}, {"a": 1, "b": 2}]"#)
+                .sort_by(|value: ValueTokens| value.at_key("a"))
+                .to_string()
+                .unwrap(),
+            "[{\"a\":1,\"b\":2},{\"a\":2,\"b\":1}]\n"
+        );
+    }
+
+    #[test]
+    fn groups_and_deduplicates() {
+        assert_eq!(
+            values(r#"[{"a": 1, "b": 1}, {"a": 2}, {"a": 1, "b": 2}]"#)
+                .group_by(|value: ValueTokens| value.at_key("a"))
+                .to_string()
+                .unwrap(),
+            "[[{\"a\":1,\"b\":1},{\"a\":1,\"b\":2}],[{\"a\":2}]]\n"
+        );
+        assert_eq!(
+            values("[1, 2, 1, 3, 2]").unique().to_string().unwrap(),
+            "[1,2,3]\n"
+        );
+        assert_eq!(
+            values(r#"[{"a": 1, "b": 1}, {"a": 0}, {"a": 1, "b": 2}]"#)
+                .unique_by(|value: ValueTokens| value.at_key("a"))
+                .to_string()
+                .unwrap(),
+            "[{\"a\":0},{\"a\":1,\"b\":1}]\n"
+        );
+    }
+
+    #[test]
+    fn finds_the_smallest_and_largest_values() {
+        assert_eq!(
+            values("[5, 4, 2, 7]").json_min().to_string().unwrap(),
+            "2\n"
+        );
+        assert_eq!(
+            values("[5, 4, 2, 7]").json_max().to_string().unwrap(),
+            "7\n"
+        );
+        assert_eq!(values("[]").json_min().to_string().unwrap(), "null\n");
+        assert_eq!(
+            values(r#"[{"a": 2}, {"a": 9}, {"a": 1}]"#)
+                .json_max_by(|value: ValueTokens| value.at_key("a"))
+                .to_string()
+                .unwrap(),
+            "{\"a\":9}\n"
+        );
+    }
+}
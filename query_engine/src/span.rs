@@ -3,5 +3,6 @@ use crate::location::Location;
 #[derive(Clone, Debug, Default)]
 pub(crate) struct Span {
     pub start: Location,
+    #[allow(dead_code)]
     pub end: Location,
 }
@@ -0,0 +1,330 @@
+use std::collections::VecDeque;
+
+use crate::{
+    budget::Spending,
+    filter::collect_outputs,
+    strings,
+    value::{ObjectEntries, Value},
+    Filter, JQErr, JQStream, Sanitized, SanitizedJQStream, Token,
+};
+
+/// The SQL-style builtins which build an object keyed by an expression.
+pub(crate) enum IndexMode {
+    /// `INDEX(idx_expr)` and `INDEX(stream; idx_expr)`: the last row with
+    /// each key wins.
+    Index,
+    /// `GROUP_BY(f)`: every row with each key, in order.
+    GroupBy,
+    /// `UNIQUE_BY(f)`: the first row with each key.
+    UniqueBy,
+}
+
+/// A struct for handling the `INDEX(idx_expr)`, `INDEX(stream; idx_expr)`,
+/// `GROUP_BY(f)` and `UNIQUE_BY(f)` jq queries. Rows are keyed by
+/// `idx_expr | tostring` and are pulled one at a time from the input (or
+/// from `stream`), so only the resulting object is held in memory. The
+/// active budget is spent on every token of the rows and their keys.
+pub struct Index<Stream, Rows, Key>
+where
+    Stream: JQStream,
+    Rows: Filter,
+    Key: Filter,
+{
+    finished: bool,
+    stream: Sanitized<Spending<Stream>>,
+    rows: Option<Rows>,
+    key: Key,
+    mode: IndexMode,
+    buf: VecDeque<Token>,
+}
+
+impl<Stream, Rows, Key> Index<Stream, Rows, Key>
+where
+    Stream: JQStream,
+    Rows: Filter,
+    Key: Filter,
+{
+    pub(crate) fn new(stream: Stream, rows: Option<Rows>, key: Key, mode: IndexMode) -> Self {
+        Self {
+            finished: false,
+            stream: Spending::new(stream).sanitize(),
+            rows,
+            key,
+            mode,
+            buf: VecDeque::new(),
+        }
+    }
+
+    fn insert(
+        key: &mut Key,
+        mode: &IndexMode,
+        entries: &mut ObjectEntries,
+        row: Value,
+    ) -> Result<(), JQErr> {
+        for key in collect_outputs(key, &row)? {
+            let key = match strings::tostring(key)? {
+                Value::String(key) => key,
+                _ => unreachable!(),
+            };
+
+            match mode {
+                IndexMode::Index => entries.insert(key, row.clone()),
+                IndexMode::GroupBy => match entries.get_mut(&key) {
+                    Some(Value::Array(group)) => group.push(row.clone()),
+                    _ => entries.insert(key, Value::Array(vec![row.clone()])),
+                },
+                IndexMode::UniqueBy => {
+                    if entries.get_mut(&key).is_none() {
+                        entries.insert(key, row.clone());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn index_next_input(&mut self) -> Option<Result<Value, JQErr>> {
+        let first = match self.stream.next()? {
+            Err(err) => return Some(Err(err)),
+            Ok(token) => token,
+        };
+
+        let mut entries = ObjectEntries::default();
+        match &mut self.rows {
+            None => {
+                let in_object = match first {
+                    Token::ArrayStart => false,
+                    Token::ObjectStart => true,
+                    other => {
+                        return Some(Value::parse(other, &mut self.stream).and_then(|value| {
+                            Err(JQErr::StreamOperationFailed(
+                                format!("Cannot iterate over {}", value.describe()).into(),
+                            ))
+                        }))
+                    }
+                };
+
+                while let Some(row) = Value::next_member(&mut self.stream, in_object) {
+                    let inserted = row
+                        .and_then(|row| Self::insert(&mut self.key, &self.mode, &mut entries, row));
+                    if let Err(err) = inserted {
+                        return Some(Err(err));
+                    }
+                }
+            }
+            Some(rows) => {
+                let input = match Value::parse(first, &mut self.stream) {
+                    Err(err) => return Some(Err(err)),
+                    Ok(input) => input,
+                };
+
+                let mut rows = Spending::new(rows.apply(input.into_stream())).sanitize();
+                while let Some(row) = Value::next_from(&mut rows) {
+                    let inserted = row
+                        .and_then(|row| Self::insert(&mut self.key, &self.mode, &mut entries, row));
+                    if let Err(err) = inserted {
+                        return Some(Err(err));
+                    }
+                }
+            }
+        }
+
+        Some(Ok(entries.into_value()))
+    }
+}
+
+impl<Stream, Rows, Key> Iterator for Index<Stream, Rows, Key>
+where
+    Stream: JQStream,
+    Rows: Filter,
+    Key: Filter,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.buf.pop_front() {
+            return Some(Ok(token));
+        }
+
+        if self.finished {
+            return None;
+        }
+
+        match self.index_next_input()? {
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+            Ok(index) => {
+                index.push_tokens(&mut self.buf);
+                self.buf.pop_front().map(Ok)
+            }
+        }
+    }
+}
+
+impl<Stream, Rows, Key> SanitizedJQStream for Index<Stream, Rows, Key>
+where
+    Stream: JQStream,
+    Rows: Filter,
+    Key: Filter,
+{
+}
+
+/// A struct for handling the `IN(s)` and `IN(source; s)` jq queries, which
+/// check whether any value of `source` (by default the input itself) is
+/// equal to any value of `s`. Evaluation stops at the first match.
+pub struct In<Stream, Source, Set>
+where
+    Stream: JQStream,
+    Source: Filter,
+    Set: Filter,
+{
+    finished: bool,
+    stream: Sanitized<Stream>,
+    source: Option<Source>,
+    set: Set,
+}
+
+impl<Stream, Source, Set> In<Stream, Source, Set>
+where
+    Stream: JQStream,
+    Source: Filter,
+    Set: Filter,
+{
+    pub(crate) fn new(stream: Stream, source: Option<Source>, set: Set) -> Self {
+        Self {
+            finished: false,
+            stream: stream.sanitize(),
+            source,
+            set,
+        }
+    }
+
+    fn check_next_input(&mut self) -> Option<Result<bool, JQErr>> {
+        let input = match Value::next_from(&mut self.stream)? {
+            Err(err) => return Some(Err(err)),
+            Ok(input) => input,
+        };
+
+        let source = match &mut self.source {
+            None => {
+                let mut set = self.set.apply(input.clone().into_stream()).sanitize();
+                while let Some(value) = Value::next_from(&mut set) {
+                    match value {
+                        Err(err) => return Some(Err(err)),
+                        Ok(value) if value == input => return Some(Ok(true)),
+                        Ok(_) => {}
+                    }
+                }
+                return Some(Ok(false));
+            }
+            Some(source) => source,
+        };
+
+        let set = match collect_outputs(&mut self.set, &input) {
+            Err(err) => return Some(Err(err)),
+            Ok(set) => set,
+        };
+
+        let mut values = source.apply(input.into_stream()).sanitize();
+        while let Some(value) = Value::next_from(&mut values) {
+            match value {
+                Err(err) => return Some(Err(err)),
+                Ok(value) if set.contains(&value) => return Some(Ok(true)),
+                Ok(_) => {}
+            }
+        }
+        Some(Ok(false))
+    }
+}
+
+impl<Stream, Source, Set> Iterator for In<Stream, Source, Set>
+where
+    Stream: JQStream,
+    Source: Filter,
+    Set: Filter,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        match self.check_next_input()? {
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+            Ok(true) => Some(Ok(Token::True)),
+            Ok(false) => Some(Ok(Token::False)),
+        }
+    }
+}
+
+impl<Stream, Source, Set> SanitizedJQStream for In<Stream, Source, Set>
+where
+    Stream: JQStream,
+    Source: Filter,
+    Set: Filter,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_support::values, SanitizedJQStream, ValueTokens};
+
+    fn id(row: ValueTokens) -> impl Iterator<Item = crate::Item> {
+        row.at_key("id")
+    }
+
+    const ROWS: &str = r#"[{"id": 1, "n": "a"}, {"id": "1", "n": "b"}, {"id": 2, "n": "c"}]"#;
+
+    #[test]
+    fn indexes_rows_by_a_key() {
+        assert_eq!(
+            values(ROWS).sql_index(id).to_string().unwrap(),
+            "{\"1\":{\"id\":\"1\",\"n\":\"b\"},\"2\":{\"id\":2,\"n\":\"c\"}}\n"
+        );
+        assert_eq!(
+            values(r#"{"rows": [{"id": 3}]}"#)
+                .sql_index_of(|input: ValueTokens| input.at_key("rows").values(), id)
+                .to_string()
+                .unwrap(),
+            "{\"3\":{\"id\":3}}\n"
+        );
+        assert!(values("1").sql_index(id).to_string().is_err());
+    }
+
+    #[test]
+    fn groups_and_deduplicates_rows_by_a_key() {
+        assert_eq!(
+            values(ROWS).sql_group_by(id).to_string().unwrap(),
+            concat!(
+                "{\"1\":[{\"id\":1,\"n\":\"a\"},{\"id\":\"1\",\"n\":\"b\"}],",
+                "\"2\":[{\"id\":2,\"n\":\"c\"}]}\n"
+            )
+        );
+        assert_eq!(
+            values(ROWS).sql_unique_by(id).to_string().unwrap(),
+            "{\"1\":{\"id\":1,\"n\":\"a\"},\"2\":{\"id\":2,\"n\":\"c\"}}\n"
+        );
+    }
+
+    #[test]
+    fn checks_membership() {
+        let set = |_: ValueTokens| values("1 \"a\" [2]");
+        assert_eq!(
+            values("1 [2] \"b\"").sql_in(set).to_string().unwrap(),
+            "true\ntrue\nfalse\n"
+        );
+        assert_eq!(
+            values(r#"["x", "a"] ["x"]"#)
+                .sql_in_of(|input: ValueTokens| input.values(), set)
+                .to_string()
+                .unwrap(),
+            "true\nfalse\n"
+        );
+    }
+}
@@ -34,44 +34,45 @@ where
     }
 }
 
-impl<Stream> Iterator for StreamContext<Stream>
+impl<Stream> StreamContext<Stream>
 where
     Stream: JQStream,
 {
-    type Item = crate::Item;
-
-    fn next(&mut self) -> Option<Self::Item> {
+    fn next_unfused(&mut self) -> Option<crate::Item> {
         match self.state {
-            JsonParsingState::Finished => return None,
+            JsonParsingState::Finished => None,
             JsonParsingState::Value | JsonParsingState::FirstArrayValue => match self.stream.next()
             {
                 None => {
                     self.state = JsonParsingState::Finished;
                     if self.scopes.is_empty() {
-                        return None;
+                        None
                     } else {
-                        return Some(Err(JQErr::UnexpectedEOF));
+                        Some(Err(JQErr::UnexpectedEOF))
                     }
                 }
-                Some(Err(err)) => return Some(Err(err)),
+                Some(Err(err)) => Some(Err(err)),
                 Some(Ok(token_kind)) => match token_kind {
                     Token::ObjectStart => {
                         self.scopes.push(Scope::Object);
                         self.state = JsonParsingState::FirstObjectKey;
-                        return Some(Ok(Token::ObjectStart));
+                        Some(Ok(Token::ObjectStart))
                     }
                     Token::ArrayStart => {
                         self.scopes.push(Scope::Array(0));
                         self.state = JsonParsingState::FirstArrayValue;
-                        return Some(Ok(Token::ArrayStart));
+                        Some(Ok(Token::ArrayStart))
                     }
                     Token::ArrayEnd => {
                         if matches!(self.state, JsonParsingState::FirstArrayValue) {
-                            self.state = JsonParsingState::Value;
                             assert!(matches!(self.scopes.pop(), Some(Scope::Array(_))));
-                            return Some(Ok(Token::ArrayEnd));
+                            self.state = match self.scopes.last() {
+                                None => JsonParsingState::Value,
+                                Some(_) => JsonParsingState::AfterValue,
+                            };
+                            Some(Ok(Token::ArrayEnd))
                         } else {
-                            return Some(Err(JQErr::InvalidStream));
+                            Some(Err(JQErr::InvalidStream))
                         }
                     }
                     Token::String(_)
@@ -81,10 +82,10 @@ where
                     | Token::False
                     | Token::Null => {
                         self.state = JsonParsingState::AfterValue;
-                        return Some(Ok(token_kind));
+                        Some(Ok(token_kind))
                     }
                     Token::ObjectEnd | Token::Colon | Token::Comma => {
-                        return Some(Err(JQErr::InvalidStream))
+                        Some(Err(JQErr::InvalidStream))
                     }
                 },
             },
@@ -93,12 +94,12 @@ where
                     None => {
                         self.state = JsonParsingState::Finished;
                         if self.scopes.is_empty() {
-                            return None;
+                            None
                         } else {
-                            return Some(Err(JQErr::UnexpectedEOF));
+                            Some(Err(JQErr::UnexpectedEOF))
                         }
                     }
-                    Some(Err(err)) => return Some(Err(err)),
+                    Some(Err(err)) => Some(Err(err)),
                     Some(Ok(token_kind)) => match token_kind {
                         Token::ObjectEnd => {
                             if matches!(self.state, JsonParsingState::FirstObjectKey) {
@@ -111,9 +112,9 @@ where
                                     Some(_) => JsonParsingState::AfterValue,
                                 };
 
-                                return Some(Ok(Token::ObjectEnd));
+                                Some(Ok(Token::ObjectEnd))
                             } else {
-                                return Some(Err(JQErr::InvalidStream));
+                                Some(Err(JQErr::InvalidStream))
                             }
                         }
                         Token::String(key) => {
@@ -128,7 +129,7 @@ where
                             });
 
                             self.state = JsonParsingState::ObjectColon;
-                            return Some(Ok(Token::String(key)));
+                            Some(Ok(Token::String(key)))
                         }
                         Token::ObjectStart
                         | Token::ArrayStart
@@ -139,7 +140,7 @@ where
                         | Token::ParsedNumber(_)
                         | Token::True
                         | Token::False
-                        | Token::Null => return Some(Err(JQErr::InvalidStream)),
+                        | Token::Null => Some(Err(JQErr::InvalidStream)),
                     },
                 }
             }
@@ -147,16 +148,16 @@ where
                 None => {
                     self.state = JsonParsingState::Finished;
                     if self.scopes.is_empty() {
-                        return None;
+                        None
                     } else {
-                        return Some(Err(JQErr::UnexpectedEOF));
+                        Some(Err(JQErr::UnexpectedEOF))
                     }
                 }
-                Some(Err(err)) => return Some(Err(err)),
+                Some(Err(err)) => Some(Err(err)),
                 Some(Ok(token_kind)) => match token_kind {
                     Token::Colon => {
                         self.state = JsonParsingState::Value;
-                        return Some(Ok(Token::Colon));
+                        Some(Ok(Token::Colon))
                     }
                     Token::ObjectStart
                     | Token::ObjectEnd
@@ -168,35 +169,34 @@ where
                     | Token::ParsedNumber(_)
                     | Token::True
                     | Token::False
-                    | Token::Null => return Some(Err(JQErr::InvalidStream)),
+                    | Token::Null => Some(Err(JQErr::InvalidStream)),
                 },
             },
             JsonParsingState::AfterValue => match self.stream.next() {
                 None => {
+                    self.state = JsonParsingState::Finished;
                     if self.scopes.is_empty() {
-                        return None;
+                        None
                     } else {
-                        return Some(Err(JQErr::UnexpectedEOF));
+                        Some(Err(JQErr::UnexpectedEOF))
                     }
                 }
-                Some(Err(err)) => return Some(Err(err)),
+                Some(Err(err)) => Some(Err(err)),
                 Some(Ok(token_kind)) => match token_kind {
                     Token::Comma => match self.scopes.pop() {
-                        None => {
-                            return Some(Err(JQErr::InvalidStream));
-                        }
+                        None => Some(Err(JQErr::InvalidStream)),
                         Some(Scope::Array(index)) => {
                             self.scopes.push(Scope::Array(index + 1));
                             self.state = JsonParsingState::Value;
-                            return Some(Ok(Token::Comma));
+                            Some(Ok(Token::Comma))
                         }
                         Some(Scope::ObjectAtKey { index, .. }) => {
                             self.index_in_current_object = index;
                             self.scopes.push(Scope::Object);
                             self.state = JsonParsingState::ObjectKey;
-                            return Some(Ok(Token::Comma));
+                            Some(Ok(Token::Comma))
                         }
-                        Some(Scope::Object) => return Some(Err(JQErr::InvalidStream)),
+                        Some(Scope::Object) => Some(Err(JQErr::InvalidStream)),
                     },
                     Token::ObjectEnd => {
                         if matches!(
@@ -208,9 +208,9 @@ where
                                 Some(_) => JsonParsingState::AfterValue,
                             };
 
-                            return Some(Ok(Token::ObjectEnd));
+                            Some(Ok(Token::ObjectEnd))
                         } else {
-                            return Some(Err(JQErr::InvalidStream));
+                            Some(Err(JQErr::InvalidStream))
                         }
                     }
                     Token::ArrayEnd => {
@@ -220,20 +220,20 @@ where
                                 Some(_) => JsonParsingState::AfterValue,
                             };
 
-                            return Some(Ok(Token::ArrayEnd));
+                            Some(Ok(Token::ArrayEnd))
                         } else {
-                            return Some(Err(JQErr::InvalidStream));
+                            Some(Err(JQErr::InvalidStream))
                         }
                     }
                     Token::ObjectStart => {
                         self.scopes.push(Scope::Object);
                         self.state = JsonParsingState::FirstObjectKey;
-                        return Some(Ok(Token::ObjectStart));
+                        Some(Ok(Token::ObjectStart))
                     }
                     Token::ArrayStart => {
                         self.scopes.push(Scope::Array(0));
                         self.state = JsonParsingState::FirstArrayValue;
-                        return Some(Ok(Token::ArrayStart));
+                        Some(Ok(Token::ArrayStart))
                     }
                     Token::String(_)
                     | Token::Number(_)
@@ -242,11 +242,59 @@ where
                     | Token::False
                     | Token::Null => {
                         self.state = JsonParsingState::AfterValue;
-                        return Some(Ok(token_kind));
+                        Some(Ok(token_kind))
                     }
-                    Token::Colon => return Some(Err(JQErr::InvalidStream)),
+                    Token::Colon => Some(Err(JQErr::InvalidStream)),
                 },
             },
         }
     }
 }
+
+impl<Stream> Iterator for StreamContext<Stream>
+where
+    Stream: JQStream,
+{
+    type Item = crate::Item;
+
+    /// Finishes after the first error, whether it came from the stream or
+    /// from the stream not being valid JSON.
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.next_unfused();
+        if matches!(item, Some(Err(_))) {
+            self.state = JsonParsingState::Finished;
+        }
+        item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CharStream, JQErr, JQStream, ParseLimits, Token};
+
+    #[test]
+    fn finishes_after_an_error_between_values() {
+        let tokens = "[1,2,3]"
+            .chars()
+            .into_json_tokens()
+            .with_limits(ParseLimits::default().with_max_tokens(4))
+            .sanitize()
+            .take(10)
+            .collect::<Vec<_>>();
+
+        assert_eq!(tokens.len(), 5);
+        assert!(matches!(tokens[3], Ok(Token::Number(ref num)) if &**num == "2"));
+        assert!(matches!(tokens[4], Err(JQErr::TokenLimitExceeded(_))));
+    }
+
+    #[test]
+    fn finishes_after_the_stream_is_not_valid_json() {
+        let mut tokens = [Ok(Token::ArrayStart), Ok(Token::Colon), Ok(Token::Null)]
+            .into_iter()
+            .sanitize();
+
+        assert!(matches!(tokens.next(), Some(Ok(Token::ArrayStart))));
+        assert!(matches!(tokens.next(), Some(Err(JQErr::InvalidStream))));
+        assert!(tokens.next().is_none());
+    }
+}
@@ -0,0 +1,304 @@
+use std::collections::VecDeque;
+
+use crate::{
+    paths::setpath, value::Value, JQErr, JQStream, Sanitized, SanitizedJQStream, Scope, Token,
+};
+
+/// A struct for handling the `tostream` jq query, which is the same as
+/// running jq with `--stream`. Every leaf becomes a `[path, leaf]` event
+/// and every non-empty container is closed by a `[path]` event holding the
+/// path of its last child.
+///
+/// Only the current path is ever held in memory, as it comes straight from
+/// the scope stack that [`Sanitized`] already tracks, so this works on
+/// documents which are much larger than memory.
+pub struct ToStream<Stream>
+where
+    Stream: JQStream,
+{
+    finished: bool,
+    stream: Sanitized<Stream>,
+    /// The previous token, used to tell object keys from string values.
+    prev: Option<Token>,
+    /// Whether the container which was just opened might be empty.
+    opened: bool,
+    /// The path of the last event, which is what a closing event reports.
+    last_path: Vec<Value>,
+    buf: VecDeque<Token>,
+}
+
+impl<Stream> ToStream<Stream>
+where
+    Stream: JQStream,
+{
+    pub(crate) fn new(stream: Stream) -> Self {
+        Self {
+            finished: false,
+            stream: stream.sanitize(),
+            prev: None,
+            opened: false,
+            last_path: Vec::new(),
+            buf: VecDeque::new(),
+        }
+    }
+
+    fn current_path(&self) -> Vec<Value> {
+        self.stream
+            .get_path()
+            .iter()
+            .filter_map(|scope| match scope {
+                Scope::Array(index) => Some(Value::ParsedNumber(*index as f64)),
+                Scope::ObjectAtKey { key, .. } => Some(Value::String(key.clone())),
+                Scope::Object => None,
+            })
+            .collect()
+    }
+
+    fn leaf(&mut self, leaf: Value) -> Value {
+        self.last_path = self.current_path();
+        Value::Array(vec![Value::Array(self.last_path.clone()), leaf])
+    }
+
+    /// Turns the next token into an event, if it completes one.
+    fn event(&mut self, token: Token) -> Option<Value> {
+        let prev = self.prev.replace(token.clone());
+        let opened = std::mem::take(&mut self.opened);
+
+        match token {
+            Token::ObjectStart | Token::ArrayStart => {
+                self.opened = true;
+                None
+            }
+            Token::ObjectEnd if opened => Some(self.leaf(Value::Object(Vec::new()))),
+            Token::ArrayEnd if opened => Some(self.leaf(Value::Array(Vec::new()))),
+            Token::ObjectEnd | Token::ArrayEnd => {
+                let event = Value::Array(vec![Value::Array(self.last_path.clone())]);
+                self.last_path.pop();
+                Some(event)
+            }
+            Token::Colon | Token::Comma => None,
+            Token::String(_)
+                if matches!(prev, Some(Token::ObjectStart | Token::Comma))
+                    && matches!(
+                        self.stream.get_path().last(),
+                        Some(Scope::ObjectAtKey { .. })
+                    ) =>
+            {
+                None
+            }
+            Token::String(str) => Some(self.leaf(Value::String(str))),
+            Token::Number(num) => Some(self.leaf(Value::Number(num))),
+            Token::ParsedNumber(num) => Some(self.leaf(Value::ParsedNumber(num))),
+            Token::True => Some(self.leaf(Value::True)),
+            Token::False => Some(self.leaf(Value::False)),
+            Token::Null => Some(self.leaf(Value::Null)),
+        }
+    }
+}
+
+impl<Stream> Iterator for ToStream<Stream>
+where
+    Stream: JQStream,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.buf.pop_front() {
+            return Some(Ok(token));
+        }
+
+        if self.finished {
+            return None;
+        }
+
+        loop {
+            match self.stream.next()? {
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+                Ok(token) => {
+                    if let Some(event) = self.event(token) {
+                        event.push_tokens(&mut self.buf);
+                        return self.buf.pop_front().map(Ok);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<Stream> SanitizedJQStream for ToStream<Stream> where Stream: JQStream {}
+
+fn invalid_event(event: &Value) -> JQErr {
+    JQErr::StreamOperationFailed(format!("Invalid stream event {}", event.describe()).into())
+}
+
+/// A struct for handling the `fromstream(f)` jq query, where `f` is the
+/// stream of events being reassembled. Each value is produced as soon as
+/// its last event has been read, so only one value is held at a time.
+pub struct FromStream<Stream>
+where
+    Stream: JQStream,
+{
+    finished: bool,
+    stream: Sanitized<Stream>,
+    value: Value,
+    buf: VecDeque<Token>,
+}
+
+impl<Stream> FromStream<Stream>
+where
+    Stream: JQStream,
+{
+    pub(crate) fn new(stream: Stream) -> Self {
+        Self {
+            finished: false,
+            stream: stream.sanitize(),
+            value: Value::Null,
+            buf: VecDeque::new(),
+        }
+    }
+
+    /// Applies the next event, returning the value it completes, if any.
+    fn apply(&mut self, event: Value) -> Result<Option<Value>, JQErr> {
+        let mut parts = match &event {
+            Value::Array(parts) if parts.len() == 1 || parts.len() == 2 => parts.clone(),
+            _ => return Err(invalid_event(&event)),
+        };
+
+        let leaf = if parts.len() == 2 { parts.pop() } else { None };
+        let path = match parts.pop() {
+            Some(Value::Array(path)) => path,
+            _ => return Err(invalid_event(&event)),
+        };
+
+        let complete = match leaf {
+            Some(leaf) => {
+                let value = std::mem::replace(&mut self.value, Value::Null);
+                self.value = setpath(value, &path, leaf)?;
+                path.is_empty()
+            }
+            None => path.len() == 1,
+        };
+
+        if complete {
+            Ok(Some(std::mem::replace(&mut self.value, Value::Null)))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<Stream> Iterator for FromStream<Stream>
+where
+    Stream: JQStream,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.buf.pop_front() {
+            return Some(Ok(token));
+        }
+
+        if self.finished {
+            return None;
+        }
+
+        loop {
+            let result = Value::next_from(&mut self.stream)?.and_then(|event| self.apply(event));
+            match result {
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+                Ok(None) => {}
+                Ok(Some(value)) => {
+                    value.push_tokens(&mut self.buf);
+                    return self.buf.pop_front().map(Ok);
+                }
+            }
+        }
+    }
+}
+
+impl<Stream> SanitizedJQStream for FromStream<Stream> where Stream: JQStream {}
+
+/// jq's `depth | truncate_stream(event)`: events at or above `depth` are
+/// dropped and the rest have the first `depth` components of their path
+/// removed.
+pub(crate) fn truncate_stream(event: Value, depth: usize) -> Result<Option<Value>, JQErr> {
+    let mut parts = match event {
+        Value::Array(parts) if !parts.is_empty() => parts,
+        other => return Err(invalid_event(&other)),
+    };
+
+    match &mut parts[0] {
+        Value::Array(path) if path.len() > depth => {
+            path.drain(..depth);
+        }
+        Value::Array(_) => return Ok(None),
+        _ => return Err(invalid_event(&Value::Array(parts))),
+    }
+
+    Ok(Some(Value::Array(parts)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_support::values, SanitizedJQStream};
+
+    const DOCUMENT: &str = r#"{"a": [1, {"b": 2}], "c": [], "d": {}}"#;
+
+    #[test]
+    fn produces_the_events_of_jq_stream() {
+        assert_eq!(
+            values(DOCUMENT).to_stream_events().to_string().unwrap(),
+            concat!(
+                "[[\"a\",0],1]\n",
+                "[[\"a\",1,\"b\"],2]\n",
+                "[[\"a\",1,\"b\"]]\n",
+                "[[\"a\",1]]\n",
+                "[[\"c\"],[]]\n",
+                "[[\"d\"],{}]\n",
+                "[[\"d\"]]\n",
+            )
+        );
+        assert_eq!(
+            values("3 []").to_stream_events().to_string().unwrap(),
+            "[[],3]\n[[],[]]\n"
+        );
+    }
+
+    #[test]
+    fn rebuilds_values_from_their_events() {
+        assert_eq!(
+            values(DOCUMENT)
+                .to_stream_events()
+                .fromstream()
+                .to_string()
+                .unwrap(),
+            "{\"a\":[1,{\"b\":2}],\"c\":[],\"d\":{}}\n"
+        );
+        assert_eq!(
+            values("1 [2, [3]] \"a\"")
+                .to_stream_events()
+                .fromstream()
+                .to_string()
+                .unwrap(),
+            "1\n[2,[3]]\n\"a\"\n"
+        );
+    }
+
+    #[test]
+    fn truncates_the_paths_of_events() {
+        assert_eq!(
+            values(r#"{"a": [1, {"b": 2}]}"#)
+                .to_stream_events()
+                .truncate_stream(1)
+                .to_string()
+                .unwrap(),
+            "[[0],1]\n[[1,\"b\"],2]\n[[1,\"b\"]]\n[[1]]\n"
+        );
+    }
+}
@@ -0,0 +1,387 @@
+use std::rc::Rc;
+
+use crate::{value::Value, CharStream, JQErr};
+
+fn string_required(input: &Value, name: &str) -> Result<Rc<str>, JQErr> {
+    match input {
+        Value::String(str) => Ok(str.clone()),
+        _ => Err(JQErr::StreamOperationFailed(
+            format!("{name} input must be a string").into(),
+        )),
+    }
+}
+
+pub(crate) fn ascii_downcase(input: &Value) -> Result<Value, JQErr> {
+    let str = string_required(input, "ascii_downcase")?;
+    Ok(Value::String(str.to_ascii_lowercase().into()))
+}
+
+pub(crate) fn ascii_upcase(input: &Value) -> Result<Value, JQErr> {
+    let str = string_required(input, "ascii_upcase")?;
+    Ok(Value::String(str.to_ascii_uppercase().into()))
+}
+
+/// `ltrimstr` and `rtrimstr` leave anything that isn't a string as it is.
+pub(crate) fn ltrimstr(input: Value, prefix: &str) -> Value {
+    match &input {
+        Value::String(str) => match str.strip_prefix(prefix) {
+            Some(trimmed) => Value::String(trimmed.into()),
+            None => input,
+        },
+        _ => input,
+    }
+}
+
+pub(crate) fn rtrimstr(input: Value, suffix: &str) -> Value {
+    match &input {
+        Value::String(str) => match str.strip_suffix(suffix) {
+            Some(trimmed) => Value::String(trimmed.into()),
+            None => input,
+        },
+        _ => input,
+    }
+}
+
+pub(crate) fn trim(input: &Value, start: bool, end: bool) -> Result<Value, JQErr> {
+    let name = match (start, end) {
+        (true, true) => "trim",
+        (true, false) => "ltrim",
+        _ => "rtrim",
+    };
+    let str = string_required(input, name)?;
+
+    let mut trimmed = &*str;
+    if start {
+        trimmed = trimmed.trim_start();
+    }
+    if end {
+        trimmed = trimmed.trim_end();
+    }
+    Ok(Value::String(trimmed.into()))
+}
+
+pub(crate) fn startswith(input: &Value, prefix: &str) -> Result<Value, JQErr> {
+    match input {
+        Value::String(str) => Ok(Value::from(str.starts_with(prefix))),
+        _ => Err(JQErr::StreamOperationFailed(
+            "startswith() requires string inputs".into(),
+        )),
+    }
+}
+
+pub(crate) fn endswith(input: &Value, suffix: &str) -> Result<Value, JQErr> {
+    match input {
+        Value::String(str) => Ok(Value::from(str.ends_with(suffix))),
+        _ => Err(JQErr::StreamOperationFailed(
+            "endswith() requires string inputs".into(),
+        )),
+    }
+}
+
+pub(crate) fn split(input: &Value, separator: &str) -> Result<Value, JQErr> {
+    let str = match input {
+        Value::String(str) => str,
+        _ => {
+            return Err(JQErr::StreamOperationFailed(
+                "split input and separator must be strings".into(),
+            ))
+        }
+    };
+
+    if str.is_empty() {
+        return Ok(Value::Array(Vec::new()));
+    }
+
+    let parts = if separator.is_empty() {
+        str.chars()
+            .map(|ch| Value::String(ch.to_string().into()))
+            .collect()
+    } else {
+        str.split(separator)
+            .map(|part| Value::String(part.into()))
+            .collect()
+    };
+    Ok(Value::Array(parts))
+}
+
+/// jq's `join`: `null`s become empty strings, numbers and booleans are
+/// converted with `tojson` and anything else can't be joined.
+pub(crate) fn join(input: Value, separator: &str) -> Result<Value, JQErr> {
+    let items = match input {
+        Value::Array(items) => items,
+        Value::Object(entries) => entries.into_iter().map(|(_, value)| value).collect(),
+        other => {
+            return Err(JQErr::StreamOperationFailed(
+                format!("Cannot iterate over {}", other.describe()).into(),
+            ))
+        }
+    };
+
+    let mut joined = String::new();
+    for (i, item) in items.into_iter().enumerate() {
+        if i != 0 {
+            joined.push_str(separator);
+        }
+
+        match item {
+            Value::Null => {}
+            Value::String(str) => joined.push_str(&str),
+            item @ (Value::True | Value::False | Value::Number(_) | Value::ParsedNumber(_)) => {
+                joined.push_str(&item.to_json()?)
+            }
+            item => {
+                return Err(JQErr::StreamOperationFailed(
+                    format!(
+                        "{} and {} cannot be added",
+                        Value::String(joined.into()).describe(),
+                        item.describe()
+                    )
+                    .into(),
+                ))
+            }
+        }
+    }
+    Ok(Value::String(joined.into()))
+}
+
+pub(crate) fn explode(input: &Value) -> Result<Value, JQErr> {
+    let str = string_required(input, "explode")?;
+    Ok(Value::Array(
+        str.chars()
+            .map(|ch| Value::ParsedNumber(ch as u32 as f64))
+            .collect(),
+    ))
+}
+
+pub(crate) fn implode(input: &Value) -> Result<Value, JQErr> {
+    let items = match input {
+        Value::Array(items) => items,
+        _ => {
+            return Err(JQErr::StreamOperationFailed(
+                "implode input must be an array".into(),
+            ))
+        }
+    };
+
+    let mut imploded = String::with_capacity(items.len());
+    for item in items {
+        let code = match item.as_f64() {
+            Some(code) => code,
+            None => {
+                return Err(JQErr::StreamOperationFailed(
+                    "Unicode codepoint must be numeric".into(),
+                ))
+            }
+        };
+
+        match char::from_u32(code as u32) {
+            Some(ch) if code >= 0.0 && code <= u32::MAX as f64 => imploded.push(ch),
+            _ => {
+                return Err(JQErr::StreamOperationFailed(
+                    format!("Invalid codepoint literal {code}").into(),
+                ))
+            }
+        }
+    }
+    Ok(Value::String(imploded.into()))
+}
+
+pub(crate) fn tostring(input: Value) -> Result<Value, JQErr> {
+    match input {
+        Value::String(_) => Ok(input),
+        other => Ok(Value::String(other.to_json()?.into())),
+    }
+}
+
+pub(crate) fn tonumber(input: Value) -> Result<Value, JQErr> {
+    match input {
+        Value::Number(_) | Value::ParsedNumber(_) => Ok(input),
+        Value::String(str) => {
+            let mut tokens = str.chars().into_json_tokens();
+            match (tokens.next(), tokens.next()) {
+                (Some(Ok(crate::Token::Number(num))), None) => Ok(Value::Number(num)),
+                _ => Err(JQErr::StreamOperationFailed(
+                    format!("Cannot parse '{str}' as JSON").into(),
+                )),
+            }
+        }
+        other => Err(JQErr::StreamOperationFailed(
+            format!("{} cannot be parsed as a number", other.describe()).into(),
+        )),
+    }
+}
+
+pub(crate) fn tojson(input: &Value) -> Result<Value, JQErr> {
+    Ok(Value::String(input.to_json()?.into()))
+}
+
+pub(crate) fn fromjson(input: &Value) -> Result<Value, JQErr> {
+    let str = match input {
+        Value::String(str) => str,
+        other => {
+            return Err(JQErr::StreamOperationFailed(
+                format!("{} cannot be parsed as JSON", other.describe()).into(),
+            ))
+        }
+    };
+
+    let mut values = match Value::all_from(str.chars().into_json_tokens()) {
+        Ok(values) => values.into_iter(),
+        Err(err) => {
+            return Err(JQErr::StreamOperationFailed(
+                format!("{err} (while parsing '{str}')").into(),
+            ))
+        }
+    };
+
+    match (values.next(), values.next()) {
+        (Some(value), None) => Ok(value),
+        (None, _) => Err(JQErr::StreamOperationFailed(
+            format!("Expected JSON value (while parsing '{str}')").into(),
+        )),
+        (Some(_), Some(_)) => Err(JQErr::StreamOperationFailed(
+            format!("Unexpected extra JSON values (while parsing '{str}')").into(),
+        )),
+    }
+}
+
+pub(crate) fn utf8bytelength(input: &Value) -> Result<Value, JQErr> {
+    match input {
+        Value::String(str) => Ok(Value::ParsedNumber(str.len() as f64)),
+        other => Err(JQErr::StreamOperationFailed(
+            format!("{} only strings have UTF-8 byte length", other.describe()).into(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_support::values, JQErr, SanitizedJQStream};
+
+    fn failure(result: Result<String, JQErr>) -> String {
+        match result {
+            Err(JQErr::StreamOperationFailed(msg)) => msg.to_string(),
+            other => panic!("expected an operation failure, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn changes_case_and_trims() {
+        assert_eq!(
+            values(r#""aBc-é""#).ascii_upcase().to_string().unwrap(),
+            "\"ABC-é\"\n"
+        );
+        assert_eq!(
+            values(r#""aBc""#).ascii_downcase().to_string().unwrap(),
+            "\"abc\"\n"
+        );
+        assert_eq!(values(r#"" a ""#).trim().to_string().unwrap(), "\"a\"\n");
+        assert_eq!(values(r#"" a ""#).ltrim().to_string().unwrap(), "\"a \"\n");
+        assert_eq!(values(r#"" a ""#).rtrim().to_string().unwrap(), "\" a\"\n");
+        assert_eq!(
+            failure(values("1").trim().to_string()),
+            "trim input must be a string"
+        );
+    }
+
+    #[test]
+    fn trims_and_checks_prefixes_and_suffixes() {
+        assert_eq!(
+            values(r#""foobar" "bar" 1"#)
+                .ltrimstr("foo")
+                .to_string()
+                .unwrap(),
+            "\"bar\"\n\"bar\"\n1\n"
+        );
+        assert_eq!(
+            values(r#""foobar""#).rtrimstr("bar").to_string().unwrap(),
+            "\"foo\"\n"
+        );
+        assert_eq!(
+            values(r#""foobar""#).startswith("foo").to_string().unwrap(),
+            "true\n"
+        );
+        assert_eq!(
+            values(r#""foobar""#).endswith("foo").to_string().unwrap(),
+            "false\n"
+        );
+        assert_eq!(
+            failure(values("1").startswith("foo").to_string()),
+            "startswith() requires string inputs"
+        );
+    }
+
+    #[test]
+    fn splits_and_joins() {
+        assert_eq!(
+            values(r#""a, b, c" "" "ab""#)
+                .split(", ")
+                .to_string()
+                .unwrap(),
+            "[\"a\",\"b\",\"c\"]\n[]\n[\"ab\"]\n"
+        );
+        assert_eq!(
+            values(r#""ab""#).split("").to_string().unwrap(),
+            "[\"a\",\"b\"]\n"
+        );
+        assert_eq!(
+            values(r#"["a", 1, null, true]"#)
+                .join("-")
+                .to_string()
+                .unwrap(),
+            "\"a-1--true\"\n"
+        );
+        assert_eq!(
+            failure(values(r#"["a", [1]]"#).join("-").to_string()),
+            "string (\"a-\") and array ([1]) cannot be added"
+        );
+    }
+
+    #[test]
+    fn explodes_and_implodes_codepoints() {
+        assert_eq!(
+            values(r#""aé😀""#).explode().to_string().unwrap(),
+            "[97,233,128512]\n"
+        );
+        assert_eq!(
+            values("[97, 233, 128512]").implode().to_string().unwrap(),
+            "\"aé😀\"\n"
+        );
+        assert_eq!(
+            failure(values("[55296]").implode().to_string()),
+            "Invalid codepoint literal 55296"
+        );
+    }
+
+    #[test]
+    fn converts_to_and_from_json() {
+        assert_eq!(
+            values(r#"[1, 2] "a" 1"#).tostring().to_string().unwrap(),
+            "\"[1,2]\"\n\"a\"\n\"1\"\n"
+        );
+        assert_eq!(
+            values(r#""1.50" 2"#).tonumber().to_string().unwrap(),
+            "1.50\n2\n"
+        );
+        assert_eq!(
+            failure(values(r#""1 2""#).tonumber().to_string()),
+            "Cannot parse '1 2' as JSON"
+        );
+        assert_eq!(
+            values(r#"{"a": [1]}"#)
+                .tojson()
+                .fromjson()
+                .to_string()
+                .unwrap(),
+            "{\"a\":[1]}\n"
+        );
+        assert_eq!(
+            failure(values(r#""1 2""#).fromjson().to_string()),
+            "Unexpected extra JSON values (while parsing '1 2')"
+        );
+        assert_eq!(
+            values(r#""aé😀""#).utf8bytelength().to_string().unwrap(),
+            "7\n"
+        );
+    }
+}
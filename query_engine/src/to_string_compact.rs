@@ -1,6 +1,9 @@
 use std::collections::VecDeque;
 
-use crate::{JQStream, JQErr, Sanitized, Token};
+use crate::{
+    escape::write_string, number_format::format_number, JQErr, JQStream, Sanitized, StringEscapes,
+    Token,
+};
 
 pub struct CompactChars<Stream>
 where
@@ -8,6 +11,7 @@ where
 {
     stream: Sanitized<Stream>,
     buf: VecDeque<char>,
+    escapes: StringEscapes,
 }
 
 impl<Stream> CompactChars<Stream>
@@ -18,8 +22,16 @@ where
         Self {
             stream: stream.sanitize(),
             buf: VecDeque::new(),
+            escapes: StringEscapes::default(),
         }
     }
+
+    /// Sets which characters are escaped in strings, such as everything
+    /// outside of ASCII for jq's `--ascii-output`.
+    pub fn with_escapes(mut self, escapes: StringEscapes) -> Self {
+        self.escapes = escapes;
+        self
+    }
 }
 
 impl<Stream> Iterator for CompactChars<Stream>
@@ -35,49 +47,10 @@ where
 
         let result = match self.stream.next()? {
             Err(err) => Some(Err(err)),
-            Ok(token_kind) => match token_kind {
-                Token::ObjectStart => Some(Ok('{')),
-                Token::ObjectEnd => Some(Ok('}')),
-                Token::ArrayStart => Some(Ok('[')),
-                Token::ArrayEnd => Some(Ok(']')),
-                Token::Colon => Some(Ok(':')),
-                Token::Comma => Some(Ok(',')),
-                Token::Number(str) | Token::String(str) => {
-                    for ch in str.chars() {
-                        self.buf.push_back(ch);
-                    }
-                    self.buf.push_back('"');
-                    Some(Ok('"'))
-                }
-                Token::ParsedNumber(value) => {
-                    let str = value.to_string();
-                    for ch in str.chars() {
-                        self.buf.push_back(ch);
-                    }
-                    Some(Ok(self.buf.pop_front().expect(
-                        "A float to generate a string with at least one character",
-                    )))
-                }
-                Token::True => {
-                    self.buf.push_back('r');
-                    self.buf.push_back('u');
-                    self.buf.push_back('e');
-                    Some(Ok('t'))
-                }
-                Token::False => {
-                    self.buf.push_back('a');
-                    self.buf.push_back('l');
-                    self.buf.push_back('s');
-                    self.buf.push_back('e');
-                    Some(Ok('f'))
-                }
-                Token::Null => {
-                    self.buf.push_back('u');
-                    self.buf.push_back('l');
-                    self.buf.push_back('l');
-                    Some(Ok('n'))
-                }
-            },
+            Ok(token) => {
+                write_token(token, self.escapes, &mut self.buf);
+                self.buf.pop_front().map(Ok)
+            }
         };
 
         if self.stream.get_path().is_empty() {
@@ -87,3 +60,21 @@ where
         result
     }
 }
+
+/// Writes the compact JSON for a single token.
+pub(crate) fn write_token(token: Token, escapes: StringEscapes, out: &mut VecDeque<char>) {
+    match token {
+        Token::ObjectStart => out.push_back('{'),
+        Token::ObjectEnd => out.push_back('}'),
+        Token::ArrayStart => out.push_back('['),
+        Token::ArrayEnd => out.push_back(']'),
+        Token::Colon => out.push_back(':'),
+        Token::Comma => out.push_back(','),
+        Token::String(str) => write_string(&str, escapes, out),
+        Token::Number(str) => out.extend(str.chars()),
+        Token::ParsedNumber(value) => out.extend(format_number(value).chars()),
+        Token::True => out.extend("true".chars()),
+        Token::False => out.extend("false".chars()),
+        Token::Null => out.extend("null".chars()),
+    }
+}
@@ -1,6 +1,9 @@
 use std::collections::VecDeque;
 
-use crate::{JQStream, JQErr, Sanitized, Token};
+use crate::{
+    escape::write_string, number_format::format_number, JQErr, JQStream, Sanitized, StringEscapes,
+    Token,
+};
 
 pub struct PrettyChars<Stream>
 where
@@ -10,6 +13,7 @@ where
     buf: VecDeque<char>,
     indent_level: usize,
     previous: Option<Token>,
+    escapes: StringEscapes,
 }
 
 impl<Stream> PrettyChars<Stream>
@@ -30,9 +34,17 @@ where
             buf: VecDeque::new(),
             indent_level: 0,
             previous: None,
+            escapes: StringEscapes::default(),
         }
     }
 
+    /// Sets which characters are escaped in strings, such as everything
+    /// outside of ASCII for jq's `--ascii-output`.
+    pub fn with_escapes(mut self, escapes: StringEscapes) -> Self {
+        self.escapes = escapes;
+        self
+    }
+
     fn add_new_line(&mut self) {
         {
             self.buf.extend(Self::new_line().chars());
@@ -56,7 +68,7 @@ where
         }
 
         match self.stream.next()? {
-            Err(err) => return Some(Err(err)),
+            Err(err) => Some(Err(err)),
             Ok(token_kind) => {
                 if self.stream.get_path().is_empty()
                     && self.previous.is_some()
@@ -107,7 +119,14 @@ where
                         self.buf.push_back(',');
                         self.add_new_line();
                     }
-                    Token::String(str) | Token::Number(str) => {
+                    Token::String(str) => {
+                        if let Some(Token::ObjectStart | Token::ArrayStart) = self.previous {
+                            self.add_new_line();
+                        }
+
+                        write_string(&str, self.escapes, &mut self.buf);
+                    }
+                    Token::Number(str) => {
                         if let Some(Token::ObjectStart | Token::ArrayStart) = self.previous {
                             self.add_new_line();
                         }
@@ -119,7 +138,7 @@ where
                             self.add_new_line();
                         }
 
-                        let string = value.to_string();
+                        let string = format_number(value);
                         self.buf.extend(string.chars());
                     }
                     Token::True => {
@@ -143,10 +162,10 @@ where
                 }
 
                 self.previous = Some(token_kind.clone());
-                return Some(Ok(self
+                Some(Ok(self
                     .buf
                     .pop_front()
-                    .expect("buf to have a character in it")));
+                    .expect("buf to have a character in it")))
             }
         }
     }
@@ -31,4 +31,13 @@ impl Token {
             Token::Comma | Token::Colon | Token::ObjectEnd | Token::ArrayEnd => false,
         }
     }
+
+    /// Gets the numeric value of this token, if it is a number.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Token::Number(num) => num.parse::<f64>().ok(),
+            Token::ParsedNumber(num) => Some(*num),
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,323 @@
+use std::collections::VecDeque;
+
+use toml::{Table, Value as TomlValue};
+
+use crate::{
+    raw::LimitCheck, value::Value, JQErr, JQStream, Location, ParseLimits, Sanitized,
+    SanitizedJQStream, ValueTokens,
+};
+
+/// Reads a TOML document into a stream of JSON tokens, producing the
+/// whole document as a single object. Datetimes become strings in their
+/// TOML form, such as `"1979-05-27T07:32:00Z"`.
+///
+/// TOML tables can be extended anywhere in a document, so the input is
+/// read in full before the first token is produced.
+pub struct TomlTokens<Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    chars: Option<Chars>,
+    tokens: Option<ValueTokens>,
+    limits: LimitCheck,
+    /// Where the document ends, as that's where limit errors are.
+    end: Location,
+}
+
+impl<Chars> TomlTokens<Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    pub(crate) fn new(chars: Chars) -> Self {
+        Self {
+            chars: Some(chars),
+            tokens: None,
+            limits: LimitCheck::default(),
+            end: Location::new(0, 0),
+        }
+    }
+
+    /// Sets limits on the input, such as how many tokens it may have. The
+    /// document is read in full first, so they only limit the tokens
+    /// produced from it, and their errors are at the end of the document.
+    pub fn with_limits(mut self, limits: ParseLimits) -> Self {
+        self.limits = LimitCheck::new(limits);
+        self
+    }
+}
+
+impl<Chars> Iterator for TomlTokens<Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(chars) = self.chars.take() {
+            let text = chars.collect::<String>();
+            self.end = location(&text, text.len());
+            match text.parse::<Table>() {
+                Ok(table) => self.tokens = Some(from_toml(TomlValue::Table(table)).into_stream()),
+                Err(err) => {
+                    let offset = err.span().map(|span| span.start).unwrap_or_default();
+                    return Some(Err(JQErr::InvalidToml {
+                        location: location(&text, offset),
+                        msg: err.message().into(),
+                    }));
+                }
+            }
+        }
+
+        let token = match self.tokens.as_mut()?.next()? {
+            Ok(token) => token,
+            Err(err) => return Some(Err(err)),
+        };
+        if let Err(err) = self.limits.check(&token, || self.end.clone()) {
+            self.tokens = None;
+            return Some(Err(err));
+        }
+        Some(Ok(token))
+    }
+}
+
+impl<Chars> SanitizedJQStream for TomlTokens<Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    // TOML rejects tables which repeat a key.
+    fn has_unique_keys(&self) -> bool {
+        true
+    }
+}
+
+/// Finds the zero-based line and column of a byte offset into `text`.
+fn location(text: &str, offset: usize) -> Location {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count();
+    let col = before
+        .rsplit('\n')
+        .next()
+        .map(|line| line.chars().count())
+        .unwrap_or_default();
+    Location::new(line, col)
+}
+
+fn from_toml(value: TomlValue) -> Value {
+    match value {
+        TomlValue::String(str) => Value::String(str.into()),
+        TomlValue::Integer(num) => Value::Number(num.to_string().into()),
+        TomlValue::Float(num) => Value::ParsedNumber(num),
+        TomlValue::Boolean(bool) => Value::from(bool),
+        TomlValue::Datetime(datetime) => Value::String(datetime.to_string().into()),
+        TomlValue::Array(items) => Value::Array(items.into_iter().map(from_toml).collect()),
+        TomlValue::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key.into(), from_toml(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn to_toml(value: Value) -> Result<TomlValue, JQErr> {
+    Ok(match value {
+        Value::Null => {
+            return Err(JQErr::StreamOperationFailed(
+                "null cannot be written as TOML".into(),
+            ))
+        }
+        Value::True => TomlValue::Boolean(true),
+        Value::False => TomlValue::Boolean(false),
+        Value::Number(num) => match num.parse::<i64>() {
+            Ok(num) => TomlValue::Integer(num),
+            Err(_) => TomlValue::Float(Value::Number(num).as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::ParsedNumber(num) if num.fract() == 0.0 && num.abs() < i64::MAX as f64 => {
+            TomlValue::Integer(num as i64)
+        }
+        Value::ParsedNumber(num) => TomlValue::Float(num),
+        Value::String(str) => TomlValue::String(str.to_string()),
+        Value::Array(items) => {
+            TomlValue::Array(items.into_iter().map(to_toml).collect::<Result<_, _>>()?)
+        }
+        Value::Object(entries) => TomlValue::Table(
+            entries
+                .into_iter()
+                .map(|(key, value)| Ok((key.to_string(), to_toml(value)?)))
+                .collect::<Result<_, JQErr>>()?,
+        ),
+    })
+}
+
+/// Writes a value as a TOML document. Only an object can be written, and
+/// nothing in it can be null, as TOML has no way to represent either.
+/// Since a TOML file holds exactly one document, a second value is an
+/// error too.
+pub struct TomlChars<Stream>
+where
+    Stream: JQStream,
+{
+    finished: bool,
+    stream: Sanitized<Stream>,
+    started: bool,
+    buf: VecDeque<char>,
+}
+
+impl<Stream> TomlChars<Stream>
+where
+    Stream: JQStream,
+{
+    pub(crate) fn new(stream: Stream) -> Self {
+        Self {
+            finished: false,
+            stream: stream.sanitize(),
+            started: false,
+            buf: VecDeque::new(),
+        }
+    }
+
+    fn write(&mut self, value: Value) -> Result<(), JQErr> {
+        if std::mem::replace(&mut self.started, true) {
+            return Err(JQErr::StreamOperationFailed(
+                "Only one value can be written as a TOML document".into(),
+            ));
+        }
+
+        let table = match value {
+            value @ Value::Object(_) => match to_toml(value)? {
+                TomlValue::Table(table) => table,
+                _ => unreachable!("objects always convert to tables"),
+            },
+            other => {
+                return Err(JQErr::StreamOperationFailed(
+                    format!(
+                        "{} cannot be written as a TOML document, as it is not an object",
+                        other.describe()
+                    )
+                    .into(),
+                ))
+            }
+        };
+
+        let text = toml::to_string(&table)
+            .map_err(|err| JQErr::StreamOperationFailed(err.to_string().into()))?;
+        self.buf.extend(text.chars());
+        Ok(())
+    }
+}
+
+impl<Stream> Iterator for TomlChars<Stream>
+where
+    Stream: JQStream,
+{
+    type Item = Result<char, JQErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(ch) = self.buf.pop_front() {
+                return Some(Ok(ch));
+            }
+
+            if self.finished {
+                return None;
+            }
+
+            // An empty object is an empty document, so this loops until
+            // there is something to write.
+            let result = Value::next_from(&mut self.stream)?.and_then(|value| self.write(value));
+            if let Err(err) = result {
+                self.finished = true;
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CharStream, JQErr, Location, ParseLimits, SanitizedJQStream};
+
+    fn read(toml: &str, limits: ParseLimits) -> Result<String, JQErr> {
+        toml.chars()
+            .into_toml_tokens()
+            .with_limits(limits)
+            .to_string()
+    }
+
+    #[test]
+    fn limits_the_tokens_of_the_document() {
+        let toml = "a = \"abc\"\n[b]\nc = [1, 2]\n";
+        assert_eq!(
+            read(toml, ParseLimits::default().with_max_tokens(17)).unwrap(),
+            "{\"a\":\"abc\",\"b\":{\"c\":[1,2]}}\n"
+        );
+        assert!(matches!(
+            read(toml, ParseLimits::default().with_max_tokens(16)),
+            Err(JQErr::TokenLimitExceeded(location)) if location == Location::new(3, 0)
+        ));
+        assert!(matches!(
+            read(toml, ParseLimits::default().with_max_depth(2)),
+            Err(JQErr::DepthLimitExceeded(_))
+        ));
+        assert!(matches!(
+            read(toml, ParseLimits::default().with_max_string_len(2)),
+            Err(JQErr::StringLimitExceeded(_))
+        ));
+    }
+
+    fn to_toml(json: &str) -> Result<String, JQErr> {
+        json.chars().into_json_tokens().to_chars_toml().collect()
+    }
+
+    fn from_toml(toml: &str) -> Result<String, JQErr> {
+        toml.chars().into_toml_tokens().to_string()
+    }
+
+    #[test]
+    fn reads_a_document_as_one_object() {
+        assert_eq!(
+            from_toml(
+                "title = \"x\"\nwhen = 1979-05-27T07:32:00Z\n[server]\nport = 8080\nratio = 0.5\nhosts = [\"a\", \"b\"]\n[[items]]\nid = 1\n[[items]]\nid = 2\n"
+            )
+            .unwrap(),
+            "{\"title\":\"x\",\"when\":\"1979-05-27T07:32:00Z\",\"server\":{\"port\":8080,\"ratio\":0.5,\"hosts\":[\"a\",\"b\"]},\"items\":[{\"id\":1},{\"id\":2}]}\n"
+        );
+    }
+
+    #[test]
+    fn reports_where_a_document_is_malformed() {
+        assert!(matches!(
+            from_toml("a = 1\nb = \n"),
+            Err(JQErr::InvalidToml { location, .. }) if location.line() == 1
+        ));
+        assert!(matches!(
+            from_toml("a = 1\na = 2\n"),
+            Err(JQErr::InvalidToml { .. })
+        ));
+    }
+
+    #[test]
+    fn round_trips_objects() {
+        let json = r#"{"a": "x\"y\nz", "b": [1, 2.5, true], "c": {"d": {"e": -3}}, "f": [{"g": 1}, {"g": 2}], "h": {}}"#;
+        let expected = json.chars().into_json_tokens().to_string().unwrap();
+        assert_eq!(from_toml(&to_toml(json).unwrap()).unwrap(), expected);
+    }
+
+    #[test]
+    fn rejects_values_which_toml_cannot_hold() {
+        assert!(matches!(
+            to_toml(r#"{"a": null}"#),
+            Err(JQErr::StreamOperationFailed(msg)) if &*msg == "null cannot be written as TOML"
+        ));
+        assert!(matches!(
+            to_toml("[1]"),
+            Err(JQErr::StreamOperationFailed(msg))
+                if &*msg == "array ([1]) cannot be written as a TOML document, as it is not an object"
+        ));
+        assert!(matches!(
+            to_toml(r#"{"a": 1} {"b": 2}"#),
+            Err(JQErr::StreamOperationFailed(msg))
+                if &*msg == "Only one value can be written as a TOML document"
+        ));
+    }
+}
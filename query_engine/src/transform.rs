@@ -0,0 +1,458 @@
+use std::{collections::VecDeque, rc::Rc};
+
+use crate::{
+    budget::Spending, paths, re, re::Pattern, stream_events, strings, value::Value, JQErr,
+    JQStream, Sanitized, SanitizedJQStream, Token,
+};
+
+/// The builtins which transform each input value into a new value.
+pub(crate) enum TransformOp {
+    /// `flatten` and `flatten(depth)`. [`None`] flattens all the way down.
+    Flatten(Option<usize>),
+    /// `reverse`.
+    Reverse,
+    /// `contains(b)`, once for every value `b` produces.
+    Contains,
+    /// `inside(b)`, once for every value `b` produces.
+    Inside,
+    /// `indices(i)`, once for every value `i` produces.
+    Indices,
+    /// `ascii_downcase`.
+    AsciiDowncase,
+    /// `ascii_upcase`.
+    AsciiUpcase,
+    /// `ltrimstr(prefix)`.
+    LtrimStr(Rc<str>),
+    /// `rtrimstr(suffix)`.
+    RtrimStr(Rc<str>),
+    /// `trim`, `ltrim` and `rtrim`, as (start, end).
+    Trim(bool, bool),
+    /// `startswith(prefix)`.
+    StartsWith(Rc<str>),
+    /// `endswith(suffix)`.
+    EndsWith(Rc<str>),
+    /// `split(separator)`.
+    Split(Rc<str>),
+    /// `join(separator)`.
+    Join(Rc<str>),
+    /// `explode`.
+    Explode,
+    /// `implode`.
+    Implode,
+    /// `tostring`.
+    ToString,
+    /// `tonumber`.
+    ToNumber,
+    /// `tojson`.
+    ToJson,
+    /// `fromjson`.
+    FromJson,
+    /// `utf8bytelength`.
+    Utf8ByteLength,
+    /// `getpath(path)`, once for every value `path` produces.
+    GetPath,
+    /// `pick(pathexps)`, with all the paths `pathexps` produces.
+    Pick,
+    /// `abs`.
+    Abs,
+    /// `toarray`.
+    ToArray,
+    /// `splits($re; $flags)`.
+    Splits(Pattern),
+    /// `scan($re; $flags)`.
+    Scan(Pattern),
+    /// `depth | truncate_stream(events)`.
+    TruncateStream(usize),
+    /// Queries like `have_decnum` and `$__loc__` which replace every input
+    /// with the same value.
+    Constant(Value),
+}
+
+/// A struct for handling the jq queries which map every input value to
+/// new values on their own, such as `flatten`, `reverse`, `contains(b)`,
+/// `indices(i)` and the string builtins like `split(separator)`. The
+/// active budget is spent on every token of the values transformed.
+pub struct Transform<Stream>
+where
+    Stream: JQStream,
+{
+    finished: bool,
+    stream: Sanitized<Spending<Stream>>,
+    op: TransformOp,
+    args: Vec<Value>,
+    args_err: Option<JQErr>,
+    buf: VecDeque<Token>,
+}
+
+impl<Stream> Transform<Stream>
+where
+    Stream: JQStream,
+{
+    pub(crate) fn new(stream: Stream, op: TransformOp) -> Self {
+        Self {
+            finished: false,
+            stream: Spending::new(stream).sanitize(),
+            op,
+            args: Vec::new(),
+            args_err: None,
+            buf: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn with_args<Args>(stream: Stream, op: TransformOp, args: Args) -> Self
+    where
+        Args: JQStream,
+    {
+        let mut transform = Self::new(stream, op);
+        match Value::all_from(args) {
+            Ok(args) => transform.args = args,
+            Err(err) => transform.args_err = Some(err),
+        }
+        transform
+    }
+
+    fn apply(&self, input: Value) -> Result<Vec<Value>, JQErr> {
+        match &self.op {
+            TransformOp::Flatten(depth) => Ok(vec![flatten(input, *depth)?]),
+            TransformOp::Reverse => Ok(vec![reverse(input)?]),
+            TransformOp::Contains => self
+                .args
+                .iter()
+                .map(|arg| contains(&input, arg).map(Value::from))
+                .collect(),
+            TransformOp::Inside => self
+                .args
+                .iter()
+                .map(|arg| contains(arg, &input).map(Value::from))
+                .collect(),
+            TransformOp::Indices => self.args.iter().map(|arg| indices(&input, arg)).collect(),
+            TransformOp::AsciiDowncase => Ok(vec![strings::ascii_downcase(&input)?]),
+            TransformOp::AsciiUpcase => Ok(vec![strings::ascii_upcase(&input)?]),
+            TransformOp::LtrimStr(prefix) => Ok(vec![strings::ltrimstr(input, prefix)]),
+            TransformOp::RtrimStr(suffix) => Ok(vec![strings::rtrimstr(input, suffix)]),
+            TransformOp::Trim(start, end) => Ok(vec![strings::trim(&input, *start, *end)?]),
+            TransformOp::StartsWith(prefix) => Ok(vec![strings::startswith(&input, prefix)?]),
+            TransformOp::EndsWith(suffix) => Ok(vec![strings::endswith(&input, suffix)?]),
+            TransformOp::Split(separator) => Ok(vec![strings::split(&input, separator)?]),
+            TransformOp::Join(separator) => Ok(vec![strings::join(input, separator)?]),
+            TransformOp::Explode => Ok(vec![strings::explode(&input)?]),
+            TransformOp::Implode => Ok(vec![strings::implode(&input)?]),
+            TransformOp::ToString => Ok(vec![strings::tostring(input)?]),
+            TransformOp::ToNumber => Ok(vec![strings::tonumber(input)?]),
+            TransformOp::ToJson => Ok(vec![strings::tojson(&input)?]),
+            TransformOp::FromJson => Ok(vec![strings::fromjson(&input)?]),
+            TransformOp::Utf8ByteLength => Ok(vec![strings::utf8bytelength(&input)?]),
+            TransformOp::GetPath => self
+                .args
+                .iter()
+                .map(|path| paths::getpath(&input, path))
+                .collect(),
+            TransformOp::Pick => Ok(vec![paths::pick(&input, &self.args)?]),
+            TransformOp::Abs => Ok(vec![abs(input)?]),
+            TransformOp::ToArray => Ok(vec![toarray(input)]),
+            TransformOp::Splits(pattern) => re::splits(&input, pattern),
+            TransformOp::Scan(pattern) => re::scan(&input, pattern),
+            TransformOp::TruncateStream(depth) => {
+                Ok(stream_events::truncate_stream(input, *depth)?
+                    .into_iter()
+                    .collect())
+            }
+            TransformOp::Constant(value) => Ok(vec![value.clone()]),
+        }
+    }
+}
+
+impl<Stream> Iterator for Transform<Stream>
+where
+    Stream: JQStream,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.buf.pop_front() {
+            return Some(Ok(token));
+        }
+
+        if self.finished {
+            return None;
+        }
+
+        if let Some(err) = self.args_err.take() {
+            self.finished = true;
+            return Some(Err(err));
+        }
+
+        loop {
+            let result = Value::next_from(&mut self.stream)?.and_then(|input| self.apply(input));
+            match result {
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+                Ok(values) => {
+                    for value in values {
+                        value.push_tokens(&mut self.buf);
+                    }
+
+                    if let Some(token) = self.buf.pop_front() {
+                        return Some(Ok(token));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<Stream> SanitizedJQStream for Transform<Stream> where Stream: JQStream {}
+
+fn flatten(input: Value, depth: Option<usize>) -> Result<Value, JQErr> {
+    fn flatten_into(items: Vec<Value>, depth: Option<usize>, out: &mut Vec<Value>) {
+        for item in items {
+            match item {
+                Value::Array(inner) if depth != Some(0) => {
+                    flatten_into(inner, depth.map(|depth| depth - 1), out)
+                }
+                item => out.push(item),
+            }
+        }
+    }
+
+    let items = match input {
+        Value::Array(items) => items,
+        Value::Object(entries) => entries.into_iter().map(|(_, value)| value).collect(),
+        other => {
+            return Err(JQErr::StreamOperationFailed(
+                format!("Cannot iterate over {}", other.describe()).into(),
+            ))
+        }
+    };
+
+    let mut out = Vec::new();
+    flatten_into(items, depth, &mut out);
+    Ok(Value::Array(out))
+}
+
+fn reverse(input: Value) -> Result<Value, JQErr> {
+    match input {
+        Value::Null => Ok(Value::Array(Vec::new())),
+        Value::Array(mut items) => {
+            items.reverse();
+            Ok(Value::Array(items))
+        }
+        Value::String(str) => Ok(Value::String(str.chars().rev().collect::<String>().into())),
+        Value::Object(entries) if entries.is_empty() => Ok(Value::Array(Vec::new())),
+        other => Err(JQErr::StreamOperationFailed(
+            format!("Cannot index {} with number", other.type_name()).into(),
+        )),
+    }
+}
+
+fn abs(input: Value) -> Result<Value, JQErr> {
+    match input.as_f64() {
+        Some(number) if number < 0.0 => Ok(Value::ParsedNumber(-number)),
+        Some(_) => Ok(input),
+        None => Err(JQErr::StreamOperationFailed(
+            format!("{} has no absolute value", input.describe()).into(),
+        )),
+    }
+}
+
+fn toarray(input: Value) -> Value {
+    match input {
+        Value::Array(_) => input,
+        other => Value::Array(vec![other]),
+    }
+}
+
+/// jq's `contains`: strings contain substrings, arrays contain arrays
+/// whose every element is contained by one of theirs, objects contain
+/// objects whose every value is contained by theirs at the same key and
+/// everything else must be equal.
+pub(crate) fn contains(a: &Value, b: &Value) -> Result<bool, JQErr> {
+    fn contains_inner(a: &Value, b: &Value) -> bool {
+        if !a.same_kind(b) {
+            return false;
+        }
+
+        match (a, b) {
+            (Value::Object(a), Value::Object(b)) => b.iter().all(|(key, b_value)| {
+                a.iter()
+                    .rev()
+                    .find(|(a_key, _)| a_key == key)
+                    .is_some_and(|(_, a_value)| contains_inner(a_value, b_value))
+            }),
+            (Value::Array(a), Value::Array(b)) => b
+                .iter()
+                .all(|b_item| a.iter().any(|a_item| contains_inner(a_item, b_item))),
+            (Value::String(a), Value::String(b)) => a.contains(&**b),
+            (a, b) => a == b,
+        }
+    }
+
+    if !a.same_kind(b) {
+        return Err(JQErr::StreamOperationFailed(
+            format!(
+                "{} and {} cannot have their containment checked",
+                a.describe(),
+                b.describe()
+            )
+            .into(),
+        ));
+    }
+
+    Ok(contains_inner(a, b))
+}
+
+/// jq's `indices(i)`.
+fn indices(input: &Value, i: &Value) -> Result<Value, JQErr> {
+    let found = match (input, i) {
+        (Value::Null, _) => return Ok(Value::Null),
+        (Value::String(haystack), Value::String(needle)) => {
+            let haystack = haystack.chars().collect::<Vec<_>>();
+            let needle = needle.chars().collect::<Vec<_>>();
+            if needle.is_empty() {
+                Vec::new()
+            } else {
+                haystack
+                    .windows(needle.len())
+                    .enumerate()
+                    .filter(|(_, window)| *window == needle.as_slice())
+                    .map(|(index, _)| index)
+                    .collect()
+            }
+        }
+        (Value::Array(haystack), Value::Array(needle)) => subarray_indices(haystack, needle),
+        (Value::Array(haystack), needle) => {
+            subarray_indices(haystack, std::slice::from_ref(needle))
+        }
+        (input, i) => {
+            return Err(JQErr::StreamOperationFailed(
+                format!("Cannot index {} with {}", input.type_name(), i.type_name()).into(),
+            ))
+        }
+    };
+
+    Ok(Value::Array(
+        found
+            .into_iter()
+            .map(|index| Value::ParsedNumber(index as f64))
+            .collect(),
+    ))
+}
+
+fn subarray_indices(haystack: &[Value], needle: &[Value]) -> Vec<usize> {
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    haystack
+        .windows(needle.len())
+        .enumerate()
+        .filter(|(_, window)| *window == needle)
+        .map(|(index, _)| index)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_support::values, JQErr, SanitizedJQStream};
+
+    #[test]
+    fn flattens_arrays() {
+        assert_eq!(
+            values("[1, [2, [3]]]").json_flatten().to_string().unwrap(),
+            "[1,2,3]\n"
+        );
+        assert_eq!(
+            values("[1, [2, [3]]]")
+                .json_flatten_depth(1)
+                .to_string()
+                .unwrap(),
+            "[1,2,[3]]\n"
+        );
+    }
+
+    #[test]
+    fn reverses_arrays_and_strings() {
+        assert_eq!(
+            values(r#"[1, 2, 3] "abc" null"#)
+                .reverse()
+                .to_string()
+                .unwrap(),
+            "[3,2,1]\n\"cba\"\n[]\n"
+        );
+    }
+
+    #[test]
+    fn finds_indices_of_substrings_and_subarrays() {
+        assert_eq!(
+            values(r#""a,b, cd, efg""#)
+                .indices(values(r#"", ""#))
+                .to_string()
+                .unwrap(),
+            "[3,7]\n"
+        );
+        assert_eq!(
+            values("[0, 1, 2, 1, 3, 1, 2]")
+                .indices(values("1 [1, 2]"))
+                .to_string()
+                .unwrap(),
+            "[1,3,5]\n[1,5]\n"
+        );
+    }
+
+    #[test]
+    fn checks_containment_in_both_directions() {
+        assert_eq!(
+            values(r#"{"a": [1, 2], "b": "xyz"}"#)
+                .contains(values(r#"{"a": [1], "b": "y"} {"c": 1}"#))
+                .to_string()
+                .unwrap(),
+            "true\nfalse\n"
+        );
+        assert_eq!(
+            values(r#""bar""#)
+                .inside(values(r#""foobar""#))
+                .to_string()
+                .unwrap(),
+            "true\n"
+        );
+        assert!(matches!(
+            values("1").contains(values(r#""a""#)).to_string(),
+            Err(JQErr::StreamOperationFailed(msg))
+                if &*msg == "number (1) and string (\"a\") cannot have their containment checked"
+        ));
+    }
+
+    #[test]
+    fn takes_absolute_values_and_wraps_in_arrays() {
+        assert_eq!(
+            values("-5 2.5 -0.5").abs().to_string().unwrap(),
+            "5\n2.5\n0.5\n"
+        );
+        assert!(matches!(
+            values(r#""a""#).abs().to_string(),
+            Err(JQErr::StreamOperationFailed(msg)) if &*msg == "string (\"a\") has no absolute value"
+        ));
+        assert_eq!(
+            values("1 [2] null").toarray().to_string().unwrap(),
+            "[1]\n[2]\n[null]\n"
+        );
+    }
+
+    #[test]
+    fn reports_constants() {
+        assert_eq!(
+            values("1 2").have_literal_numbers().to_string().unwrap(),
+            "true\ntrue\n"
+        );
+        assert_eq!(
+            values("1").have_decnum().to_string().unwrap(),
+            if cfg!(feature = "decimal") {
+                "true\n"
+            } else {
+                "false\n"
+            }
+        );
+    }
+}
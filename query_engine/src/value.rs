@@ -0,0 +1,413 @@
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::Entry, HashMap},
+    rc::Rc,
+};
+
+use crate::{decimal, JQErr, JQStream, SanitizedJQStream, Token};
+
+/// A fully materialized JSON value. Most operations in this crate work
+/// directly on token streams, but some (error messages, sorting, etc.)
+/// need to look at an entire value at once.
+#[derive(Clone, Debug)]
+pub(crate) enum Value {
+    Null,
+    True,
+    False,
+    Number(Rc<str>),
+    ParsedNumber(f64),
+    String(Rc<str>),
+    Array(Vec<Value>),
+    Object(Vec<(Rc<str>, Value)>),
+}
+
+impl Value {
+    /// Reads the next top-level value out of a sanitized stream.
+    pub(crate) fn next_from<Stream>(stream: &mut Stream) -> Option<Result<Value, JQErr>>
+    where
+        Stream: Iterator<Item = crate::Item>,
+    {
+        match stream.next()? {
+            Err(err) => Some(Err(err)),
+            Ok(token) => Some(Self::parse(token, stream)),
+        }
+    }
+
+    /// Reads every top-level value out of a stream.
+    pub(crate) fn all_from<Stream>(stream: Stream) -> Result<Vec<Value>, JQErr>
+    where
+        Stream: JQStream,
+    {
+        let mut stream = stream.sanitize();
+        let mut values = Vec::new();
+        while let Some(value) = Self::next_from(&mut stream) {
+            values.push(value?);
+        }
+        Ok(values)
+    }
+
+    /// Reads the next member out of the array or object whose start
+    /// token has already been pulled out of the stream. For objects,
+    /// only the value of each key value pair is returned. Returns
+    /// [`None`] once the end of the container has been consumed.
+    pub(crate) fn next_member<Stream>(
+        stream: &mut Stream,
+        in_object: bool,
+    ) -> Option<Result<Value, JQErr>>
+    where
+        Stream: Iterator<Item = crate::Item>,
+    {
+        let mut token = match stream.next() {
+            None => return Some(Err(JQErr::UnexpectedEOF)),
+            Some(Err(err)) => return Some(Err(err)),
+            Some(Ok(token)) => token,
+        };
+
+        if matches!(token, Token::Comma) {
+            token = match stream.next() {
+                None => return Some(Err(JQErr::UnexpectedEOF)),
+                Some(Err(err)) => return Some(Err(err)),
+                Some(Ok(token)) => token,
+            };
+        }
+
+        match token {
+            Token::ArrayEnd | Token::ObjectEnd => None,
+            Token::String(_) if in_object => {
+                match stream.next() {
+                    None => return Some(Err(JQErr::UnexpectedEOF)),
+                    Some(Err(err)) => return Some(Err(err)),
+                    Some(Ok(Token::Colon)) => {}
+                    Some(Ok(_)) => return Some(Err(JQErr::InvalidStream)),
+                }
+                Self::next_from(stream).or(Some(Err(JQErr::UnexpectedEOF)))
+            }
+            token => Some(Self::parse(token, stream)),
+        }
+    }
+
+    /// Reads the rest of a value whose first token has already been
+    /// pulled out of the stream.
+    pub(crate) fn parse<Stream>(first: Token, stream: &mut Stream) -> Result<Value, JQErr>
+    where
+        Stream: Iterator<Item = crate::Item>,
+    {
+        match first {
+            Token::Null => Ok(Value::Null),
+            Token::True => Ok(Value::True),
+            Token::False => Ok(Value::False),
+            Token::Number(num) => Ok(Value::Number(num)),
+            Token::ParsedNumber(num) => Ok(Value::ParsedNumber(num)),
+            Token::String(str) => Ok(Value::String(str)),
+            Token::ArrayStart => {
+                let mut items = Vec::new();
+                loop {
+                    match stream.next() {
+                        None => return Err(JQErr::UnexpectedEOF),
+                        Some(Err(err)) => return Err(err),
+                        Some(Ok(Token::ArrayEnd)) => return Ok(Value::Array(items)),
+                        Some(Ok(Token::Comma)) => {}
+                        Some(Ok(token)) if token.is_value_start() => {
+                            items.push(Self::parse(token, stream)?)
+                        }
+                        Some(Ok(_)) => return Err(JQErr::InvalidStream),
+                    }
+                }
+            }
+            Token::ObjectStart => {
+                let mut entries = ObjectEntries::default();
+                loop {
+                    let key = match stream.next() {
+                        None => return Err(JQErr::UnexpectedEOF),
+                        Some(Err(err)) => return Err(err),
+                        Some(Ok(Token::ObjectEnd)) => return Ok(entries.into_value()),
+                        Some(Ok(Token::Comma)) => continue,
+                        Some(Ok(Token::String(key))) => key,
+                        Some(Ok(_)) => return Err(JQErr::InvalidStream),
+                    };
+
+                    match stream.next() {
+                        None => return Err(JQErr::UnexpectedEOF),
+                        Some(Err(err)) => return Err(err),
+                        Some(Ok(Token::Colon)) => {}
+                        Some(Ok(_)) => return Err(JQErr::InvalidStream),
+                    }
+
+                    match stream.next() {
+                        None => return Err(JQErr::UnexpectedEOF),
+                        Some(Err(err)) => return Err(err),
+                        Some(Ok(token)) if token.is_value_start() => {
+                            entries.insert(key, Self::parse(token, stream)?)
+                        }
+                        Some(Ok(_)) => return Err(JQErr::InvalidStream),
+                    }
+                }
+            }
+            Token::ObjectEnd | Token::ArrayEnd | Token::Colon | Token::Comma => {
+                Err(JQErr::InvalidStream)
+            }
+        }
+    }
+
+    /// Appends the tokens which make up this value to `out`.
+    pub(crate) fn push_tokens<Out>(self, out: &mut Out)
+    where
+        Out: Extend<Token>,
+    {
+        match self {
+            Value::Null => out.extend([Token::Null]),
+            Value::True => out.extend([Token::True]),
+            Value::False => out.extend([Token::False]),
+            Value::Number(num) => out.extend([Token::Number(num)]),
+            Value::ParsedNumber(num) => out.extend([Token::ParsedNumber(num)]),
+            Value::String(str) => out.extend([Token::String(str)]),
+            Value::Array(items) => {
+                out.extend([Token::ArrayStart]);
+                for (i, item) in items.into_iter().enumerate() {
+                    if i != 0 {
+                        out.extend([Token::Comma]);
+                    }
+                    item.push_tokens(out);
+                }
+                out.extend([Token::ArrayEnd]);
+            }
+            Value::Object(entries) => {
+                out.extend([Token::ObjectStart]);
+                for (i, (key, value)) in entries.into_iter().enumerate() {
+                    if i != 0 {
+                        out.extend([Token::Comma]);
+                    }
+                    out.extend([Token::String(key), Token::Colon]);
+                    value.push_tokens(out);
+                }
+                out.extend([Token::ObjectEnd]);
+            }
+        }
+    }
+
+    /// Converts this value into a stream of its tokens.
+    pub(crate) fn into_stream(self) -> ValueTokens {
+        let mut tokens = Vec::new();
+        self.push_tokens(&mut tokens);
+        ValueTokens::new(tokens)
+    }
+
+    /// Gets the numeric value of this value, if it is a number.
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(num) => num.parse::<f64>().ok(),
+            Value::ParsedNumber(num) => Some(*num),
+            _ => None,
+        }
+    }
+
+    /// jq considers everything except `false` and `null` to be true.
+    pub(crate) fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Null | Value::False)
+    }
+
+    /// Gets the entries of an object sorted by key.
+    fn sorted_entries(entries: &[(Rc<str>, Value)]) -> Vec<&(Rc<str>, Value)> {
+        let mut sorted = entries.iter().collect::<Vec<_>>();
+        sorted.sort_by(|a, b| a.0.cmp(&b.0));
+        sorted
+    }
+
+    /// Whether jq considers both values to be of the same kind. This is
+    /// the same as comparing types, except that `true` and `false` are
+    /// different kinds.
+    pub(crate) fn same_kind(&self, other: &Value) -> bool {
+        self.type_order() == other.type_order()
+    }
+
+    /// The position of this value's type in jq's sort order.
+    fn type_order(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::False => 1,
+            Value::True => 2,
+            Value::Number(_) | Value::ParsedNumber(_) => 3,
+            Value::String(_) => 4,
+            Value::Array(_) => 5,
+            Value::Object(_) => 6,
+        }
+    }
+
+    /// The name jq uses for this value's type.
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::True | Value::False => "boolean",
+            Value::Number(_) | Value::ParsedNumber(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        }
+    }
+
+    /// Serializes this value as compact JSON, without a trailing newline.
+    pub(crate) fn to_json(&self) -> Result<String, JQErr> {
+        let mut dumped = self
+            .clone()
+            .into_stream()
+            .to_chars_compact()
+            .collect::<Result<String, JQErr>>()?;
+        if dumped.ends_with('\n') {
+            dumped.pop();
+        }
+        Ok(dumped)
+    }
+
+    /// Formats this value the way jq does in error messages, i.e.
+    /// `array ([1,2,3])`, truncating long values.
+    pub(crate) fn describe(&self) -> String {
+        const MAX_LEN: usize = 11;
+
+        let mut dumped = self.to_json().unwrap_or_default();
+
+        if dumped.len() > MAX_LEN {
+            let mut end = MAX_LEN;
+            while !dumped.is_char_boundary(end) {
+                end -= 1;
+            }
+            dumped.truncate(end);
+            dumped.push_str("...");
+        }
+
+        format!("{} ({})", self.type_name(), dumped)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        if value {
+            Value::True
+        } else {
+            Value::False
+        }
+    }
+}
+
+/// Values are ordered the same way jq orders them: `null`, `false`,
+/// `true`, numbers, strings, arrays and then objects. Arrays compare
+/// element by element and objects compare their sorted keys first, then
+/// their values in key order.
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (
+                Value::Number(_) | Value::ParsedNumber(_),
+                Value::Number(_) | Value::ParsedNumber(_),
+            ) => {
+                if let Some(ordering) = decimal::cmp(self, other) {
+                    return ordering;
+                }
+
+                let left = self.as_f64().unwrap_or(f64::NAN);
+                let right = other.as_f64().unwrap_or(f64::NAN);
+                // jq sorts nan below every other number.
+                match (left.is_nan(), right.is_nan()) {
+                    (true, true) => Ordering::Equal,
+                    (true, false) => Ordering::Less,
+                    (false, true) => Ordering::Greater,
+                    (false, false) => left.partial_cmp(&right).unwrap_or(Ordering::Equal),
+                }
+            }
+            (Value::String(left), Value::String(right)) => left.cmp(right),
+            (Value::Array(left), Value::Array(right)) => left.cmp(right),
+            (Value::Object(left), Value::Object(right)) => {
+                let left = Self::sorted_entries(left);
+                let right = Self::sorted_entries(right);
+                left.iter()
+                    .map(|(key, _)| key)
+                    .cmp(right.iter().map(|(key, _)| key))
+                    .then_with(|| {
+                        left.iter()
+                            .map(|(_, value)| value)
+                            .cmp(right.iter().map(|(_, value)| value))
+                    })
+            }
+            _ => self.type_order().cmp(&other.type_order()),
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+/// Collects the entries of an object as it's built. Like jq, a repeated
+/// key keeps the position of its first entry and the value of its last.
+#[derive(Default)]
+pub(crate) struct ObjectEntries {
+    entries: Vec<(Rc<str>, Value)>,
+    positions: HashMap<Rc<str>, usize>,
+}
+
+impl ObjectEntries {
+    pub(crate) fn insert(&mut self, key: Rc<str>, value: Value) {
+        match self.positions.entry(key) {
+            Entry::Occupied(position) => self.entries[*position.get()].1 = value,
+            Entry::Vacant(position) => {
+                let key = position.key().clone();
+                position.insert(self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        let position = *self.positions.get(key)?;
+        Some(&mut self.entries[position].1)
+    }
+
+    pub(crate) fn into_value(self) -> Value {
+        Value::Object(self.entries)
+    }
+}
+
+impl FromIterator<(Rc<str>, Value)> for ObjectEntries {
+    fn from_iter<Entries>(entries: Entries) -> Self
+    where
+        Entries: IntoIterator<Item = (Rc<str>, Value)>,
+    {
+        let mut object = Self::default();
+        for (key, value) in entries {
+            object.insert(key, value);
+        }
+        object
+    }
+}
+
+/// A [`SanitizedJQStream`] over the tokens of a single materialized value.
+pub struct ValueTokens {
+    tokens: std::vec::IntoIter<Token>,
+}
+
+impl ValueTokens {
+    pub(crate) fn new(tokens: Vec<Token>) -> Self {
+        Self {
+            tokens: tokens.into_iter(),
+        }
+    }
+}
+
+impl Iterator for ValueTokens {
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.tokens.next().map(Ok)
+    }
+}
+
+impl SanitizedJQStream for ValueTokens {}
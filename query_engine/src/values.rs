@@ -67,13 +67,12 @@ where
                                 self.finished = true;
                                 return Some(Err(err));
                             }
-                            Some(Ok(token_kind)) => {
-                                if token_kind.is_value_start() {
-                                    return Some(Ok(token_kind));
-                                } else {
-                                    self.finished = true;
-                                    return Some(Err(JQErr::InvalidStream));
-                                }
+                            Some(Ok(token_kind)) if token_kind.is_value_start() => {
+                                return Some(Ok(token_kind));
+                            }
+                            Some(Ok(_)) => {
+                                self.finished = true;
+                                return Some(Err(JQErr::InvalidStream));
                             }
                         },
                         Token::ObjectStart => {
@@ -304,13 +303,12 @@ where
                                 self.finished = true;
                                 return Some(Err(err));
                             }
-                            Some(Ok(token_kind)) => {
-                                if token_kind.is_value_start() {
-                                    return Some(Ok(token_kind));
-                                } else {
-                                    self.finished = true;
-                                    return Some(Err(JQErr::InvalidStream));
-                                }
+                            Some(Ok(token_kind)) if token_kind.is_value_start() => {
+                                return Some(Ok(token_kind));
+                            }
+                            Some(Ok(_)) => {
+                                self.finished = true;
+                                return Some(Err(JQErr::InvalidStream));
                             }
                             None => {
                                 self.finished = true;
@@ -0,0 +1,647 @@
+use std::collections::{HashMap, VecDeque};
+
+use yaml_rust2::{
+    parser::{Event, Parser, Tag},
+    scanner::{Marker, TScalarStyle},
+};
+
+use crate::{
+    escape,
+    number_format::format_number,
+    raw::{decimal_token, LimitCheck},
+    value::Value,
+    JQErr, JQStream, Location, ParseLimits, Sanitized, SanitizedJQStream, StringEscapes, Token,
+};
+
+/// How the YAML reader handles mapping keys which aren't strings, such as
+/// `1: one` or `[a, b]: c`, since JSON only allows string keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum YamlKeys {
+    /// Fails with [`JQErr::InvalidYaml`].
+    Reject,
+    /// Uses the text of a scalar key, or the compact JSON of a sequence or
+    /// mapping key, like `yq` does.
+    Stringify,
+}
+
+enum Frame {
+    Sequence {
+        first: bool,
+    },
+    /// `at_key` is whether the next node is a key rather than a value.
+    Mapping {
+        first: bool,
+        at_key: bool,
+    },
+}
+
+/// Reads YAML into a stream of JSON tokens, producing every document in
+/// the input as a separate value. Plain scalars are resolved with the
+/// YAML 1.2 core schema, so `~`, `true`, `0x1F` and `.inf` become null, a
+/// boolean and numbers, while quoted scalars are always strings. Aliases
+/// are replaced with a copy of the node they refer to.
+pub struct YamlTokens<Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    finished: bool,
+    parser: Parser<Chars>,
+    keys: YamlKeys,
+    frames: Vec<Frame>,
+    /// The tokens of every anchored node, to be replayed by its aliases.
+    anchors: HashMap<usize, Vec<Token>>,
+    /// The anchored collections being read, with the depth they started at.
+    recording: Vec<(usize, usize, Vec<Token>)>,
+    /// The tokens of a collection being used as a mapping key, with the
+    /// depth it started at.
+    key: Option<(usize, Vec<Token>)>,
+    buf: VecDeque<Token>,
+    limits: LimitCheck,
+    /// Where the event which produced the tokens in `buf` is.
+    location: Location,
+}
+
+impl<Chars> YamlTokens<Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    pub(crate) fn new(chars: Chars, keys: YamlKeys) -> Self {
+        Self {
+            finished: false,
+            parser: Parser::new(chars),
+            keys,
+            frames: Vec::new(),
+            anchors: HashMap::new(),
+            recording: Vec::new(),
+            key: None,
+            buf: VecDeque::new(),
+            limits: LimitCheck::default(),
+            location: Location::new(0, 0),
+        }
+    }
+
+    /// Sets limits on the input, such as how many tokens it may have.
+    /// They're checked against the tokens as they're produced, so aliases
+    /// count every token of the node they copy.
+    pub fn with_limits(mut self, limits: ParseLimits) -> Self {
+        self.limits = LimitCheck::new(limits);
+        self
+    }
+
+    fn emit(&mut self, token: Token) {
+        let key_depth = self.key.as_ref().map(|(depth, _)| *depth);
+        for (_, depth, tokens) in &mut self.recording {
+            if key_depth.is_none_or(|key_depth| *depth >= key_depth) {
+                tokens.push(token.clone());
+            }
+        }
+
+        match &mut self.key {
+            Some((_, tokens)) => tokens.push(token),
+            None => self.buf.push_back(token),
+        }
+    }
+
+    /// Emits the separator which comes before the next node, returning
+    /// whether that node is a mapping key.
+    fn begin_node(&mut self) -> bool {
+        let (comma, is_key) = match self.frames.last_mut() {
+            None => (false, false),
+            Some(Frame::Sequence { first }) => (!std::mem::take(first), false),
+            Some(Frame::Mapping { first, at_key }) => {
+                let is_key = std::mem::replace(at_key, !*at_key);
+                if is_key {
+                    (!std::mem::take(first), true)
+                } else {
+                    (false, false)
+                }
+            }
+        };
+
+        if comma {
+            self.emit(Token::Comma);
+        }
+        is_key
+    }
+
+    /// Emits a mapping key which was read as `tokens`, converting it to a
+    /// string if it isn't one already.
+    fn emit_key(
+        &mut self,
+        mut tokens: Vec<Token>,
+        text: Option<String>,
+        mark: Marker,
+    ) -> Result<(), JQErr> {
+        let key = match (tokens.len(), tokens.pop()) {
+            (1, Some(Token::String(key))) => key,
+            (_, last) => {
+                if self.keys == YamlKeys::Reject {
+                    return Err(non_string_key(mark));
+                }
+
+                match text {
+                    Some(text) => text.into(),
+                    None => {
+                        tokens.extend(last);
+                        let mut tokens = tokens.into_iter().map(Ok);
+                        match Value::next_from(&mut tokens) {
+                            Some(value) => value?.to_json()?.into(),
+                            None => return Err(JQErr::InvalidStream),
+                        }
+                    }
+                }
+            }
+        };
+
+        self.emit(Token::String(key));
+        self.emit(Token::Colon);
+        Ok(())
+    }
+
+    fn begin_collection(
+        &mut self,
+        anchor: usize,
+        start: Token,
+        frame: Frame,
+        mark: Marker,
+    ) -> Result<(), JQErr> {
+        if self.begin_node() && self.key.is_none() {
+            if self.keys == YamlKeys::Reject {
+                return Err(non_string_key(mark));
+            }
+            self.key = Some((self.frames.len(), Vec::new()));
+        }
+
+        if anchor != 0 {
+            self.recording.push((anchor, self.frames.len(), Vec::new()));
+        }
+
+        self.emit(start);
+        self.frames.push(frame);
+        Ok(())
+    }
+
+    fn end_collection(&mut self, end: Token, mark: Marker) -> Result<(), JQErr> {
+        self.frames.pop();
+        self.emit(end);
+
+        let depth = self.frames.len();
+        if matches!(self.recording.last(), Some((_, start, _)) if *start == depth) {
+            if let Some((anchor, _, tokens)) = self.recording.pop() {
+                self.anchors.insert(anchor, tokens);
+            }
+        }
+
+        if matches!(self.key, Some((start, _)) if start == depth) {
+            if let Some((_, tokens)) = self.key.take() {
+                self.emit_key(tokens, None, mark)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handles the next parser event, returning `false` once the input is
+    /// exhausted.
+    fn handle(&mut self, event: Event, mark: Marker) -> Result<bool, JQErr> {
+        match event {
+            Event::StreamEnd => return Ok(false),
+            Event::Nothing | Event::StreamStart | Event::DocumentEnd => {}
+            Event::DocumentStart => self.anchors.clear(),
+            Event::Scalar(text, style, anchor, tag) => {
+                let token = resolve(&text, style, tag.as_ref(), mark)?;
+                if anchor != 0 {
+                    self.anchors.insert(anchor, vec![token.clone()]);
+                }
+
+                if self.begin_node() {
+                    self.emit_key(vec![token], Some(text), mark)?;
+                } else {
+                    self.emit(token);
+                }
+            }
+            Event::Alias(anchor) => {
+                let tokens = match self.anchors.get(&anchor) {
+                    Some(tokens) => tokens.clone(),
+                    None => {
+                        return Err(JQErr::InvalidYaml {
+                            location: location(mark),
+                            msg: "alias refers to an unknown anchor".into(),
+                        })
+                    }
+                };
+
+                if self.begin_node() {
+                    self.emit_key(tokens, None, mark)?;
+                } else {
+                    for token in tokens {
+                        self.emit(token);
+                    }
+                }
+            }
+            Event::SequenceStart(anchor, _) => self.begin_collection(
+                anchor,
+                Token::ArrayStart,
+                Frame::Sequence { first: true },
+                mark,
+            )?,
+            Event::SequenceEnd => self.end_collection(Token::ArrayEnd, mark)?,
+            Event::MappingStart(anchor, _) => self.begin_collection(
+                anchor,
+                Token::ObjectStart,
+                Frame::Mapping {
+                    first: true,
+                    at_key: true,
+                },
+                mark,
+            )?,
+            Event::MappingEnd => self.end_collection(Token::ObjectEnd, mark)?,
+        }
+
+        Ok(true)
+    }
+}
+
+impl<Chars> Iterator for YamlTokens<Chars>
+where
+    Chars: Iterator<Item = char>,
+{
+    type Item = crate::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(token) = self.buf.pop_front() {
+                let location = self.location.clone();
+                if let Err(err) = self.limits.check(&token, || location) {
+                    self.finished = true;
+                    self.buf.clear();
+                    return Some(Err(err));
+                }
+                return Some(Ok(token));
+            }
+
+            if self.finished {
+                return None;
+            }
+
+            let result = match self.parser.next_token() {
+                Ok((event, mark)) => {
+                    self.location = location(mark);
+                    self.handle(event, mark)
+                }
+                Err(err) => Err(JQErr::InvalidYaml {
+                    location: location(*err.marker()),
+                    msg: err.info().into(),
+                }),
+            };
+
+            match result {
+                Ok(true) => {}
+                Ok(false) => self.finished = true,
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+impl<Chars> SanitizedJQStream for YamlTokens<Chars> where Chars: Iterator<Item = char> {}
+
+fn location(mark: Marker) -> Location {
+    Location::new(mark.line().saturating_sub(1), mark.col())
+}
+
+fn non_string_key(mark: Marker) -> JQErr {
+    JQErr::InvalidYaml {
+        location: location(mark),
+        msg: "mapping keys must be strings".into(),
+    }
+}
+
+/// Resolves a scalar to a JSON token, using its tag if it has a standard
+/// one and the YAML 1.2 core schema otherwise.
+fn resolve(
+    text: &str,
+    style: TScalarStyle,
+    tag: Option<&Tag>,
+    mark: Marker,
+) -> Result<Token, JQErr> {
+    let suffix = match tag {
+        Some(tag) if tag.handle == "tag:yaml.org,2002:" => Some(tag.suffix.as_str()),
+        _ => None,
+    };
+
+    if suffix == Some("str") || (suffix.is_none() && style != TScalarStyle::Plain) {
+        return Ok(Token::String(text.into()));
+    }
+
+    let token = resolve_plain(text);
+    let matches_tag = match (suffix, &token) {
+        (None, _) => true,
+        (Some("null"), Token::Null) => true,
+        (Some("bool"), Token::True | Token::False) => true,
+        (Some("int" | "float"), Token::Number(_) | Token::ParsedNumber(_)) => true,
+        (Some("null" | "bool" | "int" | "float"), _) => false,
+        // Other tags, such as `!!binary` or application specific ones,
+        // have no JSON equivalent, so they're ignored.
+        (Some(_), _) => true,
+    };
+
+    if matches_tag {
+        Ok(token)
+    } else {
+        Err(JQErr::InvalidYaml {
+            location: location(mark),
+            msg: format!("{text} is not a valid !!{}", suffix.unwrap_or_default()).into(),
+        })
+    }
+}
+
+/// Resolves a plain scalar with the YAML 1.2 core schema.
+fn resolve_plain(text: &str) -> Token {
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => return Token::Null,
+        "true" | "True" | "TRUE" => return Token::True,
+        "false" | "False" | "FALSE" => return Token::False,
+        ".nan" | ".NaN" | ".NAN" => return Token::ParsedNumber(f64::NAN),
+        ".inf" | ".Inf" | ".INF" | "+.inf" | "+.Inf" | "+.INF" => {
+            return Token::ParsedNumber(f64::INFINITY)
+        }
+        "-.inf" | "-.Inf" | "-.INF" => return Token::ParsedNumber(f64::NEG_INFINITY),
+        _ => {}
+    }
+
+    let radix = match text.get(..2) {
+        Some("0o") => Some(8),
+        Some("0x") => Some(16),
+        _ => None,
+    };
+
+    let number = match radix {
+        Some(radix) => u64::from_str_radix(&text[2..], radix)
+            .ok()
+            .filter(|_| !text[2..].starts_with('+'))
+            .map(|value| Token::Number(value.to_string().into())),
+        None => decimal_token(text),
+    };
+
+    number.unwrap_or_else(|| Token::String(text.into()))
+}
+
+/// Writes values as YAML, using block style for every non-empty
+/// collection. Each value after the first is preceded by a `---` document
+/// marker.
+pub struct YamlChars<Stream>
+where
+    Stream: JQStream,
+{
+    finished: bool,
+    stream: Sanitized<Stream>,
+    started: bool,
+    buf: VecDeque<char>,
+}
+
+impl<Stream> YamlChars<Stream>
+where
+    Stream: JQStream,
+{
+    pub(crate) fn new(stream: Stream) -> Self {
+        Self {
+            finished: false,
+            stream: stream.sanitize(),
+            started: false,
+            buf: VecDeque::new(),
+        }
+    }
+}
+
+impl<Stream> Iterator for YamlChars<Stream>
+where
+    Stream: JQStream,
+{
+    type Item = Result<char, JQErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(ch) = self.buf.pop_front() {
+            return Some(Ok(ch));
+        }
+
+        if self.finished {
+            return None;
+        }
+
+        match Value::next_from(&mut self.stream)? {
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+            Ok(value) => {
+                if std::mem::replace(&mut self.started, true) {
+                    self.buf.extend("---\n".chars());
+                }
+                write_node(&value, 0, &mut self.buf);
+                self.buf.push_back('\n');
+                self.buf.pop_front().map(Ok)
+            }
+        }
+    }
+}
+
+fn is_block(value: &Value) -> bool {
+    match value {
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(entries) => !entries.is_empty(),
+        _ => false,
+    }
+}
+
+/// Writes a value which starts on the current line, indenting any
+/// following lines by `indent` spaces.
+fn write_node(value: &Value, indent: usize, out: &mut VecDeque<char>) {
+    let newline = |out: &mut VecDeque<char>| {
+        out.push_back('\n');
+        out.extend(std::iter::repeat_n(' ', indent));
+    };
+
+    match value {
+        Value::Array(items) if !items.is_empty() => {
+            for (i, item) in items.iter().enumerate() {
+                if i != 0 {
+                    newline(out);
+                }
+                out.extend("- ".chars());
+                write_node(item, indent + 2, out);
+            }
+        }
+        Value::Object(entries) if !entries.is_empty() => {
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i != 0 {
+                    newline(out);
+                }
+                write_string(key, out);
+                out.push_back(':');
+                if is_block(value) {
+                    out.push_back('\n');
+                    out.extend(std::iter::repeat_n(' ', indent + 2));
+                    write_node(value, indent + 2, out);
+                } else {
+                    out.push_back(' ');
+                    write_node(value, indent, out);
+                }
+            }
+        }
+        Value::Array(_) => out.extend("[]".chars()),
+        Value::Object(_) => out.extend("{}".chars()),
+        Value::String(str) => write_string(str, out),
+        Value::ParsedNumber(num) if num.is_nan() => out.extend(".nan".chars()),
+        Value::ParsedNumber(num) if num.is_infinite() => {
+            out.extend(if *num > 0.0 { ".inf" } else { "-.inf" }.chars())
+        }
+        Value::ParsedNumber(num) => out.extend(format_number(*num).chars()),
+        Value::Number(num) => out.extend(num.chars()),
+        Value::True => out.extend("true".chars()),
+        Value::False => out.extend("false".chars()),
+        Value::Null => out.extend("null".chars()),
+    }
+}
+
+/// Writes a string as a plain scalar if that would be read back as the
+/// same string, or double-quoted otherwise. JSON's escapes are all valid in
+/// YAML's double-quoted scalars.
+fn write_string(str: &str, out: &mut VecDeque<char>) {
+    let plain = matches!(resolve_plain(str), Token::String(_))
+        && !str.starts_with(|ch: char| ch.is_whitespace() || "-?:,[]{}#&*!|>'\"%@`".contains(ch))
+        && !str.ends_with(|ch: char| ch.is_whitespace() || ch == ':')
+        && !str.starts_with("...")
+        && !str.contains(": ")
+        && !str.contains(" #")
+        && !str.contains(char::is_control);
+
+    if plain {
+        out.extend(str.chars());
+    } else {
+        escape::write_string(str, StringEscapes::default(), out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CharStream, JQErr, ParseLimits, SanitizedJQStream, YamlKeys};
+
+    fn read(yaml: &str, limits: ParseLimits) -> Result<String, JQErr> {
+        yaml.chars()
+            .into_yaml_tokens(YamlKeys::Reject)
+            .with_limits(limits)
+            .to_string()
+    }
+
+    #[test]
+    fn limits_nesting_strings_and_numbers() {
+        let limits = ParseLimits::default()
+            .with_max_depth(2)
+            .with_max_string_len(3)
+            .with_max_number_len(3);
+        assert_eq!(
+            read("a: [abc, 123]", limits).unwrap(),
+            "{\"a\":[\"abc\",123]}\n"
+        );
+        assert!(matches!(
+            read("a: [[1]]", limits),
+            Err(JQErr::DepthLimitExceeded(location)) if location.col() == 4
+        ));
+        assert!(matches!(
+            read("a: abcd", limits),
+            Err(JQErr::StringLimitExceeded(location)) if location.col() == 3
+        ));
+        assert!(matches!(
+            read("- 1234", limits),
+            Err(JQErr::NumberLimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn limits_the_tokens_that_aliases_copy() {
+        let yaml = "a: &a [1, 1, 1, 1]\nb: &b [*a, *a, *a, *a]\nc: [*b, *b, *b, *b]\n";
+        assert!(read(yaml, ParseLimits::default()).is_ok());
+        assert!(matches!(
+            read(yaml, ParseLimits::default().with_max_tokens(100)),
+            Err(JQErr::TokenLimitExceeded(location)) if location.line() == 2
+        ));
+    }
+
+    fn yaml(yaml: &str, keys: YamlKeys) -> Result<String, JQErr> {
+        yaml.chars().into_yaml_tokens(keys).to_string()
+    }
+
+    fn to_yaml(json: &str) -> String {
+        json.chars()
+            .into_json_tokens()
+            .to_chars_yaml()
+            .collect::<Result<String, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn resolves_scalars_with_the_core_schema() {
+        assert_eq!(
+            yaml(
+                "[~, null, true, False, 12, 0x1F, 0o17, 1.5e3, .inf, -.inf, .nan, abc, '1', \"true\"]",
+                YamlKeys::Reject
+            )
+            .unwrap(),
+            "[null,null,true,false,12,31,15,1.5e3,1.7976931348623157e+308,-1.7976931348623157e+308,null,\"abc\",\"1\",\"true\"]\n"
+        );
+        assert!(matches!(
+            yaml("!!int abc", YamlKeys::Reject),
+            Err(JQErr::InvalidYaml { msg, .. }) if &*msg == "abc is not a valid !!int"
+        ));
+    }
+
+    #[test]
+    fn reads_documents_and_aliases() {
+        assert_eq!(
+            yaml(
+                "a: &x\n  - 1\n  - b: 2\nc: *x\n---\n- plain text\n",
+                YamlKeys::Reject
+            )
+            .unwrap(),
+            "{\"a\":[1,{\"b\":2}],\"c\":[1,{\"b\":2}]}\n[\"plain text\"]\n"
+        );
+    }
+
+    #[test]
+    fn rejects_or_stringifies_keys_which_are_not_strings() {
+        assert!(matches!(
+            yaml("1: one", YamlKeys::Reject),
+            Err(JQErr::InvalidYaml { .. })
+        ));
+        assert_eq!(
+            yaml("1: one\n[1, 2]: c\nnull: d", YamlKeys::Stringify).unwrap(),
+            "{\"1\":\"one\",\"[1,2]\":\"c\",\"null\":\"d\"}\n"
+        );
+    }
+
+    #[test]
+    fn writes_block_style_documents() {
+        assert_eq!(
+            to_yaml(r#"{"a": [1, {"b": null, "c": []}], "d": {}} "e""#),
+            "a:\n  - 1\n  - b: null\n    c: []\nd: {}\n---\ne\n"
+        );
+    }
+
+    #[test]
+    fn round_trips_strings_which_need_quoting() {
+        let json = r##"["", "true", "1", "0x1F", "~", ".inf", "- a", "a: b", "a #b", " x", "x ", "...", ":", "a\nb", "\u0001", "#", "'", "plain", "é"]"##;
+        let expected = json.chars().into_json_tokens().to_string().unwrap();
+        assert_eq!(yaml(&to_yaml(json), YamlKeys::Reject).unwrap(), expected);
+    }
+
+    #[test]
+    fn round_trips_nested_values_and_numbers() {
+        let json = r#"{"a": [1, -2.5, 1e300, [], {}, [[1, 2], {"b": "c"}]], "d": {"e": {"f": false}}, "": null}"#;
+        let expected = json.chars().into_json_tokens().to_string().unwrap();
+        assert_eq!(yaml(&to_yaml(json), YamlKeys::Reject).unwrap(), expected);
+    }
+}